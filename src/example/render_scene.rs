@@ -0,0 +1,45 @@
+use std::env::args;
+use std::fs::{read, read_to_string};
+use std::path::Path;
+
+use image::RgbImage;
+use rayon::iter::{IntoParallelIterator, ParallelIterator};
+use raytracer::description::SceneDescription;
+use raytracer::render::Renderer;
+
+fn main() {
+    let args = args().collect::<Vec<String>>();
+    let scene_path = args.get(1).map(String::as_str).unwrap_or("data/demo.json");
+    let output_path = args.get(2).map(String::as_str).unwrap_or("render.png");
+
+    let text = read_to_string(scene_path)
+        .unwrap_or_else(|err| panic!("Failed to read scene {scene_path}: {err}"));
+    let base_dir = Path::new(scene_path).parent().unwrap_or(Path::new("."));
+
+    let loaded = SceneDescription::from_json(&text)
+        .and_then(|description| description.build(|path| read(base_dir.join(path))))
+        .unwrap_or_else(|err| panic!("Failed to load scene {scene_path}: {err}"));
+
+    let (scene, settings) = (&loaded.scene, &loaded.settings);
+    println!("Resolution: {}x{}", settings.width, settings.height);
+    println!("Points: {}", scene.geometry().vec_buf.points.len());
+    println!("Lights: {}", scene.lights().len());
+    println!("Materials: {}", scene.geometry().material_buf.materials.len());
+
+    let renderer = Renderer::new(scene, &loaded.camera, settings);
+
+    let to_color = |color: f32| (color.clamp(0.0, 1.0) * 255.0) as u8;
+    let pixels = (0..settings.width * settings.height)
+        .into_par_iter()
+        .flat_map_iter(|pix| {
+            let color = renderer.render_scene_pixel(pix);
+            [color.x, color.y, color.z].map(to_color)
+        })
+        .collect::<Vec<u8>>();
+
+    RgbImage::from_vec(settings.width as u32, settings.height as u32, pixels)
+        .expect("Frame buffer size mismatch")
+        .save(output_path)
+        .unwrap_or_else(|err| panic!("Failed to save {output_path}: {err}"));
+    println!("Saved {output_path}");
+}
@@ -0,0 +1,383 @@
+// Scattering functions in a local frame where the shading normal is +z, with both directions
+// pointing away from the surface. `eval` returns the BSDF times the cosine at `wi`.
+use core::f32::consts::PI;
+
+use crate::sampling::{cosine_hemisphere, Rng};
+use crate::utils::MediumIdx;
+use crate::vec3::{cross_product, dot_product, Vec3};
+
+#[derive(Debug, Clone, Copy)]
+pub struct BsdfSample {
+    pub wi: Vec3,
+    // BSDF times cosine over pdf
+    pub weight: Vec3,
+    // zero for delta lobes, which lights can not be sampled against
+    pub pdf: f32,
+}
+
+// GGX (Trowbridge-Reitz) normal distribution with the height correlated Smith shadowing.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Ggx {
+    alpha: f32,
+}
+
+impl Ggx {
+    // Perceptual roughness, squared into alpha.
+    pub fn new(roughness: f32) -> Ggx {
+        Ggx {
+            alpha: (roughness * roughness).max(1e-3),
+        }
+    }
+
+    pub fn d(&self, h: &Vec3) -> f32 {
+        if h.z <= 0.0 {
+            return 0.0;
+        }
+        let alpha2 = self.alpha * self.alpha;
+        let denominator = h.z * h.z * (alpha2 - 1.0) + 1.0;
+        alpha2 / (PI * denominator * denominator)
+    }
+
+    fn lambda(&self, v: &Vec3) -> f32 {
+        let cos2 = v.z * v.z;
+        if cos2 >= 1.0 {
+            return 0.0;
+        }
+        let tan2 = (1.0 - cos2) / cos2;
+        0.5 * (libm::sqrtf(1.0 + self.alpha * self.alpha * tan2) - 1.0)
+    }
+
+    pub fn g1(&self, v: &Vec3) -> f32 {
+        1.0 / (1.0 + self.lambda(v))
+    }
+
+    pub fn g(&self, wo: &Vec3, wi: &Vec3) -> f32 {
+        1.0 / (1.0 + self.lambda(wo) + self.lambda(wi))
+    }
+
+    // Microfacet normal from the distribution of normals visible from `wo` (Heitz 2018).
+    pub fn sample_visible(&self, wo: &Vec3, rng: &mut Rng) -> Vec3 {
+        let view = Vec3::new(self.alpha * wo.x, self.alpha * wo.y, wo.z).normalized();
+        let len2 = view.x * view.x + view.y * view.y;
+        let t1 = if len2 > 0.0 {
+            Vec3::new(-view.y, view.x, 0.0) * (1.0 / libm::sqrtf(len2))
+        } else {
+            Vec3::new(1.0, 0.0, 0.0)
+        };
+        let t2 = cross_product(&view, &t1);
+
+        let sample = rng.next_vec2();
+        let radius = libm::sqrtf(sample.x);
+        let (sin, cos) = libm::sincosf(2.0 * PI * sample.y);
+        let p1 = radius * cos;
+        let s = 0.5 * (1.0 + view.z);
+        let p2 = (1.0 - s) * libm::sqrtf(1.0 - p1 * p1) + s * radius * sin;
+        let normal = t1 * p1 + t2 * p2 + view * libm::sqrtf((1.0 - p1 * p1 - p2 * p2).max(0.0));
+        Vec3::new(self.alpha * normal.x, self.alpha * normal.y, normal.z.max(0.0)).normalized()
+    }
+
+    // Density of `wi` reflected about normals sampled by `sample_visible`.
+    pub fn reflect_pdf(&self, wo: &Vec3, h: &Vec3) -> f32 {
+        if wo.z <= 0.0 {
+            return 0.0;
+        }
+        self.g1(wo) * self.d(h) / (4.0 * wo.z)
+    }
+}
+
+pub fn schlick(f0: Vec3, cos: f32) -> Vec3 {
+    let weight = libm::powf((1.0 - cos).clamp(0.0, 1.0), 5.0);
+    f0 + (Vec3::new(1.0, 1.0, 1.0) - f0) * weight
+}
+
+pub fn reflect_local(v: &Vec3, h: &Vec3) -> Vec3 {
+    *h * (2.0 * dot_product(v, h)) - *v
+}
+
+fn mean(color: &Vec3) -> f32 {
+    (color.x + color.y + color.z) / 3.0
+}
+
+// Metallic-roughness model as in glTF: a Lambertian base under a GGX specular layer, whose
+// normal incidence reflectance is `0.08 * specular` for dielectrics and `base_color` for metals.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Pbr {
+    pub base_color: Vec3,
+    pub metallic: f32,
+    pub roughness: f32,
+    pub specular: f32,
+}
+
+impl Pbr {
+    pub fn new(base_color: Vec3, metallic: f32, roughness: f32) -> Pbr {
+        Pbr {
+            base_color,
+            metallic,
+            roughness,
+            specular: 0.5,
+        }
+    }
+
+    pub fn f0(&self) -> Vec3 {
+        let dielectric = Vec3::new(1.0, 1.0, 1.0) * (0.08 * self.specular);
+        dielectric * (1.0 - self.metallic) + self.base_color * self.metallic
+    }
+
+    fn diffuse_color(&self) -> Vec3 {
+        self.base_color * (1.0 - self.metallic)
+    }
+
+    // Chance of sampling the specular lobe, by the rough share of energy each lobe reflects.
+    fn specular_chance(&self, wo: &Vec3) -> f32 {
+        let specular = mean(&schlick(self.f0(), wo.z));
+        let diffuse = mean(&self.diffuse_color()) * (1.0 - specular);
+        if specular + diffuse <= 0.0 {
+            return 1.0;
+        }
+        (specular / (specular + diffuse)).clamp(0.05, 1.0)
+    }
+
+    pub fn eval(&self, wo: &Vec3, wi: &Vec3) -> Vec3 {
+        if wo.z <= 0.0 || wi.z <= 0.0 {
+            return Default::default();
+        }
+        let h = (*wo + *wi).normalized();
+        let ggx = Ggx::new(self.roughness);
+        let fresnel = schlick(self.f0(), dot_product(wo, &h));
+        let specular = fresnel * (ggx.d(&h) * ggx.g(wo, wi) / (4.0 * wo.z * wi.z));
+        let diffuse = (Vec3::new(1.0, 1.0, 1.0) - fresnel) * self.diffuse_color() * (1.0 / PI);
+        (specular + diffuse) * wi.z
+    }
+
+    pub fn pdf(&self, wo: &Vec3, wi: &Vec3) -> f32 {
+        if wo.z <= 0.0 || wi.z <= 0.0 {
+            return 0.0;
+        }
+        let h = (*wo + *wi).normalized();
+        let chance = self.specular_chance(wo);
+        chance * Ggx::new(self.roughness).reflect_pdf(wo, &h) + (1.0 - chance) * wi.z / PI
+    }
+
+    pub fn sample(&self, wo: &Vec3, rng: &mut Rng) -> Option<BsdfSample> {
+        if wo.z <= 0.0 {
+            return None;
+        }
+        let wi = if rng.next_f32() < self.specular_chance(wo) {
+            let h = Ggx::new(self.roughness).sample_visible(wo, rng);
+            reflect_local(wo, &h)
+        } else {
+            cosine_hemisphere(rng.next_vec2())
+        };
+        let pdf = self.pdf(wo, &wi);
+        if pdf <= 0.0 {
+            return None;
+        }
+        Some(BsdfSample {
+            wi,
+            weight: self.eval(wo, &wi) * (1.0 / pdf),
+            pdf,
+        })
+    }
+}
+
+// Exact reflectance of unpolarized light at a smooth boundary between two dielectrics, one on
+// total internal reflection.
+pub fn fresnel_dielectric(cos_i: f32, eta_i: f32, eta_t: f32) -> f32 {
+    let cos_i = cos_i.clamp(0.0, 1.0);
+    let sin_t = eta_i / eta_t * libm::sqrtf(1.0 - cos_i * cos_i);
+    if sin_t >= 1.0 {
+        return 1.0;
+    }
+    let cos_t = libm::sqrtf(1.0 - sin_t * sin_t);
+    let parallel = (eta_t * cos_i - eta_i * cos_t) / (eta_t * cos_i + eta_i * cos_t);
+    let perpendicular = (eta_i * cos_i - eta_t * cos_t) / (eta_i * cos_i + eta_t * cos_t);
+    0.5 * (parallel * parallel + perpendicular * perpendicular)
+}
+
+// Glass, water and the like, frosted with a GGX `roughness` above zero. Where dielectrics
+// overlap, the one with the higher `priority` fills the overlap, so glass in water needs no
+// exactly matching surfaces. Light inside is absorbed by `absorption` per unit length and
+// channel (Beer-Lambert), tinting it more the thicker the object, and may be scattered by
+// a `medium` filling it. With an `ior` of one the surface only bounds the medium.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Dielectric {
+    pub ior: f32,
+    pub priority: u32,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub roughness: f32,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub absorption: Vec3,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub medium: Option<MediumIdx>,
+}
+
+impl Dielectric {
+    pub fn new(ior: f32) -> Dielectric {
+        Dielectric {
+            ior,
+            priority: 0,
+            roughness: 0.0,
+            absorption: Default::default(),
+            medium: None,
+        }
+    }
+
+    // Microfacet normal to reflect or refract `wo` about, the surface normal when smooth.
+    pub fn facet(&self, wo: &Vec3, rng: &mut Rng) -> Vec3 {
+        if self.roughness > 0.0 {
+            Ggx::new(self.roughness).sample_visible(wo, rng)
+        } else {
+            Vec3::new(0.0, 0.0, 1.0)
+        }
+    }
+
+    // Shadowing of `wi` once `wo` went through a sampled facet, reflected or refracted alike.
+    pub fn facet_weight(&self, wo: &Vec3, wi: &Vec3) -> f32 {
+        if self.roughness > 0.0 {
+            let ggx = Ggx::new(self.roughness);
+            ggx.g(wo, wi) / ggx.g1(wo)
+        } else {
+            1.0
+        }
+    }
+}
+
+// Translucent solid such as skin, wax or marble, rendered by a random walk scattering light
+// beneath its smooth surface, which must be closed. Light of each channel gets about `radius`
+// into it before scattering, and `color` is how it looks once the walk is done.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Subsurface {
+    pub ior: f32,
+    pub color: Vec3,
+    pub radius: Vec3,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub anisotropy: f32,
+}
+
+impl Subsurface {
+    pub fn new(ior: f32, color: Vec3, radius: Vec3) -> Subsurface {
+        Subsurface {
+            ior,
+            color,
+            radius,
+            anisotropy: 0.0,
+        }
+    }
+
+    // Extinction and scattering coefficients, the single scattering albedo being fitted to
+    // give `color` after many bounces (Chiang et al. 2016).
+    fn coefficients(&self) -> (Vec3, Vec3) {
+        let extinction = self.radius.map(|radius| 1.0 / radius.max(1e-6));
+        let albedo = self.color.map(|color| {
+            let color = color.clamp(0.0, 1.0);
+            let root = 4.09712 + 4.20863 * color
+                - libm::sqrtf(9.59217 + 41.6808 * color + 17.7126 * color * color);
+            1.0 - root * root
+        });
+        (extinction, extinction * albedo)
+    }
+
+    // Distance to the next scattering along a walk, sampled by the extinction of `channel`,
+    // or `max_dist` when reaching the surface first. Also gives the fraction of each channel
+    // scattered or let through there, and the density of sampling that distance with each
+    // channel: a walk sticks to one channel and is weighted by the mean density of the others
+    // following it (spectral MIS).
+    pub fn sample_distance(&self, channel: usize, max_dist: f32, rng: &mut Rng) -> (f32, Vec3, Vec3) {
+        let (extinction, scattering) = self.coefficients();
+        let coefficient = [extinction.x, extinction.y, extinction.z][channel];
+        let dist = -libm::logf(1.0 - rng.next_f32()) / coefficient;
+        let transmittance = |dist: f32| extinction.map(|coefficient| libm::expf(-coefficient * dist));
+        if dist < max_dist {
+            let transmittance = transmittance(dist);
+            (dist, scattering * transmittance, extinction * transmittance)
+        } else {
+            let transmittance = transmittance(max_dist);
+            (max_dist, transmittance, transmittance)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Integrates the sampled weights, which estimate the directional albedo.
+    fn albedo(pbr: &Pbr, wo: &Vec3) -> Vec3 {
+        let mut rng = Rng::new(3);
+        let count = 20000;
+        (0..count)
+            .filter_map(|_| pbr.sample(wo, &mut rng))
+            .fold(Vec3::default(), |sum, sample| sum + sample.weight)
+            * (1.0 / count as f32)
+    }
+
+    #[test]
+    fn test_pbr_energy() {
+        let wo = Vec3::new(0.6, 0.0, 0.8);
+        let white = Vec3::new(1.0, 1.0, 1.0);
+        // single scattering loses energy on rough metals, but never gains it
+        for (roughness, lowest) in [(0.1, 0.95), (0.5, 0.85), (1.0, 0.3)] {
+            let metal = albedo(&Pbr::new(white, 1.0, roughness), &wo);
+            let plastic = albedo(&Pbr::new(white, 0.0, roughness), &wo);
+            assert!(metal.x <= 1.01 && metal.x > lowest, "{roughness} {metal:?}");
+            assert!(plastic.x <= 1.01 && plastic.x > 0.8, "{roughness} {plastic:?}");
+        }
+
+        // a sampled estimate agrees with the evaluated density
+        let pbr = Pbr::new(Vec3::new(0.8, 0.2, 0.2), 0.3, 0.4);
+        let sample = pbr.sample(&wo, &mut Rng::new(5)).unwrap();
+        let expected = pbr.eval(&wo, &sample.wi) * (1.0 / pbr.pdf(&wo, &sample.wi));
+        assert!((sample.weight - expected).norm() < 1e-4);
+    }
+
+    #[test]
+    fn test_fresnel_dielectric() {
+        // 4% at normal incidence on glass, total reflection past the critical angle inside it
+        assert!((fresnel_dielectric(1.0, 1.0, 1.5) - 0.04).abs() < 1e-4);
+        assert_eq!(1.0, fresnel_dielectric(0.5, 1.5, 1.0));
+        assert!(fresnel_dielectric(0.9, 1.5, 1.0) < 0.1);
+        assert!((fresnel_dielectric(0.0, 1.0, 1.5) - 1.0).abs() < 1e-4);
+    }
+
+    #[test]
+    fn test_frosted_facets() {
+        let mut rng = Rng::new(5);
+        let wo = Vec3::new(0.6, 0.0, 0.8);
+        let smooth = Dielectric::new(1.5);
+        assert_eq!(Vec3::new(0.0, 0.0, 1.0), smooth.facet(&wo, &mut rng));
+        let frosted = Dielectric {
+            roughness: 0.6,
+            ..smooth
+        };
+        for _ in 0..1000 {
+            // only facets seen from `wo`, and shadowing never adds energy
+            let facet = frosted.facet(&wo, &mut rng);
+            assert!(dot_product(&wo, &facet) > 0.0);
+            let wi = reflect_local(&wo, &facet);
+            let weight = frosted.facet_weight(&wo, &wi);
+            assert!(weight > 0.0 && weight <= 1.0);
+        }
+    }
+
+    #[test]
+    fn test_subsurface_steps() {
+        // the weights reaching the surface estimate how much of each channel gets that far
+        let skin = Subsurface::new(1.4, Vec3::new(0.8, 0.5, 0.4), Vec3::new(1.0, 0.4, 0.2));
+        let mut rng = Rng::new(11);
+        let count = 40000;
+        let reached = (0..count)
+            .map(|i| skin.sample_distance(i % 3, 0.5, &mut rng))
+            .filter(|(dist, _, _)| *dist == 0.5)
+            .fold(Vec3::default(), |sum, (_, value, pdf)| sum + value * (3.0 / (pdf.x + pdf.y + pdf.z)))
+            * (1.0 / count as f32);
+        let expected = skin.radius.map(|radius| libm::expf(-0.5 / radius));
+        assert!((reached - expected).map(libm::fabsf).norm() < 0.02, "{reached:?}");
+
+        // a white walk scatters without losing anything
+        let (extinction, scattering) = Subsurface::new(1.4, Vec3::new(1.0, 1.0, 1.0), skin.radius).coefficients();
+        assert!((extinction - scattering).norm() < 1e-2 * extinction.norm());
+    }
+}
@@ -0,0 +1,802 @@
+use alloc::sync::Arc;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::fmt;
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+use crate::entity::bounding_box::BoundingBox;
+use crate::entity::capsule::Capsule;
+use crate::entity::cone::Cone;
+use crate::entity::csg::{Csg, CsgOp};
+use crate::entity::cuboid::Cuboid;
+use crate::entity::cylinder::Cylinder;
+use crate::entity::disk::Disk;
+use crate::entity::group::Group;
+use crate::entity::instance::Instance;
+use crate::entity::plane::Plane;
+use crate::entity::quad::Quad;
+use crate::entity::sdf::{Sdf, SdfNode};
+use crate::entity::sphere::Sphere;
+use crate::entity::torus::Torus;
+use crate::entity::triangle::Triangle;
+use crate::entity::Entity;
+use crate::environment::{Environment, Projection};
+use crate::light::Light;
+use crate::loader::{build_mesh, load_mesh, LoadError, MeshFormat, Shading};
+use crate::material::{Coat, Layered, Material, MaterialKind, Mix};
+use crate::medium::Medium;
+use crate::scene::{Geometry, Scene};
+use crate::transform::Transform;
+use crate::texture::Texture;
+#[cfg(feature = "std")]
+use crate::texture::{ColorSpace, Filter, WrapMode};
+#[cfg(feature = "std")]
+use crate::loader::image::load_image;
+use crate::utils::{MaterialIdx, MediumIdx, TextureIdx};
+use crate::vec3::{cross_product, Vec3};
+
+// Handles are tagged with the builder they came from, so that one from another builder is
+// refused rather than taken for whichever material, texture or medium has the same index here.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MaterialHandle {
+    builder: usize,
+    idx: MaterialIdx,
+}
+
+impl MaterialHandle {
+    pub fn idx(&self) -> MaterialIdx {
+        self.idx
+    }
+}
+
+static NEXT_BUILDER: AtomicUsize = AtomicUsize::new(0);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LightHandle(usize);
+
+// Textures are referenced from `Material::textures` by index.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TextureHandle {
+    builder: usize,
+    idx: TextureIdx,
+}
+
+impl TextureHandle {
+    pub fn idx(&self) -> TextureIdx {
+        self.idx
+    }
+}
+
+// Media are referenced from `Dielectric::medium` by index.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MediumHandle {
+    builder: usize,
+    idx: MediumIdx,
+}
+
+impl MediumHandle {
+    pub fn idx(&self) -> MediumIdx {
+        self.idx
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum BuildError {
+    UnknownMaterial(MaterialHandle),
+    UnknownTexture(TextureHandle),
+    UnknownMedium(MediumHandle),
+    InvalidValue(&'static str),
+    Mesh(LoadError),
+}
+
+impl fmt::Display for BuildError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BuildError::UnknownMaterial(handle) => {
+                write!(f, "material {} does not belong to this scene", handle.idx)
+            }
+            BuildError::UnknownTexture(handle) => {
+                write!(f, "texture {} does not belong to this scene", handle.idx)
+            }
+            BuildError::UnknownMedium(handle) => {
+                write!(f, "medium {} does not belong to this scene", handle.idx)
+            }
+            BuildError::InvalidValue(message) => write!(f, "invalid value: {message}"),
+            BuildError::Mesh(error) => write!(f, "failed to load mesh: {error}"),
+        }
+    }
+}
+
+impl core::error::Error for BuildError {}
+
+impl From<LoadError> for BuildError {
+    fn from(error: LoadError) -> Self {
+        BuildError::Mesh(error)
+    }
+}
+
+pub struct SceneBuilder {
+    id: usize,
+    geometry: Geometry,
+    lights: Vec<Light>,
+    groups: Vec<Vec<Entity>>,
+    atmosphere: Option<MediumIdx>,
+    environment: Option<Environment>,
+}
+
+impl Default for SceneBuilder {
+    fn default() -> Self {
+        SceneBuilder::new()
+    }
+}
+
+impl SceneBuilder {
+    pub fn new() -> SceneBuilder {
+        SceneBuilder {
+            id: NEXT_BUILDER.fetch_add(1, Ordering::Relaxed),
+            geometry: Default::default(),
+            lights: vec![],
+            groups: vec![vec![]],
+            atmosphere: None,
+            environment: None,
+        }
+    }
+
+    // The indices in `material` can't be told apart from those of another builder, so they
+    // are only checked to be in range.
+    pub fn add_material(&mut self, material: Material) -> Result<MaterialHandle, BuildError> {
+        let geometry = &self.geometry;
+        let textures = &material.textures;
+        let texture_idx = [
+            textures.diffuse,
+            textures.specular,
+            textures.roughness,
+            textures.metallic,
+            textures.reflection,
+            textures.normal,
+            textures.bump,
+        ];
+        let (parts, mask, medium) = match &material.kind {
+            MaterialKind::Mix(mix) => (&mix.materials[..], mix.mask, None),
+            MaterialKind::Layered(layered) => (core::slice::from_ref(&layered.base), None, None),
+            MaterialKind::Dielectric(dielectric) => (&[][..], None, dielectric.medium),
+            _ => (&[][..], None, None),
+        };
+        let mut texture_idx = texture_idx.into_iter().chain([mask]).flatten();
+        if texture_idx.any(|idx| geometry.texture_buf.get(idx).is_none()) {
+            return Err(BuildError::InvalidValue("material texture does not belong to this scene"));
+        }
+        if medium.is_some_and(|idx| geometry.medium_buf.get(idx).is_none()) {
+            return Err(BuildError::InvalidValue("dielectric medium does not belong to this scene"));
+        }
+        // only ones added before, so none nests in itself
+        let count = geometry.material_buf.materials.len();
+        if parts.iter().any(|idx| *idx as usize >= count) {
+            return Err(BuildError::InvalidValue("material part does not belong to this scene"));
+        }
+        Ok(MaterialHandle {
+            builder: self.id,
+            idx: self.geometry.push_material(material),
+        })
+    }
+
+    // Composite materials only refer to ones added before them, so none nests in itself.
+    pub fn add_mix(
+        &mut self,
+        materials: [MaterialHandle; 2],
+        weight: f32,
+        mask: Option<TextureHandle>,
+    ) -> Result<MaterialHandle, BuildError> {
+        for handle in materials {
+            self.material(handle)?;
+        }
+        let mask = mask.map(|handle| self.texture(handle)).transpose()?;
+        if !(0.0..=1.0).contains(&weight) {
+            return Err(BuildError::InvalidValue("mix weight must be within [0, 1]"));
+        }
+        self.add_material(Material::mix(Mix {
+            materials: materials.map(|handle| handle.idx),
+            weight,
+            mask,
+        }))
+    }
+
+    pub fn add_layered(&mut self, base: MaterialHandle, coat: Coat) -> Result<MaterialHandle, BuildError> {
+        self.material(base)?;
+        if coat.ior <= 0.0 || coat.ior.is_nan() {
+            return Err(BuildError::InvalidValue("coat ior must be positive"));
+        }
+        if !(0.0..=1.0).contains(&coat.roughness) {
+            return Err(BuildError::InvalidValue("coat roughness must be within [0, 1]"));
+        }
+        let amounts = [coat.thickness, coat.absorption.x, coat.absorption.y, coat.absorption.z];
+        if amounts.iter().any(|amount| *amount < 0.0 || amount.is_nan()) {
+            return Err(BuildError::InvalidValue("coat thickness and absorption must not be negative"));
+        }
+        self.add_material(Material::layered(Layered { base: base.idx, coat }))
+    }
+
+    pub fn add_texture(&mut self, texture: Texture) -> TextureHandle {
+        TextureHandle {
+            builder: self.id,
+            idx: self.geometry.push_texture(texture),
+        }
+    }
+
+    #[cfg(feature = "std")]
+    pub fn load_texture(
+        &mut self,
+        data: &[u8],
+        color_space: ColorSpace,
+        wrap: WrapMode,
+        filter: Filter,
+    ) -> Result<TextureHandle, LoadError> {
+        let image = load_image(data, color_space, wrap, filter)?;
+        Ok(self.add_texture(Texture::Image(image)))
+    }
+
+    pub fn add_medium(&mut self, medium: Medium) -> MediumHandle {
+        MediumHandle {
+            builder: self.id,
+            idx: self.geometry.push_medium(medium),
+        }
+    }
+
+    // Fog filling the scene outside of dielectrics.
+    pub fn set_atmosphere(&mut self, medium: Option<MediumHandle>) -> Result<(), BuildError> {
+        self.atmosphere = medium.map(|handle| self.medium(handle)).transpose()?;
+        Ok(())
+    }
+
+    // Textures lighting the scene from every direction instead of the background color, with
+    // `rotation` in radians about +y. They need adding first, to be importance sampled.
+    pub fn set_environment(
+        &mut self,
+        projection: Projection<TextureHandle>,
+        rotation: f32,
+        intensity: f32,
+    ) -> Result<(), BuildError> {
+        let projection = match projection {
+            Projection::LatLong(handle) => Projection::LatLong(self.texture(handle)?),
+            Projection::CubeMap(faces) => {
+                let mut idx = [0; 6];
+                for (idx, handle) in idx.iter_mut().zip(faces) {
+                    *idx = self.texture(handle)?;
+                }
+                Projection::CubeMap(idx)
+            }
+        };
+        if intensity < 0.0 || intensity.is_nan() {
+            return Err(BuildError::InvalidValue("environment intensity must not be negative"));
+        }
+        let environment = Environment::new(projection, rotation, intensity, &self.geometry.texture_buf);
+        self.environment = Some(environment);
+        Ok(())
+    }
+
+    pub fn add_light(&mut self, light: Light) -> LightHandle {
+        self.lights.push(light);
+        LightHandle(self.lights.len() - 1)
+    }
+
+    pub fn add_sphere(
+        &mut self,
+        center: Vec3,
+        radius: f32,
+        material: MaterialHandle,
+    ) -> Result<(), BuildError> {
+        if radius.is_nan() || radius <= 0.0 {
+            return Err(BuildError::InvalidValue("sphere radius must be positive"));
+        }
+        let material = *self.material(material)?;
+        self.push(Entity::Sphere(Sphere::new(center, radius, material)));
+        Ok(())
+    }
+
+    pub fn add_plane(
+        &mut self,
+        point: Vec3,
+        normal: Vec3,
+        material: MaterialHandle,
+    ) -> Result<(), BuildError> {
+        if normal.norm() == 0.0 {
+            return Err(BuildError::InvalidValue("plane normal is zero"));
+        }
+        let material = *self.material(material)?;
+        self.push(Entity::Plane(Plane::new(point, normal.normalized(), material)));
+        Ok(())
+    }
+
+    pub fn add_triangle(
+        &mut self,
+        points: [Vec3; 3],
+        material: MaterialHandle,
+    ) -> Result<(), BuildError> {
+        self.material(material)?;
+        let [a, b, c] = points;
+        if cross_product(&(b - a), &(c - a)).norm() == 0.0 {
+            return Err(BuildError::InvalidValue("triangle is degenerate"));
+        }
+        let points = points.map(|point| self.geometry.push_vec(point));
+        let triangle = Triangle::new(&self.geometry, points, material.idx);
+        self.push(Entity::Triangle(triangle));
+        Ok(())
+    }
+
+    pub fn add_cuboid(
+        &mut self,
+        min: Vec3,
+        max: Vec3,
+        material: MaterialHandle,
+    ) -> Result<(), BuildError> {
+        let material = *self.material(material)?;
+        let cuboid = Cuboid::new(min, max, material)
+            .ok_or(BuildError::InvalidValue("cuboid min must be below max"))?;
+        self.push(Entity::Cuboid(cuboid));
+        Ok(())
+    }
+
+    pub fn add_oriented_cuboid(
+        &mut self,
+        center: Vec3,
+        half_size: Vec3,
+        axes: [Vec3; 3],
+        material: MaterialHandle,
+    ) -> Result<(), BuildError> {
+        let material = *self.material(material)?;
+        let cuboid = Cuboid::oriented(center, half_size, axes, material).ok_or(
+            BuildError::InvalidValue("cuboid size must be positive and its axes orthogonal"),
+        )?;
+        self.push(Entity::Cuboid(cuboid));
+        Ok(())
+    }
+
+    pub fn add_quad(
+        &mut self,
+        corner: Vec3,
+        edge_u: Vec3,
+        edge_v: Vec3,
+        material: MaterialHandle,
+    ) -> Result<(), BuildError> {
+        let material = *self.material(material)?;
+        let quad = Quad::new(corner, edge_u, edge_v, material)
+            .ok_or(BuildError::InvalidValue("quad is degenerate"))?;
+        self.push(Entity::Quad(quad));
+        Ok(())
+    }
+
+    pub fn add_disk(
+        &mut self,
+        center: Vec3,
+        normal: Vec3,
+        radius: f32,
+        material: MaterialHandle,
+    ) -> Result<(), BuildError> {
+        let material = *self.material(material)?;
+        let disk = Disk::new(center, normal, radius, material).ok_or(BuildError::InvalidValue(
+            "disk radius must be positive and its normal not zero",
+        ))?;
+        self.push(Entity::Disk(disk));
+        Ok(())
+    }
+
+    pub fn add_cylinder(
+        &mut self,
+        base: Vec3,
+        top: Vec3,
+        radius: f32,
+        capped: bool,
+        material: MaterialHandle,
+    ) -> Result<(), BuildError> {
+        let material = *self.material(material)?;
+        let cylinder = Cylinder::new(base, top, radius, capped, material).ok_or(
+            BuildError::InvalidValue("cylinder radius and height must be positive"),
+        )?;
+        self.push(Entity::Cylinder(cylinder));
+        Ok(())
+    }
+
+    pub fn add_cone(
+        &mut self,
+        base: Vec3,
+        top: Vec3,
+        radii: (f32, f32),
+        capped: bool,
+        material: MaterialHandle,
+    ) -> Result<(), BuildError> {
+        let (base_radius, top_radius) = radii;
+        let material = *self.material(material)?;
+        let cone = Cone::new(base, top, base_radius, top_radius, capped, material).ok_or(
+            BuildError::InvalidValue("cone needs a positive height and radii, one may be zero"),
+        )?;
+        self.push(Entity::Cone(cone));
+        Ok(())
+    }
+
+    pub fn add_capsule(
+        &mut self,
+        base: Vec3,
+        top: Vec3,
+        radius: f32,
+        material: MaterialHandle,
+    ) -> Result<(), BuildError> {
+        let material = *self.material(material)?;
+        let capsule = Capsule::new(base, top, radius, material).ok_or(BuildError::InvalidValue(
+            "capsule radius and height must be positive",
+        ))?;
+        self.push(Entity::Capsule(capsule));
+        Ok(())
+    }
+
+    pub fn add_torus(
+        &mut self,
+        center: Vec3,
+        axis: Vec3,
+        radii: (f32, f32),
+        material: MaterialHandle,
+    ) -> Result<(), BuildError> {
+        let (major_radius, minor_radius) = radii;
+        let material = *self.material(material)?;
+        let torus = Torus::new(center, axis, major_radius, minor_radius, material).ok_or(
+            BuildError::InvalidValue("torus axis must not be zero and 0 < minor < major radius"),
+        )?;
+        self.push(Entity::Torus(torus));
+        Ok(())
+    }
+
+    // `bounds` must enclose the whole surface, it also limits how far rays are marched.
+    pub fn add_sdf(
+        &mut self,
+        node: SdfNode,
+        bounds: BoundingBox,
+        material: MaterialHandle,
+    ) -> Result<(), BuildError> {
+        let size = bounds.max() - bounds.min();
+        check_positive(size.x.min(size.y).min(size.z), "sdf bounds are empty")?;
+        let material = *self.material(material)?;
+        self.push(Entity::Sdf(Sdf::new(node, bounds, material)));
+        Ok(())
+    }
+
+    pub fn add_mesh(
+        &mut self,
+        vertices: Vec<Vec3>,
+        faces: &[[u32; 3]],
+        material: MaterialHandle,
+        shading: Shading,
+    ) -> Result<(), BuildError> {
+        self.material(material)?;
+        check_shading(shading)?;
+        let mut mesh = build_mesh(&mut self.geometry, vertices, faces, material.idx)?;
+        mesh.shade(&mut self.geometry, shading);
+        self.push(Entity::Model(mesh.model));
+        Ok(())
+    }
+
+    pub fn load_mesh(
+        &mut self,
+        format: MeshFormat,
+        data: &[u8],
+        material: MaterialHandle,
+        shading: Shading,
+    ) -> Result<(), BuildError> {
+        self.material(material)?;
+        check_shading(shading)?;
+        let mesh = load_mesh(&mut self.geometry, format, data, material.idx, shading)?;
+        self.push(Entity::Model(mesh.model));
+        Ok(())
+    }
+
+    // Entities added inside `fill` end up in a nested `Group`.
+    pub fn group<F, E>(&mut self, fill: F) -> Result<(), E>
+    where
+        F: FnOnce(&mut SceneBuilder) -> Result<(), E>,
+    {
+        let entities = self.fill(fill)?;
+        self.push(Entity::Group(Group::new(entities)));
+        Ok(())
+    }
+
+    // Like `group`, but the filled group is returned for instancing instead of being added.
+    pub fn prototype<F, E>(&mut self, fill: F) -> Result<Arc<Entity>, E>
+    where
+        F: FnOnce(&mut SceneBuilder) -> Result<(), E>,
+    {
+        let mut entities = self.fill(fill)?;
+        Ok(Arc::new(if entities.len() == 1 {
+            entities.pop().unwrap()
+        } else {
+            Entity::Group(Group::new(entities))
+        }))
+    }
+
+    pub fn add_instance(
+        &mut self,
+        prototype: &Arc<Entity>,
+        transform: Transform,
+        material: Option<MaterialHandle>,
+    ) -> Result<(), BuildError> {
+        let material = match material {
+            Some(handle) => Some(*self.material(handle)?),
+            None => None,
+        };
+        let instance = Instance::new(&self.geometry, prototype.clone(), transform, material);
+        self.push(Entity::Instance(instance));
+        Ok(())
+    }
+
+    // Operands are usually made with `prototype` and must be solids, see `Entity::is_solid`.
+    pub fn add_csg(
+        &mut self,
+        op: CsgOp,
+        left: &Arc<Entity>,
+        right: &Arc<Entity>,
+    ) -> Result<(), BuildError> {
+        let csg = Csg::new(&self.geometry, op, left.clone(), right.clone())
+            .ok_or(BuildError::InvalidValue("csg operands must be solids"))?;
+        self.push(Entity::Csg(csg));
+        Ok(())
+    }
+
+    pub fn build(mut self) -> Scene {
+        let root = Group::new(self.groups.pop().unwrap());
+        Scene::new(self.geometry, root, self.lights)
+            .with_surroundings(self.atmosphere, self.environment)
+    }
+
+    fn material(&self, handle: MaterialHandle) -> Result<&Material, BuildError> {
+        self.geometry
+            .material_buf
+            .materials
+            .get(handle.idx as usize)
+            .filter(|_| handle.builder == self.id)
+            .ok_or(BuildError::UnknownMaterial(handle))
+    }
+
+    fn texture(&self, handle: TextureHandle) -> Result<TextureIdx, BuildError> {
+        self.geometry
+            .texture_buf
+            .get(handle.idx)
+            .filter(|_| handle.builder == self.id)
+            .map(|_| handle.idx)
+            .ok_or(BuildError::UnknownTexture(handle))
+    }
+
+    fn medium(&self, handle: MediumHandle) -> Result<MediumIdx, BuildError> {
+        self.geometry
+            .medium_buf
+            .get(handle.idx)
+            .filter(|_| handle.builder == self.id)
+            .map(|_| handle.idx)
+            .ok_or(BuildError::UnknownMedium(handle))
+    }
+
+    // Entities added by `fill` to a new group. When it fails, everything else it added or
+    // changed is undone along with them, so no handle it made outlives it.
+    fn fill<F, E>(&mut self, fill: F) -> Result<Vec<Entity>, E>
+    where
+        F: FnOnce(&mut SceneBuilder) -> Result<(), E>,
+    {
+        let geometry = &self.geometry;
+        let points = geometry.vec_buf.points.len();
+        let normals = geometry.normal_buf.normals.len();
+        let uvs = geometry.uv_buf.uvs.len();
+        let materials = geometry.material_buf.materials.len();
+        let textures = geometry.texture_buf.textures.len();
+        let media = geometry.medium_buf.media.len();
+        let lights = self.lights.len();
+        let atmosphere = self.atmosphere;
+        let environment = self.environment.clone();
+        self.groups.push(vec![]);
+        let filled = fill(self);
+        let entities = self.groups.pop().unwrap();
+        if filled.is_err() {
+            let geometry = &mut self.geometry;
+            geometry.vec_buf.points.truncate(points);
+            geometry.normal_buf.normals.truncate(normals);
+            geometry.uv_buf.uvs.truncate(uvs);
+            geometry.material_buf.materials.truncate(materials);
+            geometry.texture_buf.textures.truncate(textures);
+            geometry.medium_buf.media.truncate(media);
+            self.lights.truncate(lights);
+            self.atmosphere = atmosphere;
+            self.environment = environment;
+        }
+        filled.map(|_| entities)
+    }
+
+    fn push(&mut self, entity: Entity) {
+        self.groups.last_mut().unwrap().push(entity);
+    }
+}
+
+fn check_positive(value: f32, message: &'static str) -> Result<(), BuildError> {
+    if value.is_nan() || value <= 0.0 {
+        return Err(BuildError::InvalidValue(message));
+    }
+    Ok(())
+}
+
+fn check_shading(shading: Shading) -> Result<(), BuildError> {
+    match shading {
+        Shading::Smooth { crease_angle } if crease_angle.is_nan() || crease_angle < 0.0 => {
+            Err(BuildError::InvalidValue("crease angle must not be negative"))
+        }
+        _ => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bsdf::Dielectric;
+    use crate::intersect::Intersect;
+    use crate::ray::Ray;
+    use crate::texture::{Filter, ImageTexture, WrapMode};
+
+    #[test]
+    fn test_scene_builder() {
+        let mut builder = SceneBuilder::new();
+        let ivory = builder
+            .add_material(Material::new(
+                1.0,
+                [0.6, 0.3, 0.1, 0.0],
+                Vec3::new(0.4, 0.4, 0.3),
+                50.0,
+            ))
+            .unwrap();
+        builder.add_light(Light::new(Vec3::new(-20.0, 20.0, 20.0), 1.5));
+        builder
+            .add_plane(Vec3::new(0.0, -4.0, 0.0), Vec3::new(0.0, 2.0, 0.0), ivory)
+            .unwrap();
+        builder
+            .group(|group| -> Result<(), BuildError> {
+                group.add_sphere(Vec3::new(0.0, 0.0, -10.0), 2.0, ivory)?;
+                group.add_mesh(
+                    vec![
+                        Vec3::new(0.0, 0.0, -5.0),
+                        Vec3::new(1.0, 0.0, -5.0),
+                        Vec3::new(0.0, 1.0, -5.0),
+                    ],
+                    &[[0, 1, 2]],
+                    ivory,
+                    Shading::Flat,
+                )?;
+                Ok(())
+            })
+            .unwrap();
+
+        let scene = builder.build();
+
+        assert_eq!(3, scene.geometry().vec_buf.points.len());
+        assert_eq!(1, scene.lights().len());
+        assert!(matches!(&scene.root().entities[1], Entity::Group(group) if group.entities.len() == 2));
+    }
+
+    #[test]
+    fn test_scene_builder_instances() {
+        let mut builder = SceneBuilder::new();
+        let white = builder.add_material(Default::default()).unwrap();
+        let red = builder
+            .add_material(Material {
+                diffuse_color: Vec3::new(1.0, 0.0, 0.0),
+                ..Default::default()
+            })
+            .unwrap();
+        let ball = builder
+            .prototype(|proto| proto.add_sphere(Default::default(), 1.0, white))
+            .unwrap();
+        for x in [-4.0, 4.0] {
+            let transform = Transform::scaling(Vec3::new(1.0, 2.0, 1.0))
+                .unwrap()
+                .then(&Transform::translation(Vec3::new(x, 0.0, -10.0)));
+            builder.add_instance(&ball, transform, Some(red)).unwrap();
+        }
+        let scene = builder.build();
+        let geometry = scene.geometry();
+
+        let ray = Ray::new(Vec3::new(4.0, 10.0, -10.0), Vec3::new(0.0, -1.0, 0.0));
+        let hit = scene.root().ray_intersect(geometry, ray).unwrap();
+        assert!((hit.point - Vec3::new(4.0, 2.0, -10.0)).norm() < 1e-4);
+        assert!((hit.normal - Vec3::new(0.0, 1.0, 0.0)).norm() < 1e-4);
+        assert_eq!(Vec3::new(1.0, 0.0, 0.0), hit.material.diffuse_color);
+
+        let aabb = scene.root().entities[0].bounding_box(geometry).unwrap();
+        assert!((aabb.max() - Vec3::new(-3.0, 2.0, -9.0)).norm() < 1e-4);
+    }
+
+    #[test]
+    fn test_scene_builder_errors() {
+        // refused even though this builder has a material at the same index
+        let mut other = SceneBuilder::new();
+        let foreign = other.add_material(Default::default()).unwrap();
+
+        let mut builder = SceneBuilder::new();
+        let material = builder.add_material(Default::default()).unwrap();
+        assert_eq!(
+            Err(BuildError::UnknownMaterial(foreign)),
+            builder.add_sphere(Default::default(), 1.0, foreign)
+        );
+        assert_eq!(
+            Err(BuildError::InvalidValue("sphere radius must be positive")),
+            builder.add_sphere(Default::default(), 0.0, material)
+        );
+        // textures and media are tagged the same way, raw indices only checked to be in range
+        let white = || {
+            let texels = vec![Vec3::new(1.0, 1.0, 1.0)];
+            let image = ImageTexture::new(1, 1, texels, WrapMode::Repeat, Filter::Nearest);
+            Texture::Image(image.unwrap())
+        };
+        let foreign_mask = other.add_texture(white());
+        let foreign_fog = other.add_medium(Medium::new(0.1, Vec3::new(1.0, 1.0, 1.0), 0.0));
+        let mask = builder.add_texture(white());
+        assert_eq!(
+            Err(BuildError::UnknownTexture(foreign_mask)),
+            builder.add_mix([material, material], 0.5, Some(foreign_mask))
+        );
+        assert!(builder.add_mix([material, material], 0.5, Some(mask)).is_ok());
+        assert_eq!(
+            Err(BuildError::UnknownMedium(foreign_fog)),
+            builder.set_atmosphere(Some(foreign_fog))
+        );
+        assert_eq!(
+            Err(BuildError::UnknownTexture(foreign_mask)),
+            builder.set_environment(Projection::LatLong(foreign_mask), 0.0, 1.0)
+        );
+        let mut textured = Material::default();
+        textured.textures.bump = Some(mask.idx() + 1);
+        assert!(matches!(builder.add_material(textured), Err(BuildError::InvalidValue(_))));
+        let foggy = Material::dielectric(Dielectric {
+            ior: 1.5,
+            priority: 0,
+            roughness: 0.0,
+            absorption: Default::default(),
+            medium: Some(0),
+        });
+        assert!(matches!(builder.add_material(foggy), Err(BuildError::InvalidValue(_))));
+        let skewed = [
+            Vec3::new(1.0, 0.0, 0.0),
+            Vec3::new(1.0, 1.0, 0.0),
+            Vec3::new(0.0, 0.0, 1.0),
+        ];
+        assert_eq!(
+            Err(BuildError::InvalidValue("cuboid size must be positive and its axes orthogonal")),
+            builder.add_oriented_cuboid(Default::default(), Vec3::new(1.0, 1.0, 1.0), skewed, material)
+        );
+        assert!(matches!(
+            builder.add_mesh(vec![Default::default()], &[[0, 0, 1]], material, Shading::Flat),
+            Err(BuildError::Mesh(LoadError::IndexOutOfRange { .. }))
+        ));
+        // a failed group leaves nothing behind
+        let points = vec![
+            Vec3::new(0.0, 0.0, 0.0),
+            Vec3::new(1.0, 0.0, 0.0),
+            Vec3::new(0.0, 1.0, 0.0),
+        ];
+        assert!(builder
+            .group(|group| {
+                group.add_mesh(points, &[[0, 1, 2]], material, Shading::Flat)?;
+                group.add_sphere(Default::default(), -1.0, material)
+            })
+            .is_err());
+        let materials = builder.geometry.material_buf.materials.len();
+        assert!(builder
+            .group(|group| {
+                group.add_light(Light::new(Vec3::new(0.0, 5.0, 0.0), 1.0));
+                let red = group
+                    .add_material(Material {
+                        diffuse_color: Vec3::new(1.0, 0.0, 0.0),
+                        ..Default::default()
+                    })
+                    .unwrap();
+                group.add_sphere(Default::default(), 1.0, red)?;
+                group.add_sphere(Default::default(), -1.0, red)
+            })
+            .is_err());
+        let scene = builder.build();
+        assert!(scene.root().entities.is_empty());
+        assert!(scene.geometry().vec_buf.points.is_empty());
+        assert!(scene.lights().is_empty());
+        assert_eq!(materials, scene.geometry().material_buf.materials.len());
+    }
+}
@@ -0,0 +1,88 @@
+use crate::ray::Ray;
+use crate::vec2::Vec2;
+use crate::vec3::{cross_product, Vec3};
+
+#[derive(Debug, Copy, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Camera {
+    pub origin: Vec3,
+    pub view_dir: Vec3,
+    pub fov: f32,
+}
+
+impl Camera {
+    // `None` unless `fov` is in (0, pi) radians and `view_dir` is not zero.
+    pub fn new(origin: Vec3, view_dir: Vec3, fov: f32) -> Option<Camera> {
+        if !(fov > 0.0 && fov < core::f32::consts::PI && view_dir.norm() > 0.0) {
+            return None;
+        }
+        Some(Camera {
+            origin,
+            view_dir: view_dir.normalized(),
+            fov,
+        })
+    }
+
+    pub fn look_at(origin: Vec3, target: Vec3, fov: f32) -> Option<Camera> {
+        Camera::new(origin, target - origin, fov)
+    }
+
+    pub fn primary_ray(&self, width: usize, height: usize, pixel_id: usize) -> Ray {
+        let (i, j) = (pixel_id / width, pixel_id % width);
+        self.pixel_ray(width, height, i as f32, j as f32)
+    }
+
+    // Rays through the next pixel to the right and the next one down, for ray differentials.
+    pub fn primary_ray_offsets(&self, width: usize, height: usize, pixel_id: usize) -> [Ray; 2] {
+        let (i, j) = ((pixel_id / width) as f32, (pixel_id % width) as f32);
+        [
+            self.pixel_ray(width, height, i, j + 1.0),
+            self.pixel_ray(width, height, i + 1.0, j),
+        ]
+    }
+
+    // Ray through a point of the pixel, `jitter` in [0, 1) moving it from where `primary_ray`
+    // passes towards the next pixel on the right and below.
+    pub fn sample_ray(&self, width: usize, height: usize, pixel_id: usize, jitter: Vec2) -> Ray {
+        let (i, j) = ((pixel_id / width) as f32, (pixel_id % width) as f32);
+        self.pixel_ray(width, height, i + jitter.y, j + jitter.x)
+    }
+
+    fn pixel_ray(&self, width: usize, height: usize, i_f: f32, j_f: f32) -> Ray {
+        let width_f = width as f32;
+        let height_f = height as f32;
+
+        let y_dir = Vec3::new(0.0, 1.0, 0.0);
+
+        let i_vec = y_dir;
+        let j_vec = cross_product(&self.view_dir, &y_dir).normalized();
+        let width_real_f = self.view_dir.norm() * libm::sinf(self.fov / 2.0) * 2.0;
+        let height_real_f = width_real_f * height_f / width_f;
+
+        let j_step = j_vec * (width_real_f / width_f);
+        let i_step = i_vec * (height_real_f / height_f);
+
+        let dir = (self.view_dir
+            + i_step * -2.0 * (i_f - height_f / 2.0)
+            + j_step * 2.0 * (j_f - width_f / 2.0))
+            .normalized();
+
+        Ray::new(self.origin, dir)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_camera_new() {
+        let forward = Vec3::new(0.0, 0.0, -2.0);
+        let camera = Camera::new(Default::default(), forward, 1.0).unwrap();
+        assert_eq!(Vec3::new(0.0, 0.0, -1.0), camera.view_dir);
+        for fov in [0.0, core::f32::consts::PI, f32::NAN] {
+            assert!(Camera::new(Default::default(), forward, fov).is_none());
+        }
+        assert!(Camera::look_at(forward, forward, 1.0).is_none());
+    }
+}
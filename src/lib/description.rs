@@ -0,0 +1,1294 @@
+use alloc::boxed::Box;
+use alloc::collections::BTreeMap;
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use core::fmt;
+
+use serde::Deserialize;
+
+use crate::bsdf::{Dielectric, Pbr, Subsurface};
+use crate::builder::{BuildError, MaterialHandle, MediumHandle, SceneBuilder, TextureHandle};
+use crate::light::Light;
+use crate::loader::{LoadError, MeshFormat, Shading};
+use crate::material::{Coat, Material, MaterialTextures};
+use crate::medium::{Density, Grid, Medium};
+use crate::camera::Camera;
+use crate::entity::bounding_box::BoundingBox;
+use crate::entity::csg::CsgOp;
+use crate::entity::sdf::SdfNode;
+use crate::entity::Entity;
+use crate::environment::Projection;
+use crate::integrator::Integrator;
+use crate::render::RenderSettings;
+use crate::scene::Scene;
+use crate::spectrum::{Dispersion, D_LINE};
+use crate::texture::{
+    ColorSpace, Filter, ImageTexture, Pattern, ProceduralTexture, Texture, TextureSpace,
+    WrapMode,
+};
+use crate::transform::Transform;
+use crate::vec3::Vec3;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum SceneError {
+    Parse(String),
+    UnknownMaterial(String),
+    UnknownPrototype(String),
+    UnknownTexture(String),
+    UnknownMedium(String),
+    InvalidValue(String),
+    Build(BuildError),
+    Resolve { path: String, message: String },
+    Mesh { path: String, error: LoadError },
+    Texture { path: String, error: LoadError },
+}
+
+impl fmt::Display for SceneError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SceneError::Parse(message) => write!(f, "failed to parse scene: {message}"),
+            SceneError::UnknownMaterial(name) => write!(f, "unknown material: {name:?}"),
+            SceneError::UnknownPrototype(name) => write!(f, "unknown prototype: {name:?}"),
+            SceneError::UnknownTexture(name) => write!(f, "unknown texture: {name:?}"),
+            SceneError::UnknownMedium(name) => write!(f, "unknown medium: {name:?}"),
+            SceneError::InvalidValue(message) => write!(f, "invalid value: {message}"),
+            SceneError::Build(error) => write!(f, "{error}"),
+            SceneError::Resolve { path, message } => {
+                write!(f, "failed to read {path:?}: {message}")
+            }
+            SceneError::Mesh { path, error } => write!(f, "failed to load mesh {path:?}: {error}"),
+            SceneError::Texture { path, error } => {
+                write!(f, "failed to load texture {path:?}: {error}")
+            }
+        }
+    }
+}
+
+impl core::error::Error for SceneError {}
+
+impl From<BuildError> for SceneError {
+    fn from(error: BuildError) -> Self {
+        SceneError::Build(error)
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SceneDescription {
+    pub camera: CameraDescription,
+    #[serde(default)]
+    pub render: RenderDescription,
+    #[serde(default)]
+    pub textures: BTreeMap<String, TextureDescription>,
+    #[serde(default)]
+    pub materials: BTreeMap<String, MaterialDescription>,
+    #[serde(default)]
+    pub media: BTreeMap<String, MediumDescription>,
+    // medium filling the scene outside of dielectrics
+    #[serde(default)]
+    pub atmosphere: Option<String>,
+    // lights the scene from every direction in place of the background color
+    #[serde(default)]
+    pub environment: Option<EnvironmentDescription>,
+    #[serde(default)]
+    pub lights: Vec<LightDescription>,
+    #[serde(default)]
+    pub prototypes: BTreeMap<String, ObjectDescription>,
+    #[serde(default)]
+    pub objects: Vec<ObjectDescription>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CameraDescription {
+    pub width: usize,
+    pub height: usize,
+    pub fov: f32,
+    #[serde(default)]
+    pub origin: [f32; 3],
+    #[serde(default)]
+    pub view_dir: Option<[f32; 3]>,
+    #[serde(default)]
+    pub look_at: Option<[f32; 3]>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RenderDescription {
+    pub background_color: [f32; 3],
+    pub recursion_limit: usize,
+    pub integrator: Integrator,
+    pub spectral: bool,
+}
+
+impl Default for RenderDescription {
+    fn default() -> Self {
+        RenderDescription {
+            background_color: [0.0, 0.0, 0.0],
+            recursion_limit: 5,
+            integrator: Default::default(),
+            spectral: false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct MaterialDescription {
+    #[serde(default = "default_refract_index")]
+    pub refract_index: f32,
+    #[serde(default = "default_albedo")]
+    pub albedo: [f32; 4],
+    #[serde(default)]
+    pub diffuse_color: [f32; 3],
+    #[serde(default)]
+    pub specular_exponent: f32,
+    #[serde(default)]
+    pub diffuse_texture: Option<String>,
+    #[serde(default)]
+    pub specular_texture: Option<String>,
+    #[serde(default)]
+    pub roughness_texture: Option<String>,
+    #[serde(default)]
+    pub metallic_texture: Option<String>,
+    #[serde(default)]
+    pub reflection_texture: Option<String>,
+    #[serde(default)]
+    pub normal_texture: Option<String>,
+    #[serde(default)]
+    pub bump_texture: Option<String>,
+    #[serde(default = "default_scale")]
+    pub bump_scale: f32,
+    // emitted radiance is the color times the strength
+    #[serde(default)]
+    pub emission: [f32; 3],
+    #[serde(default = "default_scale")]
+    pub emission_strength: f32,
+    // wavelength dependent index of refraction, for either Phong or dielectric materials
+    #[serde(default)]
+    pub dispersion: Option<Dispersion>,
+    // any one of these replaces the Phong parameters above
+    #[serde(default)]
+    pub pbr: Option<PbrDescription>,
+    #[serde(default)]
+    pub dielectric: Option<DielectricDescription>,
+    #[serde(default)]
+    pub subsurface: Option<SubsurfaceDescription>,
+    // composites of other materials by name, ignoring every other field
+    #[serde(default)]
+    pub mix: Option<MixDescription>,
+    #[serde(default)]
+    pub layered: Option<LayeredDescription>,
+}
+
+// The second material is weighted by `weight` times the red channel of the `mask` texture.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct MixDescription {
+    pub materials: [String; 2],
+    #[serde(default = "default_half")]
+    pub weight: f32,
+    #[serde(default)]
+    pub mask: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct LayeredDescription {
+    pub base: String,
+    #[serde(default = "default_coat_ior")]
+    pub ior: f32,
+    #[serde(default)]
+    pub roughness: f32,
+    #[serde(default)]
+    pub thickness: f32,
+    // per unit length, for each channel
+    #[serde(default)]
+    pub absorption: [f32; 3],
+}
+
+fn default_coat_ior() -> f32 {
+    1.5
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PbrDescription {
+    pub base_color: [f32; 3],
+    #[serde(default)]
+    pub metallic: f32,
+    #[serde(default = "default_half")]
+    pub roughness: f32,
+    #[serde(default = "default_half")]
+    pub specular: f32,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct DielectricDescription {
+    pub ior: f32,
+    #[serde(default)]
+    pub priority: u32,
+    #[serde(default)]
+    pub roughness: f32,
+    // per unit length, for each channel
+    #[serde(default)]
+    pub absorption: [f32; 3],
+    // participating medium filling the inside
+    #[serde(default)]
+    pub medium: Option<String>,
+}
+
+// `radius` is how far light of each channel gets beneath the surface, in scene units.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SubsurfaceDescription {
+    #[serde(default = "default_subsurface_ior")]
+    pub ior: f32,
+    pub color: [f32; 3],
+    pub radius: [f32; 3],
+    #[serde(default)]
+    pub anisotropy: f32,
+}
+
+fn default_subsurface_ior() -> f32 {
+    1.4
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct MediumDescription {
+    pub extinction: f32,
+    #[serde(default = "default_white")]
+    pub albedo: [f32; 3],
+    #[serde(default)]
+    pub anisotropy: f32,
+    // scales the extinction, homogeneous when missing
+    #[serde(default)]
+    pub density: Option<GridDescription>,
+}
+
+// Density samples listed with x varying fastest, then y, then z.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct GridDescription {
+    pub min: [f32; 3],
+    pub max: [f32; 3],
+    pub resolution: [usize; 3],
+    pub values: Vec<f32>,
+}
+
+// Either one lat-long `texture` or six cube map `faces`, ordered +x, -x, +y, -y, +z, -z.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct EnvironmentDescription {
+    #[serde(default)]
+    pub texture: Option<String>,
+    #[serde(default)]
+    pub faces: Option<[String; 6]>,
+    // degrees about +y
+    #[serde(default)]
+    pub rotation: f32,
+    #[serde(default = "default_scale")]
+    pub intensity: f32,
+}
+
+fn default_white() -> [f32; 3] {
+    [1.0, 1.0, 1.0]
+}
+
+fn default_albedo() -> [f32; 4] {
+    [1.0, 0.0, 0.0, 0.0]
+}
+
+fn default_half() -> f32 {
+    0.5
+}
+
+// Image texels are listed row by row from the top of the image. Files need the `std` feature
+// for decoding.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+pub enum TextureDescription {
+    Image {
+        width: u32,
+        height: u32,
+        texels: Vec<[f32; 3]>,
+        #[serde(default)]
+        wrap: WrapMode,
+        #[serde(default)]
+        filter: Filter,
+    },
+    File {
+        path: String,
+        #[serde(default)]
+        color_space: ColorSpace,
+        #[serde(default)]
+        wrap: WrapMode,
+        #[serde(default)]
+        filter: Filter,
+    },
+    Procedural {
+        pattern: Pattern,
+        #[serde(default)]
+        space: TextureSpace,
+        #[serde(default = "default_scale")]
+        scale: f32,
+        colors: [[f32; 3]; 2],
+    },
+}
+
+fn default_scale() -> f32 {
+    1.0
+}
+
+fn default_refract_index() -> f32 {
+    1.0
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct LightDescription {
+    pub position: [f32; 3],
+    pub intensity: f32,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+pub enum ObjectDescription {
+    Sphere {
+        center: [f32; 3],
+        radius: f32,
+        material: String,
+    },
+    Plane {
+        point: [f32; 3],
+        normal: [f32; 3],
+        material: String,
+    },
+    Triangle {
+        points: [[f32; 3]; 3],
+        material: String,
+    },
+    Cuboid {
+        min: [f32; 3],
+        max: [f32; 3],
+        material: String,
+    },
+    Quad {
+        corner: [f32; 3],
+        edge_u: [f32; 3],
+        edge_v: [f32; 3],
+        material: String,
+    },
+    Disk {
+        center: [f32; 3],
+        normal: [f32; 3],
+        radius: f32,
+        material: String,
+    },
+    Cylinder {
+        base: [f32; 3],
+        top: [f32; 3],
+        radius: f32,
+        #[serde(default)]
+        open: bool,
+        material: String,
+    },
+    Cone {
+        base: [f32; 3],
+        top: [f32; 3],
+        base_radius: f32,
+        #[serde(default)]
+        top_radius: f32,
+        #[serde(default)]
+        open: bool,
+        material: String,
+    },
+    Capsule {
+        base: [f32; 3],
+        top: [f32; 3],
+        radius: f32,
+        material: String,
+    },
+    Torus {
+        center: [f32; 3],
+        axis: [f32; 3],
+        major_radius: f32,
+        minor_radius: f32,
+        material: String,
+    },
+    Sdf {
+        shape: SdfDescription,
+        min: [f32; 3],
+        max: [f32; 3],
+        material: String,
+    },
+    Mesh {
+        path: String,
+        #[serde(default)]
+        format: Option<MeshFormat>,
+        material: String,
+        #[serde(default)]
+        smooth: bool,
+        // in degrees, defaults to smoothing across every edge
+        #[serde(default)]
+        crease_angle: Option<f32>,
+    },
+    Group {
+        objects: Vec<ObjectDescription>,
+    },
+    Csg {
+        operation: CsgOp,
+        left: Box<ObjectDescription>,
+        right: Box<ObjectDescription>,
+    },
+    Instance {
+        prototype: String,
+        #[serde(default)]
+        transform: TransformDescription,
+        #[serde(default)]
+        material: Option<String>,
+    },
+}
+
+// Built-in distance fields, see `SdfNode`.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+pub enum SdfDescription {
+    Sphere {
+        radius: f32,
+    },
+    Box {
+        half_size: [f32; 3],
+    },
+    Torus {
+        major_radius: f32,
+        minor_radius: f32,
+    },
+    Capsule {
+        height: f32,
+        radius: f32,
+    },
+    Translate {
+        offset: [f32; 3],
+        shape: Box<SdfDescription>,
+    },
+    Union {
+        left: Box<SdfDescription>,
+        right: Box<SdfDescription>,
+    },
+    Intersection {
+        left: Box<SdfDescription>,
+        right: Box<SdfDescription>,
+    },
+    Subtraction {
+        left: Box<SdfDescription>,
+        right: Box<SdfDescription>,
+    },
+    SmoothUnion {
+        left: Box<SdfDescription>,
+        right: Box<SdfDescription>,
+        smoothness: f32,
+    },
+    SmoothSubtraction {
+        left: Box<SdfDescription>,
+        right: Box<SdfDescription>,
+        smoothness: f32,
+    },
+    Round {
+        shape: Box<SdfDescription>,
+        radius: f32,
+    },
+    Repeat {
+        period: [f32; 3],
+        shape: Box<SdfDescription>,
+    },
+    Twist {
+        rate: f32,
+        shape: Box<SdfDescription>,
+    },
+}
+
+impl SdfDescription {
+    fn build(&self) -> SdfNode {
+        let node = |shape: &SdfDescription| Box::new(shape.build());
+        match self {
+            SdfDescription::Sphere { radius } => SdfNode::Sphere { radius: *radius },
+            SdfDescription::Box { half_size } => SdfNode::Box {
+                half_size: vec3(*half_size),
+            },
+            SdfDescription::Torus {
+                major_radius,
+                minor_radius,
+            } => SdfNode::Torus {
+                major_radius: *major_radius,
+                minor_radius: *minor_radius,
+            },
+            SdfDescription::Capsule { height, radius } => SdfNode::Capsule {
+                height: *height,
+                radius: *radius,
+            },
+            SdfDescription::Translate { offset, shape } => SdfNode::Translate {
+                offset: vec3(*offset),
+                node: node(shape),
+            },
+            SdfDescription::Union { left, right } => SdfNode::Union(node(left), node(right)),
+            SdfDescription::Intersection { left, right } => {
+                SdfNode::Intersection(node(left), node(right))
+            }
+            SdfDescription::Subtraction { left, right } => {
+                SdfNode::Subtraction(node(left), node(right))
+            }
+            SdfDescription::SmoothUnion {
+                left,
+                right,
+                smoothness,
+            } => SdfNode::SmoothUnion {
+                left: node(left),
+                right: node(right),
+                smoothness: *smoothness,
+            },
+            SdfDescription::SmoothSubtraction {
+                left,
+                right,
+                smoothness,
+            } => SdfNode::SmoothSubtraction {
+                left: node(left),
+                right: node(right),
+                smoothness: *smoothness,
+            },
+            SdfDescription::Round { shape, radius } => SdfNode::Round {
+                node: node(shape),
+                radius: *radius,
+            },
+            SdfDescription::Repeat { period, shape } => SdfNode::Repeat {
+                period: vec3(*period),
+                node: node(shape),
+            },
+            SdfDescription::Twist { rate, shape } => SdfNode::Twist {
+                rate: *rate,
+                node: node(shape),
+            },
+        }
+    }
+}
+
+// Applied in the order scale, rotate (around x, then y, then z, in degrees), translate.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TransformDescription {
+    pub scale: [f32; 3],
+    pub rotate: [f32; 3],
+    pub translate: [f32; 3],
+}
+
+impl Default for TransformDescription {
+    fn default() -> Self {
+        TransformDescription {
+            scale: [1.0, 1.0, 1.0],
+            rotate: [0.0, 0.0, 0.0],
+            translate: [0.0, 0.0, 0.0],
+        }
+    }
+}
+
+impl TransformDescription {
+    fn build(&self) -> Result<Transform, SceneError> {
+        let axes = [
+            Vec3::new(1.0, 0.0, 0.0),
+            Vec3::new(0.0, 1.0, 0.0),
+            Vec3::new(0.0, 0.0, 1.0),
+        ];
+        let scaling = Transform::scaling(vec3(self.scale)).ok_or_else(|| {
+            SceneError::InvalidValue("transform scale must be finite and not zero".into())
+        })?;
+        Ok(axes
+            .iter()
+            .zip(self.rotate)
+            .fold(scaling, |acc, (axis, degrees)| {
+                acc.then(&Transform::rotation(*axis, degrees.to_radians()))
+            })
+            .then(&Transform::translation(vec3(self.translate))))
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct LoadedScene {
+    pub scene: Scene,
+    pub camera: Camera,
+    pub settings: RenderSettings,
+}
+
+fn vec3(value: [f32; 3]) -> Vec3 {
+    Vec3::new(value[0], value[1], value[2])
+}
+
+impl SceneDescription {
+    pub fn from_json(text: &str) -> Result<SceneDescription, SceneError> {
+        serde_json::from_str(text).map_err(|error| SceneError::Parse(error.to_string()))
+    }
+
+    // `resolve` maps mesh paths to file contents, so the loader itself needs no filesystem.
+    pub fn build<F, E>(&self, mut resolve: F) -> Result<LoadedScene, SceneError>
+    where
+        F: FnMut(&str) -> Result<Vec<u8>, E>,
+        E: fmt::Display,
+    {
+        let camera = self.camera.build()?;
+        let mut settings = RenderSettings::new(self.camera.width, self.camera.height);
+        settings.background_color = vec3(self.render.background_color);
+        settings.recursion_limit = self.render.recursion_limit;
+        settings.integrator = self.render.integrator;
+        settings.spectral = self.render.spectral;
+
+        let mut builder = SceneBuilder::new();
+        for light in &self.lights {
+            builder.add_light(Light::new(vec3(light.position), light.intensity));
+        }
+
+        let mut textures = BTreeMap::new();
+        for (name, description) in &self.textures {
+            let texture = description.build(&mut resolve)?;
+            textures.insert(name.as_str(), builder.add_texture(texture));
+        }
+
+        let mut media = BTreeMap::new();
+        for (name, description) in &self.media {
+            media.insert(name.as_str(), builder.add_medium(description.build()?));
+        }
+        let atmosphere = match &self.atmosphere {
+            Some(name) => Some(
+                *media
+                    .get(name.as_str())
+                    .ok_or_else(|| SceneError::UnknownMedium(name.clone()))?,
+            ),
+            None => None,
+        };
+        builder.set_atmosphere(atmosphere)?;
+
+        if let Some(environment) = &self.environment {
+            let texture = |name: &String| {
+                textures
+                    .get(name.as_str())
+                    .copied()
+                    .ok_or_else(|| SceneError::UnknownTexture(name.clone()))
+            };
+            let projection = match (&environment.texture, &environment.faces) {
+                (Some(name), None) => Projection::LatLong(texture(name)?),
+                (None, Some(faces)) => {
+                    let handles = faces.iter().map(texture).collect::<Result<Vec<_>, _>>()?;
+                    Projection::CubeMap(handles.try_into().unwrap())
+                }
+                _ => {
+                    return Err(SceneError::InvalidValue(
+                        "environment needs either a texture or six faces".into(),
+                    ))
+                }
+            };
+            builder.set_environment(projection, environment.rotation.to_radians(), environment.intensity)?;
+        }
+
+        let mut materials = Materials {
+            descriptions: &self.materials,
+            textures: &textures,
+            media: &media,
+            handles: BTreeMap::new(),
+            pending: Vec::new(),
+        };
+        for name in self.materials.keys() {
+            materials.get(&mut builder, name)?;
+        }
+
+        let mut context = Context {
+            materials: &materials.handles,
+            prototypes: BTreeMap::new(),
+            resolve: &mut resolve,
+        };
+        for (name, object) in &self.prototypes {
+            let prototype = builder.prototype(|proto| context.add_object(proto, object))?;
+            context.prototypes.insert(name.as_str(), prototype);
+        }
+        for object in &self.objects {
+            context.add_object(&mut builder, object)?;
+        }
+
+        Ok(LoadedScene {
+            scene: builder.build(),
+            camera,
+            settings,
+        })
+    }
+}
+
+// Builds materials as they are needed, so that composite ones may refer to any other.
+struct Materials<'a> {
+    descriptions: &'a BTreeMap<String, MaterialDescription>,
+    textures: &'a BTreeMap<&'a str, TextureHandle>,
+    media: &'a BTreeMap<&'a str, MediumHandle>,
+    handles: BTreeMap<&'a str, MaterialHandle>,
+    // being built, to catch materials nesting in themselves
+    pending: Vec<&'a str>,
+}
+
+impl<'a> Materials<'a> {
+    fn get(&mut self, builder: &mut SceneBuilder, name: &str) -> Result<MaterialHandle, SceneError> {
+        if let Some(handle) = self.handles.get(name) {
+            return Ok(*handle);
+        }
+        let (name, description) = self
+            .descriptions
+            .get_key_value(name)
+            .ok_or_else(|| SceneError::UnknownMaterial(name.into()))?;
+        if self.pending.contains(&name.as_str()) {
+            return Err(SceneError::InvalidValue(format!("material {name:?} contains itself")));
+        }
+        self.pending.push(name);
+        let handle = self.build(builder, description);
+        self.pending.pop();
+        let handle = handle?;
+        self.handles.insert(name, handle);
+        Ok(handle)
+    }
+
+    fn build(
+        &mut self,
+        builder: &mut SceneBuilder,
+        description: &MaterialDescription,
+    ) -> Result<MaterialHandle, SceneError> {
+        let kinds = [
+            description.pbr.is_some(),
+            description.dielectric.is_some(),
+            description.subsurface.is_some(),
+            description.mix.is_some(),
+            description.layered.is_some(),
+        ];
+        if kinds.iter().filter(|kind| **kind).count() > 1 {
+            return Err(SceneError::InvalidValue(
+                "material can only be one of pbr, dielectric, subsurface, mix and layered".into(),
+            ));
+        }
+        if let Some(mix) = &description.mix {
+            let first = self.get(builder, &mix.materials[0])?;
+            let second = self.get(builder, &mix.materials[1])?;
+            let mask = mix.mask.as_ref().map(|name| self.texture(name)).transpose()?;
+            return Ok(builder.add_mix([first, second], mix.weight, mask)?);
+        }
+        if let Some(layered) = &description.layered {
+            let base = self.get(builder, &layered.base)?;
+            let coat = Coat {
+                ior: layered.ior,
+                roughness: layered.roughness,
+                thickness: layered.thickness,
+                absorption: vec3(layered.absorption),
+            };
+            return Ok(builder.add_layered(base, coat)?);
+        }
+
+        let mut material = if let Some(pbr) = &description.pbr {
+            Material::pbr(pbr.build()?)
+        } else if let Some(dielectric) = &description.dielectric {
+            Material::dielectric(dielectric.build(self.media)?)
+        } else if let Some(subsurface) = &description.subsurface {
+            Material::subsurface(subsurface.build()?)
+        } else {
+            Material::new(
+                description.refract_index,
+                description.albedo,
+                vec3(description.diffuse_color),
+                description.specular_exponent,
+            )
+        };
+        let texture = |name: &Option<String>| {
+            name.as_ref()
+                .map(|name| self.texture(name).map(|handle| handle.idx()))
+                .transpose()
+        };
+        material.textures = MaterialTextures {
+            diffuse: texture(&description.diffuse_texture)?,
+            specular: texture(&description.specular_texture)?,
+            roughness: texture(&description.roughness_texture)?,
+            metallic: texture(&description.metallic_texture)?,
+            reflection: texture(&description.reflection_texture)?,
+            normal: texture(&description.normal_texture)?,
+            bump: texture(&description.bump_texture)?,
+            bump_scale: description.bump_scale,
+        };
+        if description.emission.iter().any(|channel| *channel < 0.0) || description.emission_strength < 0.0 {
+            return Err(SceneError::InvalidValue("emission must not be negative".into()));
+        }
+        material.emission = vec3(description.emission) * description.emission_strength;
+        if let Some(dispersion) = description.dispersion {
+            let ior = dispersion.ior(D_LINE);
+            if ior <= 0.0 || ior.is_nan() {
+                return Err(SceneError::InvalidValue("dispersion gives no valid ior".into()));
+            }
+            material.dispersion = Some(dispersion);
+        }
+        Ok(builder.add_material(material)?)
+    }
+
+    fn texture(&self, name: &str) -> Result<TextureHandle, SceneError> {
+        self.textures
+            .get(name)
+            .copied()
+            .ok_or_else(|| SceneError::UnknownTexture(name.into()))
+    }
+}
+
+impl TextureDescription {
+    fn build<F, E>(&self, resolve: &mut F) -> Result<Texture, SceneError>
+    where
+        F: FnMut(&str) -> Result<Vec<u8>, E>,
+        E: fmt::Display,
+    {
+        match self {
+            TextureDescription::Image {
+                width,
+                height,
+                texels,
+                wrap,
+                filter,
+            } => {
+                let texels = texels.iter().copied().map(vec3).collect();
+                let image = ImageTexture::new(*width, *height, texels, *wrap, *filter)
+                    .ok_or_else(|| {
+                        SceneError::InvalidValue(
+                            "texture needs one texel per pixel and a size other than zero".into(),
+                        )
+                    })?;
+                Ok(Texture::Image(image))
+            }
+            TextureDescription::File {
+                path,
+                color_space,
+                wrap,
+                filter,
+            } => {
+                let data = resolve(path).map_err(|error| SceneError::Resolve {
+                    path: path.clone(),
+                    message: error.to_string(),
+                })?;
+                #[cfg(feature = "std")]
+                let image = crate::loader::image::load_image(&data, *color_space, *wrap, *filter);
+                #[cfg(not(feature = "std"))]
+                let image = {
+                    let _ = (data, color_space, wrap, filter);
+                    Err(LoadError::UnsupportedFormat(path.clone()))
+                };
+                image
+                    .map(Texture::Image)
+                    .map_err(|error| SceneError::Texture {
+                        path: path.clone(),
+                        error,
+                    })
+            }
+            TextureDescription::Procedural {
+                pattern,
+                space,
+                scale,
+                colors,
+            } => {
+                if *scale <= 0.0 || scale.is_nan() {
+                    return Err(SceneError::InvalidValue(
+                        "texture scale must be positive".into(),
+                    ));
+                }
+                Ok(Texture::Procedural(ProceduralTexture::new(
+                    *pattern,
+                    *space,
+                    *scale,
+                    [vec3(colors[0]), vec3(colors[1])],
+                )))
+            }
+        }
+    }
+}
+
+impl PbrDescription {
+    fn build(&self) -> Result<Pbr, SceneError> {
+        let unit = 0.0..=1.0;
+        if !unit.contains(&self.metallic) || !unit.contains(&self.roughness) {
+            return Err(SceneError::InvalidValue(
+                "metallic and roughness must be within [0, 1]".into(),
+            ));
+        }
+        if self.specular < 0.0 {
+            return Err(SceneError::InvalidValue("specular must not be negative".into()));
+        }
+        Ok(Pbr {
+            base_color: vec3(self.base_color),
+            metallic: self.metallic,
+            roughness: self.roughness,
+            specular: self.specular,
+        })
+    }
+}
+
+impl DielectricDescription {
+    fn build(&self, media: &BTreeMap<&str, MediumHandle>) -> Result<Dielectric, SceneError> {
+        if self.ior <= 0.0 || self.ior.is_nan() {
+            return Err(SceneError::InvalidValue("ior must be positive".into()));
+        }
+        if !(0.0..=1.0).contains(&self.roughness) {
+            return Err(SceneError::InvalidValue("roughness must be within [0, 1]".into()));
+        }
+        if self.absorption.iter().any(|coefficient| *coefficient < 0.0) {
+            return Err(SceneError::InvalidValue("absorption must not be negative".into()));
+        }
+        Ok(Dielectric {
+            ior: self.ior,
+            priority: self.priority,
+            roughness: self.roughness,
+            absorption: vec3(self.absorption),
+            medium: match &self.medium {
+                Some(name) => Some(
+                    media
+                        .get(name.as_str())
+                        .ok_or_else(|| SceneError::UnknownMedium(name.clone()))?
+                        .idx(),
+                ),
+                None => None,
+            },
+        })
+    }
+}
+
+impl SubsurfaceDescription {
+    fn build(&self) -> Result<Subsurface, SceneError> {
+        if self.ior <= 0.0 || self.ior.is_nan() {
+            return Err(SceneError::InvalidValue("ior must be positive".into()));
+        }
+        if self.color.iter().any(|channel| !(0.0..=1.0).contains(channel)) {
+            return Err(SceneError::InvalidValue("subsurface color must be within [0, 1]".into()));
+        }
+        if self.radius.iter().any(|radius| *radius <= 0.0 || radius.is_nan()) {
+            return Err(SceneError::InvalidValue("subsurface radius must be positive".into()));
+        }
+        if !(self.anisotropy > -1.0 && self.anisotropy < 1.0) {
+            return Err(SceneError::InvalidValue("anisotropy must be in (-1, 1)".into()));
+        }
+        Ok(Subsurface {
+            ior: self.ior,
+            color: vec3(self.color),
+            radius: vec3(self.radius),
+            anisotropy: self.anisotropy,
+        })
+    }
+}
+
+impl MediumDescription {
+    fn build(&self) -> Result<Medium, SceneError> {
+        if self.extinction < 0.0 || self.extinction.is_nan() {
+            return Err(SceneError::InvalidValue("extinction must not be negative".into()));
+        }
+        if self.albedo.iter().any(|channel| !(0.0..=1.0).contains(channel)) {
+            return Err(SceneError::InvalidValue("medium albedo must be within [0, 1]".into()));
+        }
+        if !(self.anisotropy > -1.0 && self.anisotropy < 1.0) {
+            return Err(SceneError::InvalidValue("anisotropy must be in (-1, 1)".into()));
+        }
+        let mut medium = Medium::new(self.extinction, vec3(self.albedo), self.anisotropy);
+        if let Some(grid) = &self.density {
+            let grid = Grid::new(vec3(grid.min), vec3(grid.max), grid.resolution, grid.values.clone())
+                .ok_or_else(|| {
+                    SceneError::InvalidValue(
+                        "density grid needs one value, not negative, per sample of a non empty box".into(),
+                    )
+                })?;
+            medium.density = Density::Grid(grid);
+        }
+        Ok(medium)
+    }
+}
+
+impl CameraDescription {
+    fn build(&self) -> Result<Camera, SceneError> {
+        let invalid = |message: &str| Err(SceneError::InvalidValue(message.into()));
+        if self.width == 0 || self.height == 0 {
+            return invalid("camera resolution is empty");
+        }
+
+        let origin = vec3(self.origin);
+        let view_dir = match (self.view_dir, self.look_at) {
+            (Some(dir), None) => vec3(dir),
+            (None, Some(target)) => vec3(target) - origin,
+            (None, None) => Vec3::new(0.0, 0.0, -1.0),
+            (Some(_), Some(_)) => return invalid("camera has both `view_dir` and `look_at`"),
+        };
+        Camera::new(origin, view_dir, self.fov).ok_or_else(|| {
+            SceneError::InvalidValue("camera fov must be in (0, pi) and its view direction not zero".into())
+        })
+    }
+}
+
+struct Context<'a, F> {
+    materials: &'a BTreeMap<&'a str, MaterialHandle>,
+    prototypes: BTreeMap<&'a str, Arc<Entity>>,
+    resolve: &'a mut F,
+}
+
+impl<F, E> Context<'_, F>
+where
+    F: FnMut(&str) -> Result<Vec<u8>, E>,
+    E: fmt::Display,
+{
+    fn material(&self, name: &str) -> Result<MaterialHandle, SceneError> {
+        self.materials
+            .get(name)
+            .copied()
+            .ok_or_else(|| SceneError::UnknownMaterial(name.into()))
+    }
+
+    fn add_object(
+        &mut self,
+        builder: &mut SceneBuilder,
+        object: &ObjectDescription,
+    ) -> Result<(), SceneError> {
+        match object {
+            ObjectDescription::Sphere {
+                center,
+                radius,
+                material,
+            } => {
+                builder.add_sphere(vec3(*center), *radius, self.material(material)?)?;
+            }
+            ObjectDescription::Plane {
+                point,
+                normal,
+                material,
+            } => {
+                builder.add_plane(vec3(*point), vec3(*normal), self.material(material)?)?;
+            }
+            ObjectDescription::Triangle { points, material } => {
+                builder.add_triangle(points.map(vec3), self.material(material)?)?;
+            }
+            ObjectDescription::Cuboid { min, max, material } => {
+                builder.add_cuboid(vec3(*min), vec3(*max), self.material(material)?)?;
+            }
+            ObjectDescription::Quad {
+                corner,
+                edge_u,
+                edge_v,
+                material,
+            } => {
+                let material = self.material(material)?;
+                builder.add_quad(vec3(*corner), vec3(*edge_u), vec3(*edge_v), material)?;
+            }
+            ObjectDescription::Disk {
+                center,
+                normal,
+                radius,
+                material,
+            } => {
+                let material = self.material(material)?;
+                builder.add_disk(vec3(*center), vec3(*normal), *radius, material)?;
+            }
+            ObjectDescription::Cylinder {
+                base,
+                top,
+                radius,
+                open,
+                material,
+            } => {
+                let material = self.material(material)?;
+                builder.add_cylinder(vec3(*base), vec3(*top), *radius, !open, material)?;
+            }
+            ObjectDescription::Cone {
+                base,
+                top,
+                base_radius,
+                top_radius,
+                open,
+                material,
+            } => {
+                let material = self.material(material)?;
+                let radii = (*base_radius, *top_radius);
+                builder.add_cone(vec3(*base), vec3(*top), radii, !open, material)?;
+            }
+            ObjectDescription::Capsule {
+                base,
+                top,
+                radius,
+                material,
+            } => {
+                let material = self.material(material)?;
+                builder.add_capsule(vec3(*base), vec3(*top), *radius, material)?;
+            }
+            ObjectDescription::Torus {
+                center,
+                axis,
+                major_radius,
+                minor_radius,
+                material,
+            } => {
+                let material = self.material(material)?;
+                let radii = (*major_radius, *minor_radius);
+                builder.add_torus(vec3(*center), vec3(*axis), radii, material)?;
+            }
+            ObjectDescription::Sdf {
+                shape,
+                min,
+                max,
+                material,
+            } => {
+                let bounds = BoundingBox::from_min_max(vec3(*min), vec3(*max));
+                builder.add_sdf(shape.build(), bounds, self.material(material)?)?;
+            }
+            ObjectDescription::Mesh {
+                path,
+                format,
+                material,
+                smooth,
+                crease_angle,
+            } => {
+                let material = self.material(material)?;
+                let shading = if *smooth {
+                    Shading::Smooth {
+                        crease_angle: crease_angle.unwrap_or(180.0).to_radians(),
+                    }
+                } else {
+                    Shading::Flat
+                };
+                let mesh_error = |error| SceneError::Mesh {
+                    path: path.clone(),
+                    error,
+                };
+                let format = format
+                    .or_else(|| MeshFormat::from_path(path))
+                    .ok_or_else(|| mesh_error(LoadError::UnsupportedFormat(path.clone())))?;
+                let data = (self.resolve)(path).map_err(|error| SceneError::Resolve {
+                    path: path.clone(),
+                    message: error.to_string(),
+                })?;
+                builder
+                    .load_mesh(format, &data, material, shading)
+                    .map_err(|error| match error {
+                        BuildError::Mesh(error) => mesh_error(error),
+                        error => SceneError::Build(error),
+                    })?;
+            }
+            ObjectDescription::Group { objects } => {
+                builder.group(|group| {
+                    objects
+                        .iter()
+                        .try_for_each(|object| self.add_object(group, object))
+                })?;
+            }
+            ObjectDescription::Csg {
+                operation,
+                left,
+                right,
+            } => {
+                let left = builder.prototype(|proto| self.add_object(proto, left))?;
+                let right = builder.prototype(|proto| self.add_object(proto, right))?;
+                builder.add_csg(*operation, &left, &right)?;
+            }
+            ObjectDescription::Instance {
+                prototype,
+                transform,
+                material,
+            } => {
+                let prototype = self
+                    .prototypes
+                    .get(prototype.as_str())
+                    .ok_or_else(|| SceneError::UnknownPrototype(prototype.clone()))?;
+                let material = match material {
+                    Some(name) => Some(self.material(name)?),
+                    None => None,
+                };
+                builder.add_instance(prototype, transform.build()?, material)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SCENE: &str = r#"{
+        "camera": { "width": 4, "height": 3, "fov": 1.0, "look_at": [0, 0, -10] },
+        "render": { "background_color": [0.2, 0.7, 0.8], "recursion_limit": 3 },
+        "materials": {
+            "ivory": { "albedo": [0.6, 0.3, 0.1, 0.0], "diffuse_color": [0.4, 0.4, 0.3], "specular_exponent": 50 },
+            "glass": { "refract_index": 1.5, "albedo": [0.0, 0.5, 0.1, 0.8], "diffuse_color": [0.6, 0.7, 0.8] }
+        },
+        "lights": [ { "position": [-20, 20, 20], "intensity": 1.5 } ],
+        "prototypes": {
+            "ball": { "type": "sphere", "center": [0, 0, 0], "radius": 1, "material": "ivory" }
+        },
+        "objects": [
+            { "type": "instance", "prototype": "ball", "transform": { "translate": [3, 0, -10] } },
+            { "type": "instance", "prototype": "ball", "material": "glass", "transform": { "scale": [2, 2, 2] } },
+            { "type": "plane", "point": [0, -4, 0], "normal": [0, 1, 0], "material": "ivory" },
+            { "type": "group", "objects": [
+                { "type": "sphere", "center": [0, 0, -10], "radius": 2, "material": "glass" },
+                { "type": "mesh", "path": "tri.obj", "material": "glass" }
+            ] }
+        ]
+    }"#;
+
+    fn resolve(path: &str) -> Result<Vec<u8>, &'static str> {
+        match path {
+            "tri.obj" => Ok(b"v 0 0 0\nv 1 0 0\nv 0 1 0\nf 1 2 3\n".to_vec()),
+            _ => Err("not found"),
+        }
+    }
+
+    #[test]
+    fn test_build_scene() {
+        let loaded = SceneDescription::from_json(SCENE).unwrap().build(resolve).unwrap();
+        let scene = &loaded.scene;
+
+        assert_eq!(Vec3::new(0.0, 0.0, -1.0), loaded.camera.view_dir);
+        assert_eq!(3, loaded.settings.recursion_limit);
+        assert_eq!(2, scene.geometry().material_buf.materials.len());
+        assert_eq!(3, scene.geometry().vec_buf.points.len());
+        assert_eq!(1, scene.lights().len());
+        assert!(matches!(&scene.root().entities[3], Entity::Group(group) if group.entities.len() == 2));
+        assert!(matches!(&scene.root().entities[1], Entity::Instance(instance) if instance.material.is_some()));
+    }
+
+    #[test]
+    fn test_build_scene_errors() {
+        let unknown = SCENE.replace(r#""material": "ivory" }"#, r#""material": "gold" }"#);
+        let error = SceneDescription::from_json(&unknown).unwrap().build(resolve).unwrap_err();
+        assert_eq!(SceneError::UnknownMaterial("gold".into()), error);
+
+        let foggy = SCENE.replace(r#""lights""#, r#""atmosphere": "fog", "lights""#);
+        let error = SceneDescription::from_json(&foggy).unwrap().build(resolve).unwrap_err();
+        assert_eq!(SceneError::UnknownMedium("fog".into()), error);
+
+        let sky = SCENE.replace(r#""lights""#, r#""environment": { "texture": "sky" }, "lights""#);
+        let error = SceneDescription::from_json(&sky).unwrap().build(resolve).unwrap_err();
+        assert_eq!(SceneError::UnknownTexture("sky".into()), error);
+
+        let wide = SCENE.replace(r#""fov": 1.0"#, r#""fov": 4.0"#);
+        let error = SceneDescription::from_json(&wide).unwrap().build(resolve).unwrap_err();
+        assert!(matches!(error, SceneError::InvalidValue(_)));
+
+        let flat = SCENE.replace(r#""scale": [2, 2, 2]"#, r#""scale": [2, 0, 2]"#);
+        let error = SceneDescription::from_json(&flat).unwrap().build(resolve).unwrap_err();
+        assert!(matches!(error, SceneError::InvalidValue(_)));
+
+        let nested = SCENE.replace(
+            r#""ivory": {"#,
+            r#""loop": { "mix": { "materials": ["ivory", "loop"] } }, "ivory": {"#,
+        );
+        let error = SceneDescription::from_json(&nested).unwrap().build(resolve).unwrap_err();
+        assert!(matches!(error, SceneError::InvalidValue(_)));
+
+        let missing = SCENE.replace("tri.obj", "duck.obj");
+        let error = SceneDescription::from_json(&missing).unwrap().build(resolve).unwrap_err();
+        assert!(matches!(error, SceneError::Resolve { .. }));
+
+        assert!(matches!(
+            SceneDescription::from_json("{}"),
+            Err(SceneError::Parse(_))
+        ));
+    }
+}
@@ -0,0 +1,352 @@
+use alloc::vec::Vec;
+use core::f32::consts::PI;
+
+use crate::entity::group::Group;
+use crate::entity::triangle::Triangle;
+use crate::entity::Entity;
+use crate::material::{Material, MaterialKind, MAX_NESTING};
+use crate::sampling::Rng;
+use crate::scene::Geometry;
+use crate::transform::Transform;
+use crate::vec2::Vec2;
+use crate::vec3::{cross_product, dot_product, Vec3};
+
+// Emissive surface in world space that lights can be sampled on.
+#[derive(Debug, Clone, Copy)]
+pub enum Emitter {
+    Sphere {
+        center: Vec3,
+        radius: f32,
+        emission: Vec3,
+    },
+    Triangle {
+        points: [Vec3; 3],
+        emission: Vec3,
+    },
+}
+
+impl Emitter {
+    pub fn area(&self) -> f32 {
+        match self {
+            Emitter::Sphere { radius, .. } => 4.0 * PI * radius * radius,
+            Emitter::Triangle {
+                points: [a, b, c], ..
+            } => 0.5 * cross_product(&(*b - *a), &(*c - *a)).norm(),
+        }
+    }
+
+    pub fn emission(&self) -> Vec3 {
+        match self {
+            Emitter::Sphere { emission, .. } | Emitter::Triangle { emission, .. } => *emission,
+        }
+    }
+
+    // Uniformly distributed point on the surface and the normal there.
+    fn sample_point(&self, sample: Vec2) -> (Vec3, Vec3) {
+        match self {
+            Emitter::Sphere { center, radius, .. } => {
+                let z = 1.0 - 2.0 * sample.x;
+                let ring = libm::sqrtf((1.0 - z * z).max(0.0));
+                let (sin, cos) = libm::sincosf(2.0 * PI * sample.y);
+                let normal = Vec3::new(ring * cos, ring * sin, z);
+                (*center + normal * *radius, normal)
+            }
+            Emitter::Triangle {
+                points: [a, b, c], ..
+            } => {
+                let root = libm::sqrtf(sample.x);
+                let (u, v) = (1.0 - root, sample.y * root);
+                let point = *a * u + *b * v + *c * (1.0 - u - v);
+                (point, cross_product(&(*b - *a), &(*c - *a)).normalized())
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct EmitterSample {
+    pub point: Vec3,
+    pub emission: Vec3,
+    // over solid angle as seen from the shaded point
+    pub pdf: f32,
+}
+
+// Every emissive sphere and triangle of a scene, with quads split in two and instances baked
+// in, picked in proportion to their area so that points are uniform over the lit surface.
+#[derive(Debug, Clone, Default)]
+pub struct Emitters {
+    pub emitters: Vec<Emitter>,
+    // running total of the areas
+    cdf: Vec<f32>,
+    // emissive surfaces of other shapes are only found by chance, which rules out sampling
+    // any of them since weighting the two strategies needs the density of both everywhere
+    pub complete: bool,
+}
+
+impl Emitters {
+    pub fn gather(geometry: &Geometry, root: &Group) -> Emitters {
+        let mut emitters = Emitters {
+            complete: true,
+            ..Default::default()
+        };
+        for entity in &root.entities {
+            emitters.visit(geometry, entity, &Transform::identity(), None);
+        }
+        let mut total = 0.0;
+        emitters.cdf = emitters
+            .emitters
+            .iter()
+            .map(|emitter| {
+                total += emitter.area();
+                total
+            })
+            .collect();
+        emitters
+    }
+
+    pub fn sampled(&self) -> bool {
+        self.complete && !self.emitters.is_empty()
+    }
+
+    fn total_area(&self) -> f32 {
+        self.cdf.last().copied().unwrap_or(0.0)
+    }
+
+    // Point on the emitters to light `from` with, `None` when seen edge on.
+    pub fn sample(&self, from: &Vec3, rng: &mut Rng) -> Option<EmitterSample> {
+        if !self.sampled() {
+            return None;
+        }
+        let target = rng.next_f32() * self.total_area();
+        let idx = self.cdf.partition_point(|area| *area < target);
+        let emitter = self.emitters[idx.min(self.emitters.len() - 1)];
+        let (point, normal) = emitter.sample_point(rng.next_vec2());
+        let pdf = self.pdf(from, &point, &normal);
+        (pdf > 0.0).then_some(EmitterSample {
+            point,
+            emission: emitter.emission(),
+            pdf,
+        })
+    }
+
+    // Density of `sample` picking `point` as seen from `from`, zero unless sampled at all.
+    pub fn pdf(&self, from: &Vec3, point: &Vec3, normal: &Vec3) -> f32 {
+        if !self.sampled() {
+            return 0.0;
+        }
+        let to_point = *point - *from;
+        let dist2 = dot_product(&to_point, &to_point);
+        let cos = libm::fabsf(dot_product(normal, &to_point)) / libm::sqrtf(dist2);
+        if cos <= 0.0 {
+            return 0.0;
+        }
+        dist2 / (cos * self.total_area())
+    }
+
+    fn visit(
+        &mut self,
+        geometry: &Geometry,
+        entity: &Entity,
+        transform: &Transform,
+        replaced: Option<&Material>,
+    ) {
+        let material = |own: &Material| *replaced.unwrap_or(own);
+        match entity {
+            Entity::Sphere(sphere) => {
+                let material = material(&sphere.material);
+                if !material.emissive() {
+                    return self.unsampled(geometry, &material);
+                }
+                // other transforms make an ellipsoid of it
+                match uniform_scale(transform) {
+                    Some(scale) => self.emitters.push(Emitter::Sphere {
+                        center: transform.point(&sphere.center),
+                        radius: sphere.radius() * scale,
+                        emission: material.emission,
+                    }),
+                    None => self.complete = false,
+                }
+            }
+            Entity::Triangle(triangle) => {
+                self.add_triangle(geometry, triangle, transform, replaced)
+            }
+            Entity::Model(model) => {
+                for triangle in &model.triangles {
+                    self.add_triangle(geometry, triangle, transform, replaced);
+                }
+            }
+            Entity::Quad(quad) => {
+                let material = material(&quad.material);
+                let corners = [
+                    quad.corner,
+                    quad.corner + quad.edge_u,
+                    quad.corner + quad.edge_u + quad.edge_v,
+                    quad.corner + quad.edge_v,
+                ]
+                .map(|corner| transform.point(&corner));
+                self.add_points(geometry, [corners[0], corners[1], corners[2]], &material);
+                self.add_points(geometry, [corners[0], corners[2], corners[3]], &material);
+            }
+            Entity::Group(group) => {
+                for entity in &group.entities {
+                    self.visit(geometry, entity, transform, replaced);
+                }
+            }
+            // the outermost instance decides the material, as when intersecting
+            Entity::Instance(instance) => self.visit(
+                geometry,
+                &instance.entity,
+                &instance.transform.then(transform),
+                replaced.or(instance.material.as_ref()),
+            ),
+            // CSG trims the surfaces of its operands
+            Entity::Csg(csg) => {
+                let mut operands = Emitters {
+                    complete: true,
+                    ..Default::default()
+                };
+                operands.visit(geometry, &csg.left, transform, replaced);
+                operands.visit(geometry, &csg.right, transform, replaced);
+                if !operands.emitters.is_empty() || !operands.complete {
+                    self.complete = false;
+                }
+            }
+            Entity::Plane(obj) => self.unsampled(geometry, &material(&obj.material)),
+            Entity::Cuboid(obj) => self.unsampled(geometry, &material(&obj.material)),
+            Entity::Disk(obj) => self.unsampled(geometry, &material(&obj.material)),
+            Entity::Cylinder(obj) => self.unsampled(geometry, &material(&obj.material)),
+            Entity::Cone(obj) => self.unsampled(geometry, &material(&obj.material)),
+            Entity::Capsule(obj) => self.unsampled(geometry, &material(&obj.material)),
+            Entity::Torus(obj) => self.unsampled(geometry, &material(&obj.material)),
+            Entity::Sdf(obj) => self.unsampled(geometry, &material(&obj.material)),
+        }
+    }
+
+    fn add_triangle(
+        &mut self,
+        geometry: &Geometry,
+        triangle: &Triangle,
+        transform: &Transform,
+        replaced: Option<&Material>,
+    ) {
+        let material = replaced.unwrap_or(geometry.material_buf.load(triangle.material));
+        let points = triangle
+            .points
+            .map(|idx| transform.point(geometry.vec_buf.load(idx)));
+        self.add_points(geometry, points, material);
+    }
+
+    fn add_points(&mut self, geometry: &Geometry, points: [Vec3; 3], material: &Material) {
+        let emitter = Emitter::Triangle {
+            points,
+            emission: material.emission,
+        };
+        if !material.emissive() {
+            self.unsampled(geometry, material);
+        } else if emitter.area() > 0.0 {
+            self.emitters.push(emitter);
+        }
+    }
+
+    fn unsampled(&mut self, geometry: &Geometry, material: &Material) {
+        if may_emit(geometry, material, 0) {
+            self.complete = false;
+        }
+    }
+}
+
+// Whether the material, or a part of it when composite, emits light.
+fn may_emit(geometry: &Geometry, material: &Material, nesting: usize) -> bool {
+    let part = |idx| nesting < MAX_NESTING && may_emit(geometry, geometry.material_buf.load(idx), nesting + 1);
+    match material.kind {
+        MaterialKind::Mix(mix) => mix.materials.into_iter().any(part),
+        MaterialKind::Layered(layered) => part(layered.base),
+        _ => material.emissive(),
+    }
+}
+
+// Scale factor of a transform made of rotations, translations and uniform scaling only.
+fn uniform_scale(transform: &Transform) -> Option<f32> {
+    let axes = [
+        Vec3::new(1.0, 0.0, 0.0),
+        Vec3::new(0.0, 1.0, 0.0),
+        Vec3::new(0.0, 0.0, 1.0),
+    ]
+    .map(|axis| transform.vector(&axis));
+    let scale = axes[0].norm();
+    let tolerance = 1e-4 * scale;
+    let lengths_match = axes
+        .iter()
+        .all(|axis| libm::fabsf(axis.norm() - scale) <= tolerance);
+    let orthogonal =
+        (0..3).all(|i| libm::fabsf(dot_product(&axes[i], &axes[(i + 1) % 3])) <= tolerance * scale);
+    (lengths_match && orthogonal).then_some(scale)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::builder::SceneBuilder;
+    use crate::material::Mix;
+    use crate::utils::MaterialIdx;
+
+    #[test]
+    fn test_gather_emitters() {
+        let scene = |stretch: Vec3| {
+            let mut builder = SceneBuilder::new();
+            let lamp = builder
+                .add_material(Material {
+                    emission: Vec3::new(1.0, 1.0, 1.0),
+                    ..Default::default()
+                })
+                .unwrap();
+            let matte = builder.add_material(Default::default()).unwrap();
+            let (x, y, z) = (
+                Vec3::new(2.0, 0.0, 0.0),
+                Vec3::new(0.0, 2.0, 0.0),
+                Vec3::new(0.0, 0.0, 1.0),
+            );
+            builder.add_quad(y, x, z, lamp).unwrap();
+            builder.add_sphere(Default::default(), 1.0, matte).unwrap();
+            let bulb = builder
+                .prototype(|proto| proto.add_sphere(Default::default(), 0.5, lamp))
+                .unwrap();
+            let transform = Transform::scaling(stretch)
+                .unwrap()
+                .then(&Transform::translation(x * 3.0));
+            builder.add_instance(&bulb, transform, None).unwrap();
+            builder.build()
+        };
+
+        let lit = scene(Vec3::new(2.0, 2.0, 2.0));
+        let emitters = lit.emitters();
+        assert!(emitters.sampled());
+        assert_eq!(3, emitters.emitters.len());
+        assert!((emitters.total_area() - (2.0 + 4.0 * PI)).abs() < 1e-4);
+        // points are spread evenly over the whole emitting area
+        let mut rng = Rng::new(1);
+        let from = Vec3::new(1.0, 0.0, 0.5);
+        let on_quad = (0..1000)
+            .filter_map(|_| emitters.sample(&from, &mut rng))
+            .filter(|sample| libm::fabsf(sample.point.y - 2.0) < 1e-4)
+            .count();
+        assert!((100..220).contains(&on_quad));
+
+        // an ellipsoid can not be sampled
+        assert!(!scene(Vec3::new(1.0, 2.0, 1.0)).emitters().sampled());
+    }
+
+    #[test]
+    fn test_may_emit_self_nesting() {
+        // a mix of itself is followed no deeper than `MAX_NESTING`
+        let mut geometry = Geometry::default();
+        let idx = geometry.material_buf.materials.len() as MaterialIdx;
+        let looped = Material::mix(Mix {
+            materials: [idx, idx],
+            weight: 0.5,
+            mask: None,
+        });
+        geometry.push_material(looped);
+        assert!(!may_emit(&geometry, &looped, 0));
+    }
+}
@@ -4,6 +4,7 @@ use crate::ray::Ray;
 use core::ops::Index;
 
 #[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct BoundingBox {
     min: Vec3,
     max: Vec3,
@@ -79,7 +80,61 @@ impl BoundingBox {
         }
     }
 
-    pub fn ray_check_intersect_standard(&self, ray: Ray) -> bool {
+    pub fn from_min_max(min: Vec3, max: Vec3) -> BoundingBox {
+        BoundingBox { min, max }
+    }
+
+    // Tight box around a flat disk with a normalized `normal`.
+    pub fn from_disk(center: Vec3, normal: Vec3, radius: f32) -> BoundingBox {
+        let extent = |n: f32| radius * libm::sqrtf((1.0 - n * n).max(0.0));
+        let extent = Vec3::new(extent(normal.x), extent(normal.y), extent(normal.z));
+        BoundingBox::from_min_max(center - extent, center + extent)
+    }
+
+    pub fn min(&self) -> Vec3 {
+        self.min
+    }
+
+    pub fn max(&self) -> Vec3 {
+        self.max
+    }
+
+    pub fn union(&self, other: &BoundingBox) -> BoundingBox {
+        BoundingBox::new(&[self.min, self.max, other.min, other.max])
+    }
+
+    // Overlap of the two boxes, flattened onto a face of it when they are apart.
+    pub fn intersection(&self, other: &BoundingBox) -> BoundingBox {
+        let low = Vec3::new(
+            max(self.min.x, other.min.x),
+            max(self.min.y, other.min.y),
+            max(self.min.z, other.min.z),
+        );
+        let high = Vec3::new(
+            max(low.x, min(self.max.x, other.max.x)),
+            max(low.y, min(self.max.y, other.max.y)),
+            max(low.z, min(self.max.z, other.max.z)),
+        );
+        BoundingBox::from_min_max(low, high)
+    }
+
+    pub fn expanded(&self, amount: f32) -> BoundingBox {
+        let amount = Vec3::new(amount, amount, amount);
+        BoundingBox::from_min_max(self.min - amount, self.max + amount)
+    }
+
+    pub fn corners(&self) -> [Vec3; 8] {
+        [0, 1, 2, 3, 4, 5, 6, 7].map(|idx| {
+            Vec3::new(
+                self[idx & 1].x,
+                self[(idx >> 1) & 1].y,
+                self[(idx >> 2) & 1].z,
+            )
+        })
+    }
+
+    // Distances along the ray where it enters and leaves the slabs, the box is missed if near > far.
+    pub fn ray_range(&self, ray: &Ray) -> (f32, f32) {
         let mut ray_min = (self[ray.sign_x].x - ray.from.x) * ray.inv_dir.x;
         let mut ray_max = (self[1 - ray.sign_x].x - ray.from.x) * ray.inv_dir.x;
 
@@ -95,6 +150,11 @@ impl BoundingBox {
         ray_min = max(ray_min, z_min);
         ray_max = min(ray_max, z_max);
 
+        (ray_min, ray_max)
+    }
+
+    pub fn ray_check_intersect_standard(&self, ray: Ray) -> bool {
+        let (ray_min, ray_max) = self.ray_range(&ray);
         max(ray_min, 0.0) <= ray_max
     }
 }
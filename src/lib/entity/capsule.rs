@@ -0,0 +1,153 @@
+use core::f32::consts::PI;
+
+use crate::entity::bounding_box::BoundingBox;
+use crate::entity::cone::revolution_crossings;
+use crate::frame::Frame;
+use crate::intersect::{nearest_crossing, Crossings, Hit, Intersect};
+use crate::material::Material;
+use crate::ray::Ray;
+use crate::scene::Geometry;
+use crate::solver::solve_quadratic;
+use crate::vec2::Vec2;
+use crate::vec3::{dot_product, Vec3};
+
+// All points within `radius` of the segment from `base` to `base + frame.normal * height`.
+// uv is (angle / 2pi, position along the whole capsule length).
+#[derive(Debug, Copy, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Capsule {
+    pub base: Vec3,
+    pub frame: Frame,
+    pub height: f32,
+    pub radius: f32,
+    pub material: Material,
+}
+
+impl Capsule {
+    // `None` unless the radius and the height are positive.
+    pub fn new(base: Vec3, top: Vec3, radius: f32, material: Material) -> Option<Capsule> {
+        let height = (top - base).norm();
+        (height > 0.0 && radius > 0.0).then(|| Capsule {
+            base,
+            frame: Frame::from_normal(&(top - base)),
+            height,
+            radius,
+            material,
+        })
+    }
+
+    pub fn bounding_box(&self) -> BoundingBox {
+        let top = self.base + self.frame.normal * self.height;
+        BoundingBox::new(&[self.base, top]).expanded(self.radius)
+    }
+}
+
+impl Crossings for Capsule {
+    fn crossings(&self, ray: &Ray, visit: &mut dyn FnMut(f32, Vec3, Vec2)) {
+        let origin = self.frame.to_local(&(ray.from - self.base));
+        let dir = self.frame.to_local(&ray.dir);
+        let length = self.height + 2.0 * self.radius;
+        let mut visit_local =
+            |dist: f32, normal: Vec3, uv: Vec2| visit(dist, self.frame.to_world(&normal), uv);
+
+        revolution_crossings(
+            origin,
+            dir,
+            self.height,
+            (self.radius, self.radius),
+            false,
+            &mut |dist, normal, uv| {
+                let v = (uv.y * self.height + self.radius) / length;
+                visit_local(dist, normal, Vec2::new(uv.x, v))
+            },
+        );
+
+        // hemispherical ends, each only valid beyond its end of the segment
+        for (center, side) in [(0.0, -1.0), (self.height, 1.0)] {
+            let origin = origin - Vec3::new(0.0, 0.0, center);
+            let b = 2.0 * dot_product(&origin, &dir);
+            let c = dot_product(&origin, &origin) - self.radius * self.radius;
+            for dist in solve_quadratic([c as f64, b as f64, 1.0]).as_slice() {
+                let dist = *dist as f32;
+                let point = origin + dir * dist;
+                if point.z * side < 0.0 {
+                    continue;
+                }
+                let angle = libm::atan2f(point.y, point.x);
+                let v = (point.z + center + self.radius) / length;
+                visit_local(
+                    dist,
+                    point * (1.0 / self.radius),
+                    Vec2::new((angle + PI) / (2.0 * PI), v),
+                );
+            }
+        }
+    }
+}
+
+impl Intersect for Capsule {
+    fn ray_intersect(&self, _geometry: &Geometry, ray: Ray) -> Option<Hit> {
+        let (dist, normal, uv) = nearest_crossing(self, &ray)?;
+        Some(Hit::new(
+            ray.from + ray.dir * dist,
+            normal,
+            uv,
+            self.material,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vec3::assert_near;
+
+    fn count_crossings(capsule: &Capsule, ray: &Ray) -> usize {
+        let mut count = 0;
+        capsule.crossings(ray, &mut |_, _, _| count += 1);
+        count
+    }
+
+    #[test]
+    fn test_capsule_intersect() {
+        let geometry = Default::default();
+        let capsule = Capsule::new(
+            Vec3::new(0.0, 0.0, 0.0),
+            Vec3::new(0.0, 2.0, 0.0),
+            1.0,
+            Default::default(),
+        )
+        .unwrap();
+        let left = Vec3::new(-1.0, 0.0, 0.0);
+
+        // across the cylinder the spheres of the ends lie inside it and are left out
+        let side = Ray::new(Vec3::new(5.0, 0.2, 0.0), left);
+        let hit = capsule.ray_intersect(&geometry, side).unwrap();
+        assert_near(Vec3::new(1.0, 0.2, 0.0), hit.point);
+        assert_near(Vec3::new(1.0, 0.0, 0.0), hit.normal);
+        assert_eq!(2, count_crossings(&capsule, &side));
+
+        // beyond the base only its hemisphere is crossed
+        let below = Ray::new(Vec3::new(5.0, -0.5, 0.0), left);
+        let hit = capsule.ray_intersect(&geometry, below).unwrap();
+        let point = Vec3::new(libm::sqrtf(0.75), -0.5, 0.0);
+        assert_near(point, hit.point);
+        assert_near(point, hit.normal);
+        assert_eq!(2, count_crossings(&capsule, &below));
+
+        // along the axis, through both tips
+        let down = Ray::new(Vec3::new(0.0, 5.0, 0.0), Vec3::new(0.0, -1.0, 0.0));
+        let hit = capsule.ray_intersect(&geometry, down).unwrap();
+        assert_near(Vec3::new(0.0, 3.0, 0.0), hit.point);
+        assert_near(Vec3::new(0.0, 1.0, 0.0), hit.normal);
+        assert!((hit.uv.y - 1.0).abs() < 1e-4);
+        assert_eq!(2, count_crossings(&capsule, &down));
+
+        let above = Ray::new(Vec3::new(5.0, 3.5, 0.0), left);
+        assert!(capsule.ray_intersect(&geometry, above).is_none());
+
+        let base = Vec3::default();
+        assert!(Capsule::new(base, base, 1.0, Default::default()).is_none());
+        assert!(Capsule::new(base, left, -1.0, Default::default()).is_none());
+    }
+}
@@ -0,0 +1,160 @@
+use core::f32::consts::PI;
+
+use crate::entity::bounding_box::BoundingBox;
+use crate::frame::Frame;
+use crate::intersect::{nearest_crossing, Crossings, Hit, Intersect};
+use crate::material::Material;
+use crate::ray::Ray;
+use crate::scene::Geometry;
+use crate::solver::solve_quadratic;
+use crate::vec2::Vec2;
+use crate::vec3::Vec3;
+
+// Cone truncated between `base` and `base + frame.normal * height`, with a radius changing
+// linearly from `base_radius` to `top_radius`. Either radius may be zero for a pointed cone.
+#[derive(Debug, Copy, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Cone {
+    pub base: Vec3,
+    pub frame: Frame,
+    pub height: f32,
+    pub base_radius: f32,
+    pub top_radius: f32,
+    pub capped: bool,
+    pub material: Material,
+}
+
+impl Cone {
+    // `None` unless the height is positive and the radii are not negative nor both zero.
+    pub fn new(
+        base: Vec3,
+        top: Vec3,
+        base_radius: f32,
+        top_radius: f32,
+        capped: bool,
+        material: Material,
+    ) -> Option<Cone> {
+        let height = (top - base).norm();
+        let radii = base_radius >= 0.0 && top_radius >= 0.0 && base_radius.max(top_radius) > 0.0;
+        (height > 0.0 && radii).then(|| Cone {
+            base,
+            frame: Frame::from_normal(&(top - base)),
+            height,
+            base_radius,
+            top_radius,
+            capped,
+            material,
+        })
+    }
+
+    pub fn bounding_box(&self) -> BoundingBox {
+        let top = self.base + self.frame.normal * self.height;
+        BoundingBox::from_disk(self.base, self.frame.normal, self.base_radius).union(
+            &BoundingBox::from_disk(top, self.frame.normal, self.top_radius),
+        )
+    }
+}
+
+// Crossings of a local space line with a surface of revolution around z between 0 and `height`,
+// uv on the side is (angle / 2pi, z / height) and cap uv is planar.
+pub(crate) fn revolution_crossings(
+    origin: Vec3,
+    dir: Vec3,
+    height: f32,
+    radii: (f32, f32),
+    capped: bool,
+    visit: &mut dyn FnMut(f32, Vec3, Vec2),
+) {
+    let (base_radius, top_radius) = radii;
+    let slope = (top_radius - base_radius) / height;
+    let origin_radius = base_radius + slope * origin.z;
+
+    let a = dir.x * dir.x + dir.y * dir.y - slope * slope * dir.z * dir.z;
+    let b = 2.0 * (origin.x * dir.x + origin.y * dir.y - slope * dir.z * origin_radius);
+    let c = origin.x * origin.x + origin.y * origin.y - origin_radius * origin_radius;
+
+    for dist in solve_quadratic([c as f64, b as f64, a as f64]).as_slice() {
+        let dist = *dist as f32;
+        let point = origin + dir * dist;
+        if point.z < 0.0 || point.z > height {
+            continue;
+        }
+        let radius = base_radius + slope * point.z;
+        let normal = Vec3::new(point.x, point.y, -slope * radius).normalized();
+        let angle = libm::atan2f(point.y, point.x);
+        visit(
+            dist,
+            normal,
+            Vec2::new((angle + PI) / (2.0 * PI), point.z / height),
+        );
+    }
+
+    if !capped || libm::fabsf(dir.z) < f32::EPSILON {
+        return;
+    }
+    for (z, radius, side) in [(0.0, base_radius, -1.0), (height, top_radius, 1.0)] {
+        let dist = (z - origin.z) / dir.z;
+        let point = origin + dir * dist;
+        if radius > 0.0 && point.x * point.x + point.y * point.y <= radius * radius {
+            let uv = Vec2::new(point.x / radius, point.y / radius) * 0.5 + Vec2::new(0.5, 0.5);
+            visit(dist, Vec3::new(0.0, 0.0, side), uv);
+        }
+    }
+}
+
+impl Crossings for Cone {
+    fn crossings(&self, ray: &Ray, visit: &mut dyn FnMut(f32, Vec3, Vec2)) {
+        revolution_crossings(
+            self.frame.to_local(&(ray.from - self.base)),
+            self.frame.to_local(&ray.dir),
+            self.height,
+            (self.base_radius, self.top_radius),
+            self.capped,
+            &mut |dist, normal, uv| visit(dist, self.frame.to_world(&normal), uv),
+        );
+    }
+}
+
+impl Intersect for Cone {
+    fn ray_intersect(&self, _geometry: &Geometry, ray: Ray) -> Option<Hit> {
+        let (dist, normal, uv) = nearest_crossing(self, &ray)?;
+        Some(Hit::new(
+            ray.from + ray.dir * dist,
+            normal,
+            uv,
+            self.material,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cone_intersect() {
+        let base = Vec3::new(0.0, 0.0, -10.0);
+        let top = Vec3::new(0.0, 2.0, -10.0);
+        let geometry = Default::default();
+
+        let cone = Cone::new(base, top, 2.0, 0.0, true, Default::default()).unwrap();
+        let ray = Ray::new(Vec3::new(0.0, 1.0, 0.0), Vec3::new(0.0, 0.0, -1.0));
+        let hit = cone.ray_intersect(&geometry, ray).unwrap();
+        assert!((hit.point - Vec3::new(0.0, 1.0, -9.0)).norm() < 1e-4);
+        let slant = Vec3::new(0.0, 1.0, 1.0).normalized();
+        assert!((hit.normal - slant).norm() < 1e-4);
+
+        let ray = Ray::new(Vec3::new(0.5, -5.0, -10.0), Vec3::new(0.0, 1.0, 0.0));
+        let hit = cone.ray_intersect(&geometry, ray).unwrap();
+        assert!((hit.point - Vec3::new(0.5, 0.0, -10.0)).norm() < 1e-4);
+        assert!((hit.normal - Vec3::new(0.0, -1.0, 0.0)).norm() < 1e-4);
+
+        // an open tube is seen through from the end
+        let open = Cone::new(base, top, 1.0, 1.0, false, Default::default()).unwrap();
+        assert!(open.ray_intersect(&geometry, ray).is_none());
+
+        assert!(Cone::new(base, base, 1.0, 0.0, true, Default::default()).is_none());
+        assert!(Cone::new(base, top, 0.0, 0.0, true, Default::default()).is_none());
+        assert!(Cone::new(base, top, 1.0, -1.0, true, Default::default()).is_none());
+    }
+}
@@ -0,0 +1,323 @@
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+
+use crate::entity::bounding_box::BoundingBox;
+use crate::entity::Entity;
+use crate::intersect::{Crossings, Hit, Intersect};
+use crate::material::Material;
+use crate::ray::Ray;
+use crate::scene::Geometry;
+use crate::utils::EPSILON;
+use crate::vec3::dot_product;
+
+// Rays this close to parallel to a surface only touch it.
+const TANGENT_COS: f32 = 1e-6;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum CsgOp {
+    Union,
+    Intersection,
+    Difference,
+}
+
+impl CsgOp {
+    fn contains(&self, in_left: bool, in_right: bool) -> bool {
+        match self {
+            CsgOp::Union => in_left || in_right,
+            CsgOp::Intersection => in_left && in_right,
+            CsgOp::Difference => in_left && !in_right,
+        }
+    }
+}
+
+// Part of the line through a ray that lies inside a solid, as signed distances along the ray.
+#[derive(Debug, Copy, Clone)]
+pub struct Span {
+    pub enter: f32,
+    pub exit: f32,
+    pub enter_hit: Hit,
+    pub exit_hit: Hit,
+}
+
+// Pairs up the crossings of a closed surface into the spans between them, by whether each
+// one enters or leaves the solid. A ray through an edge may see a crossing twice, or once
+// where the surfaces meet imprecisely, and one grazing the surface only touches it, so
+// repeated crossings are merged and tangent ones skipped rather than paired up blindly.
+pub(crate) fn crossing_spans(surface: &impl Crossings, ray: &Ray, material: Material) -> Vec<Span> {
+    let mut crossings = Vec::new();
+    surface.crossings(ray, &mut |dist, normal, uv| {
+        crossings.push((
+            dist,
+            Hit::new(ray.from + ray.dir * dist, normal, uv, material),
+        ))
+    });
+    crossings.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+    let mut open: Option<(f32, Hit)> = None;
+    let mut spans = Vec::new();
+    for (dist, hit) in crossings {
+        let cos = dot_product(&ray.dir, &hit.normal);
+        if libm::fabsf(cos) <= TANGENT_COS {
+            continue;
+        }
+        match open {
+            None if cos < 0.0 => open = Some((dist, hit)),
+            Some((enter, enter_hit)) if cos > 0.0 => {
+                spans.push(Span {
+                    enter,
+                    exit: dist,
+                    enter_hit,
+                    exit_hit: hit,
+                });
+                open = None;
+            }
+            _ => {}
+        }
+    }
+    spans
+}
+
+// Merges two sorted lists of disjoint spans. Surfaces of the subtracted solid bound the
+// result from the outside, so their normals are flipped.
+pub fn combine(left: &[Span], right: &[Span], op: CsgOp) -> Vec<Span> {
+    let mut events = left
+        .iter()
+        .map(|span| (span, false))
+        .chain(right.iter().map(|span| (span, true)))
+        .flat_map(|(span, is_right)| {
+            [
+                (span.enter, is_right, true, span.enter_hit),
+                (span.exit, is_right, false, span.exit_hit),
+            ]
+        })
+        .collect::<Vec<_>>();
+    events.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+    let (mut in_left, mut in_right) = (false, false);
+    let mut open: Option<(f32, Hit)> = None;
+    let mut spans = Vec::new();
+    for (dist, is_right, entering, mut hit) in events {
+        if is_right {
+            in_right = entering;
+        } else {
+            in_left = entering;
+        }
+        if is_right && op == CsgOp::Difference {
+            hit.normal = -hit.normal;
+        }
+
+        match (open, op.contains(in_left, in_right)) {
+            (None, true) => open = Some((dist, hit)),
+            (Some((enter, enter_hit)), false) => {
+                spans.push(Span {
+                    enter,
+                    exit: dist,
+                    enter_hit,
+                    exit_hit: hit,
+                });
+                open = None;
+            }
+            _ => {}
+        }
+    }
+    spans
+}
+
+// Boolean combination of two solid entities, see `Entity::is_solid`.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Csg {
+    pub op: CsgOp,
+    pub left: Arc<Entity>,
+    pub right: Arc<Entity>,
+    pub aabb: BoundingBox,
+}
+
+impl Csg {
+    // `None` unless both operands are solids.
+    pub fn new(geometry: &Geometry, op: CsgOp, left: Arc<Entity>, right: Arc<Entity>) -> Option<Csg> {
+        if !left.is_solid() || !right.is_solid() {
+            return None;
+        }
+        let left_aabb = left.bounding_box(geometry)?;
+        let aabb = match op {
+            CsgOp::Union => left_aabb.union(&right.bounding_box(geometry)?),
+            CsgOp::Intersection => left_aabb.intersection(&right.bounding_box(geometry)?),
+            CsgOp::Difference => left_aabb,
+        };
+        Some(Csg {
+            op,
+            left,
+            right,
+            aabb,
+        })
+    }
+
+    pub fn spans(&self, geometry: &Geometry, ray: &Ray) -> Vec<Span> {
+        if !self.aabb.ray_check_intersect_standard(*ray) {
+            return Vec::new();
+        }
+        combine(
+            &self.left.spans(geometry, ray),
+            &self.right.spans(geometry, ray),
+            self.op,
+        )
+    }
+}
+
+impl Intersect for Csg {
+    fn ray_intersect(&self, geometry: &Geometry, ray: Ray) -> Option<Hit> {
+        self.spans(geometry, &ray)
+            .iter()
+            .flat_map(|span| [(span.enter, span.enter_hit), (span.exit, span.exit_hit)])
+            .find(|(dist, _)| *dist > EPSILON)
+            .map(|(_, hit)| hit)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::entity::cuboid::Cuboid;
+    use crate::entity::cylinder::Cylinder;
+    use crate::entity::plane::Plane;
+    use crate::entity::sphere::Sphere;
+    use crate::vec2::Vec2;
+    use crate::vec3::Vec3;
+
+    struct Listed(Vec<(f32, Vec3)>);
+
+    impl Crossings for Listed {
+        fn crossings(&self, _ray: &Ray, visit: &mut dyn FnMut(f32, Vec3, Vec2)) {
+            for (dist, normal) in &self.0 {
+                visit(*dist, *normal, Default::default());
+            }
+        }
+    }
+
+    #[test]
+    fn test_crossing_spans() {
+        // listed out of order, with edges crossed twice and a tangent point
+        let (enter, exit) = (Vec3::new(-1.0, 0.0, 0.0), Vec3::new(1.0, 0.0, 0.0));
+        let tangent = Vec3::new(0.0, 1.0, 0.0);
+        let surface = Listed(alloc::vec![
+            (5.0, exit),
+            (1.0, enter),
+            (1.0, enter),
+            (2.0, exit),
+            (3.0, tangent),
+            (4.0, enter),
+            (6.0, enter),
+            (7.0, exit),
+            (7.0, exit),
+        ]);
+        let ray = Ray::new(Vec3::default(), Vec3::new(1.0, 0.0, 0.0));
+        let spans = crossing_spans(&surface, &ray, Default::default());
+        let bounds: Vec<_> = spans.iter().map(|span| (span.enter, span.exit)).collect();
+        assert_eq!(alloc::vec![(1.0, 2.0), (4.0, 5.0), (6.0, 7.0)], bounds);
+
+        // a sphere grazed along its side
+        let sphere = Sphere::new(Vec3::default(), 1.0, Default::default());
+        let grazing = Ray::new(Vec3::new(-5.0, 1.0, 0.0), Vec3::new(1.0, 0.0, 0.0));
+        assert!(crossing_spans(&sphere, &grazing, Default::default()).len() <= 1);
+    }
+
+    #[test]
+    fn test_csg_operands() {
+        let plane = Arc::new(Entity::Plane(Plane::new(
+            Vec3::default(),
+            Vec3::new(0.0, 1.0, 0.0),
+            Default::default(),
+        )));
+        let sphere = Arc::new(Entity::Sphere(Sphere::new(Vec3::default(), 1.0, Default::default())));
+        assert!(Csg::new(&Default::default(), CsgOp::Union, sphere.clone(), plane).is_none());
+        assert!(Csg::new(&Default::default(), CsgOp::Union, sphere.clone(), sphere).is_some());
+    }
+
+    fn assert_hit(entity: &Csg, ray: Ray, point: Vec3, normal: Vec3) {
+        let hit = entity.ray_intersect(&Default::default(), ray).unwrap();
+        assert!((hit.point - point).norm() < 1e-4, "{:?}", hit.point);
+        assert!((hit.normal - normal).norm() < 1e-4, "{:?}", hit.normal);
+    }
+
+    #[test]
+    fn test_csg_difference() {
+        let plate = Cuboid::new(
+            Vec3::new(-2.0, -0.5, -2.0),
+            Vec3::new(2.0, 0.5, 2.0),
+            Default::default(),
+        )
+        .unwrap();
+        let drill = Cylinder::new(
+            Vec3::new(0.0, -1.0, 0.0),
+            Vec3::new(0.0, 1.0, 0.0),
+            0.5,
+            true,
+            Default::default(),
+        )
+        .unwrap();
+        let csg = Csg::new(
+            &Default::default(),
+            CsgOp::Difference,
+            Arc::new(Entity::Cuboid(plate)),
+            Arc::new(Entity::Cylinder(drill)),
+        )
+        .unwrap();
+
+        let down = Vec3::new(0.0, -1.0, 0.0);
+        assert!(csg
+            .ray_intersect(
+                &Default::default(),
+                Ray::new(Vec3::new(0.0, 5.0, 0.0), down)
+            )
+            .is_none());
+        assert_hit(
+            &csg,
+            Ray::new(Vec3::new(1.0, 5.0, 0.0), down),
+            Vec3::new(1.0, 0.5, 0.0),
+            Vec3::new(0.0, 1.0, 0.0),
+        );
+        // the wall of the hole faces into it
+        assert_hit(
+            &csg,
+            Ray::new(Vec3::default(), Vec3::new(1.0, 0.0, 0.0)),
+            Vec3::new(0.5, 0.0, 0.0),
+            Vec3::new(-1.0, 0.0, 0.0),
+        );
+    }
+
+    #[test]
+    fn test_csg_intersection() {
+        let sphere = |z: f32| {
+            Arc::new(Entity::Sphere(Sphere::new(
+                Vec3::new(0.0, 0.0, z),
+                1.0,
+                Default::default(),
+            )))
+        };
+        let lens = Csg::new(
+            &Default::default(),
+            CsgOp::Intersection,
+            sphere(0.5),
+            sphere(-0.5),
+        )
+        .unwrap();
+        assert_hit(
+            &lens,
+            Ray::new(Vec3::new(0.0, 0.0, 5.0), Vec3::new(0.0, 0.0, -1.0)),
+            Vec3::new(0.0, 0.0, 0.5),
+            Vec3::new(0.0, 0.0, 1.0),
+        );
+        assert!(lens
+            .ray_intersect(
+                &Default::default(),
+                Ray::new(Vec3::new(0.0, 0.9, 5.0), Vec3::new(0.0, 0.0, -1.0))
+            )
+            .is_none());
+        // bounded by where the spheres overlap
+        assert!((lens.aabb.min() - Vec3::new(-1.0, -1.0, -0.5)).norm() < 1e-4);
+        assert!((lens.aabb.max() - Vec3::new(1.0, 1.0, 0.5)).norm() < 1e-4);
+    }
+}
@@ -0,0 +1,221 @@
+use alloc::vec::Vec;
+
+use crate::entity::bounding_box::BoundingBox;
+use crate::entity::csg::Span;
+use crate::intersect::{Hit, Intersect};
+use crate::material::Material;
+use crate::ray::Ray;
+use crate::scene::Geometry;
+use crate::utils::EPSILON;
+use crate::vec2::Vec2;
+use crate::vec3::{dot_product, Vec3};
+
+#[derive(Debug, Copy, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Cuboid {
+    pub center: Vec3,
+    pub half_size: Vec3,
+    pub axes: [Vec3; 3],
+    pub material: Material,
+}
+
+impl Cuboid {
+    // `None` unless `min` is below `max` along every axis.
+    pub fn new(min: Vec3, max: Vec3, material: Material) -> Option<Cuboid> {
+        Cuboid::oriented(
+            (min + max) * 0.5,
+            (max - min) * 0.5,
+            [
+                Vec3::new(1.0, 0.0, 0.0),
+                Vec3::new(0.0, 1.0, 0.0),
+                Vec3::new(0.0, 0.0, 1.0),
+            ],
+            material,
+        )
+    }
+
+    // `axes` are the local x, y and z directions of the box, `half_size` is measured along them.
+    // `None` unless the sizes are positive and the axes orthogonal.
+    pub fn oriented(
+        center: Vec3,
+        half_size: Vec3,
+        axes: [Vec3; 3],
+        material: Material,
+    ) -> Option<Cuboid> {
+        let sized = half_size.x > 0.0 && half_size.y > 0.0 && half_size.z > 0.0;
+        (sized && orthogonal(&axes)).then(|| Cuboid {
+            center,
+            half_size,
+            axes: axes.map(|axis| axis.normalized()),
+            material,
+        })
+    }
+
+    pub fn bounding_box(&self) -> BoundingBox {
+        let extent = |idx: usize| {
+            let half = [self.half_size.x, self.half_size.y, self.half_size.z];
+            (0..3)
+                .map(|axis| {
+                    let component = [self.axes[axis].x, self.axes[axis].y, self.axes[axis].z];
+                    libm::fabsf(component[idx]) * half[axis]
+                })
+                .sum::<f32>()
+        };
+        let extent = Vec3::new(extent(0), extent(1), extent(2));
+        BoundingBox::from_min_max(self.center - extent, self.center + extent)
+    }
+
+    // Entry and exit distances along the ray with the index of the box axis crossed at each.
+    pub(crate) fn slabs(&self, ray: &Ray) -> Option<((f32, usize), (f32, usize))> {
+        let offset = ray.from - self.center;
+        let half = [self.half_size.x, self.half_size.y, self.half_size.z];
+
+        let mut near = (f32::NEG_INFINITY, 0);
+        let mut far = (f32::INFINITY, 0);
+        for (idx, axis) in self.axes.iter().enumerate() {
+            let origin = dot_product(&offset, axis);
+            let dir = dot_product(&ray.dir, axis);
+            if libm::fabsf(dir) < f32::EPSILON {
+                if libm::fabsf(origin) > half[idx] {
+                    return None;
+                }
+                continue;
+            }
+            let t1 = (-half[idx] - origin) / dir;
+            let t2 = (half[idx] - origin) / dir;
+            let (t_min, t_max) = if t1 < t2 { (t1, t2) } else { (t2, t1) };
+            if t_min > near.0 {
+                near = (t_min, idx);
+            }
+            if t_max < far.0 {
+                far = (t_max, idx);
+            }
+        }
+
+        if near.0 > far.0 {
+            return None;
+        }
+        Some((near, far))
+    }
+
+    pub fn spans(&self, ray: &Ray) -> Vec<Span> {
+        self.slabs(ray)
+            .map(|((enter, enter_axis), (exit, exit_axis))| Span {
+                enter,
+                exit,
+                enter_hit: self.surface_hit(ray, enter, enter_axis),
+                exit_hit: self.surface_hit(ray, exit, exit_axis),
+            })
+            .into_iter()
+            .collect()
+    }
+
+    pub(crate) fn surface_hit(&self, ray: &Ray, dist: f32, axis: usize) -> Hit {
+        let point = ray.from + ray.dir * dist;
+        let local = point - self.center;
+        let half = [self.half_size.x, self.half_size.y, self.half_size.z];
+
+        let side = dot_product(&local, &self.axes[axis]);
+        let normal = if side < 0.0 {
+            -self.axes[axis]
+        } else {
+            self.axes[axis]
+        };
+
+        let (u_axis, v_axis) = ((axis + 1) % 3, (axis + 2) % 3);
+        let uv = Vec2::new(
+            (dot_product(&local, &self.axes[u_axis]) / half[u_axis] + 1.0) * 0.5,
+            (dot_product(&local, &self.axes[v_axis]) / half[v_axis] + 1.0) * 0.5,
+        );
+        // uv runs across each face along the next two axes
+        Hit {
+            tangent: self.axes[u_axis] * (2.0 * half[u_axis]),
+            bitangent: self.axes[v_axis] * (2.0 * half[v_axis]),
+            ..Hit::new(point, normal, uv, self.material)
+        }
+    }
+}
+
+// Whether three axes, not zero, are at right angles to each other.
+fn orthogonal(axes: &[Vec3; 3]) -> bool {
+    if axes.iter().any(|axis| axis.norm() == 0.0) {
+        return false;
+    }
+    let axes = axes.map(|axis| axis.normalized());
+    (0..3).all(|i| libm::fabsf(dot_product(&axes[i], &axes[(i + 1) % 3])) <= 1e-4)
+}
+
+impl Intersect for Cuboid {
+    fn ray_intersect(&self, _geometry: &Geometry, ray: Ray) -> Option<Hit> {
+        let (near, far) = self.slabs(&ray)?;
+        let (dist, axis) = if near.0 > EPSILON { near } else { far };
+        if dist < EPSILON {
+            return None;
+        }
+        Some(self.surface_hit(&ray, dist, axis))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vec3::assert_near;
+
+    #[test]
+    fn test_cuboid_intersect() {
+        let geometry = Geometry::default();
+        let cuboid = Cuboid::new(
+            Vec3::new(-1.0, -1.0, -1.0),
+            Vec3::new(1.0, 2.0, 1.0),
+            Default::default(),
+        )
+        .unwrap();
+
+        let outside = Ray::new(Vec3::new(-5.0, 0.0, 0.5), Vec3::new(1.0, 0.0, 0.0));
+        let hit = cuboid.ray_intersect(&geometry, outside).unwrap();
+        assert_near(Vec3::new(-1.0, 0.0, 0.5), hit.point);
+        assert_near(Vec3::new(-1.0, 0.0, 0.0), hit.normal);
+        assert!((hit.uv.x - 1.0 / 3.0).abs() < 1e-4 && (hit.uv.y - 0.75).abs() < 1e-4);
+        // the tangents are the change of the point along u and v across the face
+        assert_near(Vec3::new(0.0, 3.0, 0.0), hit.tangent);
+        assert_near(Vec3::new(0.0, 0.0, 2.0), hit.bitangent);
+        let moved = Ray::new(outside.from + Vec3::new(0.0, 0.03, 0.0), outside.dir);
+        let moved = cuboid.ray_intersect(&geometry, moved).unwrap();
+        assert!((moved.uv.x - hit.uv.x - 0.01).abs() < 1e-4);
+
+        let inside = Ray::new(Vec3::new(0.0, 0.0, 0.0), Vec3::new(0.0, 1.0, 0.0));
+        let hit = cuboid.ray_intersect(&geometry, inside).unwrap();
+        assert_near(Vec3::new(0.0, 2.0, 0.0), hit.point);
+        assert_near(Vec3::new(0.0, 1.0, 0.0), hit.normal);
+
+        let miss = Ray::new(Vec3::new(-5.0, 3.0, 0.0), Vec3::new(1.0, 0.0, 0.0));
+        assert!(cuboid.ray_intersect(&geometry, miss).is_none());
+    }
+
+    #[test]
+    fn test_oriented_cuboid() {
+        let geometry = Geometry::default();
+        let diagonal = Vec3::new(1.0, 1.0, 0.0).normalized();
+        let cuboid = Cuboid::oriented(
+            Vec3::new(0.0, 0.0, 0.0),
+            Vec3::new(1.0, 1.0, 1.0),
+            [diagonal, Vec3::new(-1.0, 1.0, 0.0), Vec3::new(0.0, 0.0, 1.0)],
+            Default::default(),
+        )
+        .unwrap();
+
+        let ray = Ray::new(Vec3::new(-5.0, 0.0, 0.0), Vec3::new(1.0, 0.0, 0.0));
+        let hit = cuboid.ray_intersect(&geometry, ray).unwrap();
+        assert_near(Vec3::new(-core::f32::consts::SQRT_2, 0.0, 0.0), hit.point);
+
+        let aabb = cuboid.bounding_box();
+        assert_near(Vec3::new(core::f32::consts::SQRT_2, core::f32::consts::SQRT_2, 1.0), aabb.max());
+
+        let skewed = [diagonal, Vec3::new(0.0, 1.0, 0.0), Vec3::new(0.0, 0.0, 1.0)];
+        assert!(!orthogonal(&skewed));
+        let unit = Vec3::new(1.0, 1.0, 1.0);
+        assert!(Cuboid::oriented(Vec3::default(), unit, skewed, Default::default()).is_none());
+        let flat = Vec3::new(1.0, 0.0, 1.0);
+        assert!(Cuboid::new(Vec3::default(), flat, Default::default()).is_none());
+    }
+}
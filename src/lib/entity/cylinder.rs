@@ -0,0 +1,124 @@
+use crate::entity::bounding_box::BoundingBox;
+use crate::entity::cone::revolution_crossings;
+use crate::frame::Frame;
+use crate::intersect::{nearest_crossing, Crossings, Hit, Intersect};
+use crate::material::Material;
+use crate::ray::Ray;
+use crate::scene::Geometry;
+use crate::vec2::Vec2;
+use crate::vec3::Vec3;
+
+// Cylinder between `base` and `base + frame.normal * height`, optionally closed by flat caps.
+#[derive(Debug, Copy, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Cylinder {
+    pub base: Vec3,
+    pub frame: Frame,
+    pub height: f32,
+    pub radius: f32,
+    pub capped: bool,
+    pub material: Material,
+}
+
+impl Cylinder {
+    // `None` unless the radius and the height are positive.
+    pub fn new(
+        base: Vec3,
+        top: Vec3,
+        radius: f32,
+        capped: bool,
+        material: Material,
+    ) -> Option<Cylinder> {
+        let height = (top - base).norm();
+        (height > 0.0 && radius > 0.0).then(|| Cylinder {
+            base,
+            frame: Frame::from_normal(&(top - base)),
+            height,
+            radius,
+            capped,
+            material,
+        })
+    }
+
+    pub fn bounding_box(&self) -> BoundingBox {
+        let top = self.base + self.frame.normal * self.height;
+        BoundingBox::from_disk(self.base, self.frame.normal, self.radius)
+            .union(&BoundingBox::from_disk(top, self.frame.normal, self.radius))
+    }
+}
+
+impl Crossings for Cylinder {
+    fn crossings(&self, ray: &Ray, visit: &mut dyn FnMut(f32, Vec3, Vec2)) {
+        revolution_crossings(
+            self.frame.to_local(&(ray.from - self.base)),
+            self.frame.to_local(&ray.dir),
+            self.height,
+            (self.radius, self.radius),
+            self.capped,
+            &mut |dist, normal, uv| visit(dist, self.frame.to_world(&normal), uv),
+        );
+    }
+}
+
+impl Intersect for Cylinder {
+    fn ray_intersect(&self, _geometry: &Geometry, ray: Ray) -> Option<Hit> {
+        let (dist, normal, uv) = nearest_crossing(self, &ray)?;
+        Some(Hit::new(
+            ray.from + ray.dir * dist,
+            normal,
+            uv,
+            self.material,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vec3::assert_near;
+
+    #[test]
+    fn test_cylinder_intersect() {
+        let geometry = Default::default();
+        let (base, top) = (Vec3::new(0.0, 0.0, 0.0), Vec3::new(0.0, 2.0, 0.0));
+        let cylinder = Cylinder::new(base, top, 1.0, true, Default::default()).unwrap();
+
+        let side = Ray::new(Vec3::new(5.0, 1.0, 0.0), Vec3::new(-1.0, 0.0, 0.0));
+        let hit = cylinder.ray_intersect(&geometry, side).unwrap();
+        assert_near(Vec3::new(1.0, 1.0, 0.0), hit.point);
+        assert_near(Vec3::new(1.0, 0.0, 0.0), hit.normal);
+
+        // the caps face away along the axis
+        let down = Ray::new(Vec3::new(0.5, 5.0, 0.0), Vec3::new(0.0, -1.0, 0.0));
+        let hit = cylinder.ray_intersect(&geometry, down).unwrap();
+        assert_near(Vec3::new(0.5, 2.0, 0.0), hit.point);
+        assert_near(Vec3::new(0.0, 1.0, 0.0), hit.normal);
+        let up = Ray::new(Vec3::new(0.5, -5.0, 0.0), Vec3::new(0.0, 1.0, 0.0));
+        let hit = cylinder.ray_intersect(&geometry, up).unwrap();
+        assert_near(Vec3::new(0.5, 0.0, 0.0), hit.point);
+        assert_near(Vec3::new(0.0, -1.0, 0.0), hit.normal);
+
+        // from inside, the wall still faces out
+        let inside = Ray::new(Vec3::new(0.0, 1.0, 0.0), Vec3::new(0.0, 0.0, 1.0));
+        let hit = cylinder.ray_intersect(&geometry, inside).unwrap();
+        assert_near(Vec3::new(0.0, 1.0, 1.0), hit.point);
+        assert_near(Vec3::new(0.0, 0.0, 1.0), hit.normal);
+
+        // once through the side and once through a cap
+        let mut count = 0;
+        let slanted = Ray::new(
+            Vec3::new(-2.0, 0.0, 0.0),
+            Vec3::new(1.0, 1.0, 0.0).normalized(),
+        );
+        cylinder.crossings(&slanted, &mut |_, _, _| count += 1);
+        assert_eq!(2, count);
+
+        let above = Ray::new(Vec3::new(5.0, 2.5, 0.0), Vec3::new(-1.0, 0.0, 0.0));
+        assert!(cylinder.ray_intersect(&geometry, above).is_none());
+        let open = Cylinder::new(base, top, 1.0, false, Default::default()).unwrap();
+        assert!(open.ray_intersect(&geometry, down).is_none());
+
+        assert!(Cylinder::new(base, base, 1.0, true, Default::default()).is_none());
+        assert!(Cylinder::new(base, top, 0.0, true, Default::default()).is_none());
+    }
+}
@@ -0,0 +1,111 @@
+use core::f32::consts::PI;
+
+use crate::entity::bounding_box::BoundingBox;
+use crate::intersect::{Hit, Intersect};
+use crate::material::Material;
+use crate::ray::Ray;
+use crate::scene::Geometry;
+use crate::utils::EPSILON;
+use crate::vec2::Vec2;
+use crate::vec3::{dot_product, orthonormal_basis, Vec3};
+
+// Flat disk visible from both sides, uv is (distance from center / radius, angle / 2pi).
+#[derive(Debug, Copy, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Disk {
+    pub center: Vec3,
+    pub normal: Vec3,
+    pub radius: f32,
+    pub material: Material,
+}
+
+impl Disk {
+    // `None` unless the radius is positive and the normal not zero.
+    pub fn new(center: Vec3, normal: Vec3, radius: f32, material: Material) -> Option<Disk> {
+        (radius > 0.0 && normal.norm() > 0.0).then(|| Disk {
+            center,
+            normal: normal.normalized(),
+            radius,
+            material,
+        })
+    }
+
+    pub fn bounding_box(&self) -> BoundingBox {
+        BoundingBox::from_disk(self.center, self.normal, self.radius)
+    }
+}
+
+impl Intersect for Disk {
+    fn ray_intersect(&self, _geometry: &Geometry, ray: Ray) -> Option<Hit> {
+        let denominator = dot_product(&ray.dir, &self.normal);
+        if libm::fabsf(denominator) < f32::EPSILON {
+            return None;
+        }
+        let dist = dot_product(&(self.center - ray.from), &self.normal) / denominator;
+        if dist < EPSILON {
+            return None;
+        }
+
+        let hit = ray.from + ray.dir * dist;
+        let planar = hit - self.center;
+        let radius = planar.norm();
+        if radius > self.radius {
+            return None;
+        }
+
+        let (tangent, bitangent) = orthonormal_basis(&self.normal);
+        let angle = libm::atan2f(dot_product(&planar, &bitangent), dot_product(&planar, &tangent));
+        let uv = Vec2::new(radius / self.radius, (angle + PI) / (2.0 * PI));
+        Some(Hit::new(hit, self.normal, uv, self.material))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vec3::assert_near;
+
+    #[test]
+    fn test_disk_intersect() {
+        let geometry = Geometry::default();
+        let disk = Disk::new(
+            Vec3::new(0.0, 1.0, 0.0),
+            Vec3::new(0.0, 2.0, 0.0),
+            2.0,
+            Default::default(),
+        )
+        .unwrap();
+        assert_near(Vec3::new(0.0, 1.0, 0.0), disk.normal);
+
+        let down = Vec3::new(0.0, -1.0, 0.0);
+        let hit = disk
+            .ray_intersect(&geometry, Ray::new(Vec3::new(1.0, 5.0, 0.0), down))
+            .unwrap();
+        assert_near(Vec3::new(1.0, 1.0, 0.0), hit.point);
+        assert_near(disk.normal, hit.normal);
+        assert!((hit.uv.x - 0.5).abs() < 1e-4);
+
+        // the angle goes once around the center
+        let (tangent, bitangent) = orthonormal_basis(&disk.normal);
+        let angle_at = |offset: Vec3| {
+            let ray = Ray::new(Vec3::new(0.0, 1.0, 0.0) + offset + Vec3::new(0.0, 3.0, 0.0), down);
+            disk.ray_intersect(&geometry, ray).unwrap().uv.y
+        };
+        assert!((angle_at(tangent) - 0.5).abs() < 1e-4);
+        assert!((angle_at(bitangent) - 0.75).abs() < 1e-4);
+        assert!((angle_at(-bitangent) - 0.25).abs() < 1e-4);
+
+        // seen from below too
+        let up = Ray::new(Vec3::new(0.0, -5.0, 1.5), Vec3::new(0.0, 1.0, 0.0));
+        let hit = disk.ray_intersect(&geometry, up).unwrap();
+        assert!((hit.uv.x - 0.75).abs() < 1e-4);
+
+        let outside = Ray::new(Vec3::new(1.5, 5.0, 1.5), down);
+        assert!(disk.ray_intersect(&geometry, outside).is_none());
+        let parallel = Ray::new(Vec3::new(-5.0, 1.0, 0.0), Vec3::new(1.0, 0.0, 0.0));
+        assert!(disk.ray_intersect(&geometry, parallel).is_none());
+
+        assert!(Disk::new(Vec3::default(), down, 0.0, Default::default()).is_none());
+        assert!(Disk::new(Vec3::default(), Vec3::default(), 1.0, Default::default()).is_none());
+    }
+}
@@ -0,0 +1,27 @@
+use crate::entity::Entity;
+use crate::intersect::{Hit, Intersect};
+use crate::utils::OrderedFloat32;
+use alloc::vec::Vec;
+use crate::ray::Ray;
+use crate::scene::Geometry;
+
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Group {
+    pub entities: Vec<Entity>,
+}
+
+impl Group {
+    pub fn new(entities: Vec<Entity>) -> Group {
+        Group { entities }
+    }
+}
+
+impl Intersect for Group {
+    fn ray_intersect(&self, geometry: &Geometry, ray: Ray) -> Option<Hit> {
+        self.entities
+            .iter()
+            .filter_map(|entity| entity.ray_intersect(geometry, ray))
+            .min_by_key(|hit| OrderedFloat32::new(hit.point.dist_observer(&ray.from)))
+    }
+}
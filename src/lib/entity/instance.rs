@@ -0,0 +1,95 @@
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+
+use crate::entity::bounding_box::BoundingBox;
+use crate::entity::csg::Span;
+use crate::entity::Entity;
+use crate::intersect::{Hit, Intersect};
+use crate::material::Material;
+use crate::ray::Ray;
+use crate::scene::Geometry;
+use crate::transform::Transform;
+use crate::vec3::dot_product;
+
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Instance {
+    pub entity: Arc<Entity>,
+    pub transform: Transform,
+    pub material: Option<Material>,
+    pub aabb: Option<BoundingBox>,
+}
+
+impl Instance {
+    pub fn new(
+        geometry: &Geometry,
+        entity: Arc<Entity>,
+        transform: Transform,
+        material: Option<Material>,
+    ) -> Instance {
+        let aabb = entity.bounding_box(geometry).map(|aabb| {
+            BoundingBox::new(&aabb.corners().map(|corner| transform.point(&corner)))
+        });
+        Instance {
+            entity,
+            transform,
+            material,
+            aabb,
+        }
+    }
+
+    fn culls(&self, ray: &Ray) -> bool {
+        self.aabb
+            .is_some_and(|aabb| !aabb.ray_check_intersect_standard(*ray))
+    }
+
+    fn local_ray(&self, ray: &Ray) -> Ray {
+        let inverse = self.transform.inverted();
+        Ray::new(
+            inverse.point(&ray.from),
+            inverse.vector(&ray.dir).normalized(),
+        )
+    }
+
+    fn world_hit(&self, hit: Hit) -> Hit {
+        Hit {
+            point: self.transform.point(&hit.point),
+            normal: self.transform.normal(&hit.normal),
+            tangent: self.transform.vector(&hit.tangent),
+            bitangent: self.transform.vector(&hit.bitangent),
+            material: self.material.unwrap_or(hit.material),
+            ..hit
+        }
+    }
+
+    pub fn spans(&self, geometry: &Geometry, ray: &Ray) -> Vec<Span> {
+        if self.culls(ray) {
+            return Vec::new();
+        }
+        // distances along the local ray differ in scale, so measure them again in world space
+        let world_dist = |hit: &Hit| dot_product(&(hit.point - ray.from), &ray.dir);
+        self.entity
+            .spans(geometry, &self.local_ray(ray))
+            .into_iter()
+            .map(|span| {
+                let (enter_hit, exit_hit) = (self.world_hit(span.enter_hit), self.world_hit(span.exit_hit));
+                Span {
+                    enter: world_dist(&enter_hit),
+                    exit: world_dist(&exit_hit),
+                    enter_hit,
+                    exit_hit,
+                }
+            })
+            .collect()
+    }
+}
+
+impl Intersect for Instance {
+    fn ray_intersect(&self, geometry: &Geometry, ray: Ray) -> Option<Hit> {
+        if self.culls(&ray) {
+            return None;
+        }
+        let hit = self.entity.ray_intersect(geometry, self.local_ray(&ray))?;
+        Some(self.world_hit(hit))
+    }
+}
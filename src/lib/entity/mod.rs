@@ -1,38 +1,152 @@
 pub mod bounding_box;
+pub mod capsule;
+pub mod cone;
+pub mod csg;
+pub mod cuboid;
+pub mod cylinder;
+pub mod disk;
+pub mod group;
+pub mod instance;
 pub mod model;
 pub mod plane;
-pub mod scene;
+pub mod quad;
+pub mod sdf;
 pub mod sphere;
+pub mod torus;
 pub mod triangle;
 
+use alloc::vec::Vec;
+
+use crate::entity::bounding_box::BoundingBox;
+use crate::entity::capsule::Capsule;
+use crate::entity::cone::Cone;
+use crate::entity::csg::{combine, crossing_spans, Csg, CsgOp, Span};
+use crate::entity::cuboid::Cuboid;
+use crate::entity::cylinder::Cylinder;
+use crate::entity::disk::Disk;
+use crate::entity::group::Group;
+use crate::entity::instance::Instance;
 use crate::entity::model::Model;
 use crate::entity::plane::Plane;
-use crate::entity::scene::Scene;
+use crate::entity::quad::Quad;
+use crate::entity::sdf::Sdf;
 use crate::entity::sphere::Sphere;
+use crate::entity::torus::Torus;
 use crate::entity::triangle::Triangle;
-use crate::intersect::Intersect;
-use crate::material::Material;
+use crate::intersect::{Hit, Intersect};
 use crate::ray::Ray;
-use crate::render::RenderState;
-use crate::vec3::Vec3;
+use crate::scene::Geometry;
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Entity {
     Sphere(Sphere),
     Plane(Plane),
     Triangle(Triangle),
+    Cuboid(Cuboid),
+    Quad(Quad),
+    Disk(Disk),
+    Cylinder(Cylinder),
+    Cone(Cone),
+    Capsule(Capsule),
+    Torus(Torus),
     Model(Model),
-    Scene(Scene),
+    Group(Group),
+    Instance(Instance),
+    Csg(Csg),
+    Sdf(Sdf),
 }
 
 impl Intersect for Entity {
-    fn ray_intersect(&self, state: &RenderState, ray: Ray) -> Option<(Vec3, Vec3, Material)> {
+    fn ray_intersect(&self, geometry: &Geometry, ray: Ray) -> Option<Hit> {
+        match self {
+            Entity::Sphere(obj) => obj.ray_intersect(geometry, ray),
+            Entity::Plane(obj) => obj.ray_intersect(geometry, ray),
+            Entity::Triangle(obj) => obj.ray_intersect(geometry, ray),
+            Entity::Cuboid(obj) => obj.ray_intersect(geometry, ray),
+            Entity::Quad(obj) => obj.ray_intersect(geometry, ray),
+            Entity::Disk(obj) => obj.ray_intersect(geometry, ray),
+            Entity::Cylinder(obj) => obj.ray_intersect(geometry, ray),
+            Entity::Cone(obj) => obj.ray_intersect(geometry, ray),
+            Entity::Capsule(obj) => obj.ray_intersect(geometry, ray),
+            Entity::Torus(obj) => obj.ray_intersect(geometry, ray),
+            Entity::Model(obj) => obj.ray_intersect(geometry, ray),
+            Entity::Group(obj) => obj.ray_intersect(geometry, ray),
+            Entity::Instance(obj) => obj.ray_intersect(geometry, ray),
+            Entity::Csg(obj) => obj.ray_intersect(geometry, ray),
+            Entity::Sdf(obj) => obj.ray_intersect(geometry, ray),
+        }
+    }
+}
+
+impl Entity {
+    // `None` for unbounded entities, such as planes or groups containing them.
+    pub fn bounding_box(&self, geometry: &Geometry) -> Option<BoundingBox> {
+        match self {
+            Entity::Sphere(obj) => Some(obj.bounding_box()),
+            Entity::Plane(_) => None,
+            Entity::Triangle(obj) => Some(obj.bounding_box(geometry)),
+            Entity::Cuboid(obj) => Some(obj.bounding_box()),
+            Entity::Quad(obj) => Some(obj.bounding_box()),
+            Entity::Disk(obj) => Some(obj.bounding_box()),
+            Entity::Cylinder(obj) => Some(obj.bounding_box()),
+            Entity::Cone(obj) => Some(obj.bounding_box()),
+            Entity::Capsule(obj) => Some(obj.bounding_box()),
+            Entity::Torus(obj) => Some(obj.bounding_box()),
+            Entity::Model(obj) => Some(obj.aabb),
+            Entity::Group(obj) => obj
+                .entities
+                .iter()
+                .map(|entity| entity.bounding_box(geometry))
+                .reduce(|acc, aabb| Some(acc?.union(&aabb?)))
+                .flatten(),
+            Entity::Instance(obj) => obj.aabb,
+            Entity::Csg(obj) => Some(obj.aabb),
+            Entity::Sdf(obj) => Some(obj.aabb),
+        }
+    }
+
+    // Solids enclose a volume and can report the spans a ray spends inside them, which CSG needs.
+    pub fn is_solid(&self) -> bool {
+        match self {
+            Entity::Sphere(_)
+            | Entity::Cuboid(_)
+            | Entity::Capsule(_)
+            | Entity::Torus(_)
+            | Entity::Csg(_) => true,
+            Entity::Cylinder(obj) => obj.capped,
+            Entity::Cone(obj) => obj.capped,
+            Entity::Group(obj) => {
+                !obj.entities.is_empty() && obj.entities.iter().all(Entity::is_solid)
+            }
+            Entity::Instance(obj) => obj.entity.is_solid(),
+            Entity::Plane(_)
+            | Entity::Triangle(_)
+            | Entity::Quad(_)
+            | Entity::Disk(_)
+            | Entity::Model(_)
+            | Entity::Sdf(_) => false,
+        }
+    }
+
+    // Sorted spans of the whole line through `ray`, empty for entities that are not solid.
+    pub fn spans(&self, geometry: &Geometry, ray: &Ray) -> Vec<Span> {
+        if !self.is_solid() {
+            return Vec::new();
+        }
         match self {
-            Entity::Sphere(obj) => obj.ray_intersect(state, ray),
-            Entity::Plane(obj) => obj.ray_intersect(state, ray),
-            Entity::Triangle(obj) => obj.ray_intersect(state, ray),
-            Entity::Model(obj) => obj.ray_intersect(state, ray),
-            Entity::Scene(obj) => obj.ray_intersect(state, ray),
+            Entity::Sphere(obj) => crossing_spans(obj, ray, obj.material),
+            Entity::Cuboid(obj) => obj.spans(ray),
+            Entity::Cylinder(obj) => crossing_spans(obj, ray, obj.material),
+            Entity::Cone(obj) => crossing_spans(obj, ray, obj.material),
+            Entity::Capsule(obj) => crossing_spans(obj, ray, obj.material),
+            Entity::Torus(obj) => crossing_spans(obj, ray, obj.material),
+            Entity::Group(obj) => obj.entities.iter().fold(Vec::new(), |acc, entity| {
+                combine(&acc, &entity.spans(geometry, ray), CsgOp::Union)
+            }),
+            Entity::Instance(obj) => obj.spans(geometry, ray),
+            Entity::Csg(obj) => obj.spans(geometry, ray),
+            _ => Vec::new(),
         }
     }
 }
@@ -2,27 +2,27 @@ use alloc::vec::Vec;
 
 use crate::entity::bounding_box::BoundingBox;
 use crate::entity::triangle::Triangle;
-use crate::intersect::Intersect;
-use crate::material::Material;
+use crate::intersect::{Hit, Intersect};
 use crate::ray::Ray;
-use crate::render::RenderState;
+use crate::scene::Geometry;
 use crate::utils::OrderedFloat32;
 use crate::vec3::Vec3;
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Model {
     pub triangles: Vec<Triangle>,
     pub aabb: BoundingBox,
 }
 
 impl Model {
-    pub fn from_faces(state: &RenderState, triangles: Vec<Triangle>) -> Model {
+    pub fn from_faces(geometry: &Geometry, triangles: Vec<Triangle>) -> Model {
         let aabb = BoundingBox::new(
             triangles
                 .iter()
                 .flat_map(|triangle| {
                     triangle.points.iter().map(|vec_id| {
-                        *state.vec_buf.load(*vec_id)
+                        *geometry.vec_buf.load(*vec_id)
                     }).collect::<Vec<Vec3>>()
                 })
                 .collect::<Vec<Vec3>>()
@@ -36,14 +36,14 @@ impl Model {
 }
 
 impl Intersect for Model {
-    fn ray_intersect(&self, state: &RenderState, ray: Ray) -> Option<(Vec3, Vec3, Material)> {
+    fn ray_intersect(&self, geometry: &Geometry, ray: Ray) -> Option<Hit> {
         if !self.aabb.ray_check_intersect_standard(ray) {
             return None;
         }
 
         self.triangles
             .iter()
-            .filter_map(|triangle| triangle.ray_intersect(state, ray))
-            .min_by_key(|(hit, _, _)| OrderedFloat32::new(hit.dist_observer(&ray.from)))
+            .filter_map(|triangle| triangle.ray_intersect(geometry, ray))
+            .min_by_key(|hit| OrderedFloat32::new(hit.point.dist_observer(&ray.from)))
     }
 }
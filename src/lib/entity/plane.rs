@@ -1,15 +1,17 @@
-use crate::intersect::Intersect;
+use crate::intersect::{Hit, Intersect};
 use crate::material::Material;
 use crate::ray::Ray;
-use crate::render::RenderState;
+use crate::scene::Geometry;
 use crate::utils::EPSILON;
-use crate::vec3::{dot_product, Vec3};
+use crate::vec2::Vec2;
+use crate::vec3::{dot_product, orthonormal_basis, Vec3};
 
 #[derive(Debug, Copy, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Plane {
     pub point: Vec3,
     pub normal: Vec3,
-    material: Material,
+    pub material: Material,
 }
 
 impl Plane {
@@ -23,7 +25,7 @@ impl Plane {
 }
 
 impl Intersect for Plane {
-    fn ray_intersect(&self, _state: &RenderState, ray: Ray) -> Option<(Vec3, Vec3, Material)> {
+    fn ray_intersect(&self, _geometry: &Geometry, ray: Ray) -> Option<Hit> {
         let denominator = dot_product(&ray.dir, &self.normal);
         if denominator > -EPSILON {
             return None;
@@ -34,6 +36,14 @@ impl Intersect for Plane {
         }
 
         let hit = ray.from + ray.dir * (numerator / denominator);
-        Some((hit, self.normal, self.material))
+        // one uv unit per world unit, along an arbitrary basis in the plane
+        let (tangent, bitangent) = orthonormal_basis(&self.normal.normalized());
+        let offset = hit - self.point;
+        let uv = Vec2::new(dot_product(&offset, &tangent), dot_product(&offset, &bitangent));
+        Some(Hit {
+            tangent,
+            bitangent,
+            ..Hit::new(hit, self.normal, uv, self.material)
+        })
     }
 }
@@ -0,0 +1,116 @@
+use crate::entity::bounding_box::BoundingBox;
+use crate::intersect::{Hit, Intersect};
+use crate::material::Material;
+use crate::ray::Ray;
+use crate::scene::Geometry;
+use crate::utils::EPSILON;
+use crate::vec2::Vec2;
+use crate::vec3::{cross_product, dot_product, Vec3};
+
+// Parallelogram spanned by `edge_u` and `edge_v` from `corner`, visible from both sides.
+#[derive(Debug, Copy, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Quad {
+    pub corner: Vec3,
+    pub edge_u: Vec3,
+    pub edge_v: Vec3,
+    pub normal: Vec3,
+    pub material: Material,
+}
+
+impl Quad {
+    // `None` when the edges are parallel or zero.
+    pub fn new(corner: Vec3, edge_u: Vec3, edge_v: Vec3, material: Material) -> Option<Quad> {
+        let normal = cross_product(&edge_u, &edge_v);
+        (normal.norm() > 0.0).then(|| Quad {
+            corner,
+            edge_u,
+            edge_v,
+            normal: normal.normalized(),
+            material,
+        })
+    }
+
+    pub fn bounding_box(&self) -> BoundingBox {
+        BoundingBox::new(&[
+            self.corner,
+            self.corner + self.edge_u,
+            self.corner + self.edge_v,
+            self.corner + self.edge_u + self.edge_v,
+        ])
+    }
+}
+
+impl Intersect for Quad {
+    fn ray_intersect(&self, _geometry: &Geometry, ray: Ray) -> Option<Hit> {
+        let denominator = dot_product(&ray.dir, &self.normal);
+        if libm::fabsf(denominator) < f32::EPSILON {
+            return None;
+        }
+        let dist = dot_product(&(self.corner - ray.from), &self.normal) / denominator;
+        if dist < EPSILON {
+            return None;
+        }
+
+        let hit = ray.from + ray.dir * dist;
+        let planar = hit - self.corner;
+        let span = cross_product(&self.edge_u, &self.edge_v);
+        let w = span * dot_product(&span, &span).recip();
+        let u = dot_product(&w, &cross_product(&planar, &self.edge_v));
+        let v = dot_product(&w, &cross_product(&self.edge_u, &planar));
+        if !(0.0..=1.0).contains(&u) || !(0.0..=1.0).contains(&v) {
+            return None;
+        }
+
+        Some(Hit {
+            tangent: self.edge_u,
+            bitangent: self.edge_v,
+            ..Hit::new(hit, self.normal, Vec2::new(u, v), self.material)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vec3::assert_near;
+
+    #[test]
+    fn test_quad_intersect() {
+        let geometry = Geometry::default();
+        let quad = Quad::new(
+            Vec3::new(-1.0, 0.0, -1.0),
+            Vec3::new(4.0, 0.0, 0.0),
+            Vec3::new(0.0, 0.0, 2.0),
+            Default::default(),
+        )
+        .unwrap();
+        assert_near(Vec3::new(0.0, -1.0, 0.0), quad.normal);
+
+        let down = Vec3::new(0.0, -1.0, 0.0);
+        let hit = quad
+            .ray_intersect(&geometry, Ray::new(Vec3::new(0.0, 5.0, 0.5), down))
+            .unwrap();
+        assert_near(Vec3::new(0.0, 0.0, 0.5), hit.point);
+        assert_near(quad.normal, hit.normal);
+        assert!((hit.uv.x - 0.25).abs() < 1e-4 && (hit.uv.y - 0.75).abs() < 1e-4);
+        assert_near(quad.edge_u, hit.tangent);
+        assert_near(quad.edge_v, hit.bitangent);
+
+        // seen from below too
+        let up = Ray::new(Vec3::new(2.5, -5.0, -0.5), Vec3::new(0.0, 1.0, 0.0));
+        let hit = quad.ray_intersect(&geometry, up).unwrap();
+        assert!((hit.uv.x - 0.875).abs() < 1e-4 && (hit.uv.y - 0.25).abs() < 1e-4);
+
+        let beside = Ray::new(Vec3::new(3.5, 5.0, 0.0), down);
+        assert!(quad.ray_intersect(&geometry, beside).is_none());
+        let parallel = Ray::new(Vec3::new(-5.0, 0.0, 0.0), Vec3::new(1.0, 0.0, 0.0));
+        assert!(quad.ray_intersect(&geometry, parallel).is_none());
+        let away = Ray::new(Vec3::new(0.0, 5.0, 0.0), -down);
+        assert!(quad.ray_intersect(&geometry, away).is_none());
+
+        let edge = Vec3::new(1.0, 0.0, 0.0);
+        assert!(Quad::new(Vec3::default(), edge, edge * 2.0, Default::default()).is_none());
+        assert!(Quad::new(Vec3::default(), edge, Vec3::default(), Default::default()).is_none());
+    }
+}
@@ -0,0 +1,323 @@
+use alloc::boxed::Box;
+use alloc::sync::Arc;
+use core::fmt;
+
+use crate::entity::bounding_box::BoundingBox;
+use crate::intersect::{Hit, Intersect};
+use crate::material::Material;
+use crate::ray::Ray;
+use crate::scene::Geometry;
+use crate::utils::EPSILON;
+use crate::vec3::Vec3;
+
+const MAX_STEPS: usize = 256;
+const SURFACE_DISTANCE: f32 = 1e-4;
+const GRADIENT_STEP: f32 = 1e-4;
+
+// User distance function, it should never overestimate the distance to the surface.
+#[derive(Clone)]
+pub struct SdfFn(pub Arc<dyn Fn(&Vec3) -> f32 + Send + Sync>);
+
+impl fmt::Debug for SdfFn {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "SdfFn")
+    }
+}
+
+// Closures can't be stored, so a scene holding one fails to serialize with this error rather
+// than losing the node.
+#[cfg(feature = "serde")]
+const UNSERIALIZABLE: &str = "custom distance functions can not be serialized";
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for SdfFn {
+    fn serialize<S: serde::Serializer>(&self, _: S) -> Result<S::Ok, S::Error> {
+        Err(serde::ser::Error::custom(UNSERIALIZABLE))
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for SdfFn {
+    fn deserialize<D: serde::Deserializer<'de>>(_: D) -> Result<Self, D::Error> {
+        Err(serde::de::Error::custom(UNSERIALIZABLE))
+    }
+}
+
+// Distance field expression tree. Primitives are centered at the origin, axial ones along y.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum SdfNode {
+    Sphere {
+        radius: f32,
+    },
+    Box {
+        half_size: Vec3,
+    },
+    Torus {
+        major_radius: f32,
+        minor_radius: f32,
+    },
+    Capsule {
+        height: f32,
+        radius: f32,
+    },
+    Translate {
+        offset: Vec3,
+        node: Box<SdfNode>,
+    },
+    Union(Box<SdfNode>, Box<SdfNode>),
+    Intersection(Box<SdfNode>, Box<SdfNode>),
+    // first minus second
+    Subtraction(Box<SdfNode>, Box<SdfNode>),
+    SmoothUnion {
+        left: Box<SdfNode>,
+        right: Box<SdfNode>,
+        smoothness: f32,
+    },
+    SmoothSubtraction {
+        left: Box<SdfNode>,
+        right: Box<SdfNode>,
+        smoothness: f32,
+    },
+    Round {
+        node: Box<SdfNode>,
+        radius: f32,
+    },
+    // infinite repetition with the given cell size, zero components are not repeated
+    Repeat {
+        period: Vec3,
+        node: Box<SdfNode>,
+    },
+    // rotation around y by `rate` radians per unit of height
+    Twist {
+        rate: f32,
+        node: Box<SdfNode>,
+    },
+    // not serializable, see `SdfFn`
+    Custom(SdfFn),
+}
+
+// Polynomial smooth minimum (Quilez) and its blend factor.
+fn smooth_min(a: f32, b: f32, smoothness: f32) -> f32 {
+    let h = (0.5 + 0.5 * (b - a) / smoothness).clamp(0.0, 1.0);
+    b + (a - b) * h - smoothness * h * (1.0 - h)
+}
+
+impl SdfNode {
+    pub fn distance(&self, point: &Vec3) -> f32 {
+        match self {
+            SdfNode::Sphere { radius } => point.norm() - radius,
+            SdfNode::Box { half_size } => {
+                let q = point.map(libm::fabsf) - *half_size;
+                q.map(|c| c.max(0.0)).norm() + q.x.max(q.y).max(q.z).min(0.0)
+            }
+            SdfNode::Torus {
+                major_radius,
+                minor_radius,
+            } => {
+                let ring = libm::sqrtf(point.x * point.x + point.z * point.z) - major_radius;
+                libm::sqrtf(ring * ring + point.y * point.y) - minor_radius
+            }
+            SdfNode::Capsule { height, radius } => {
+                let y = point.y.clamp(0.0, *height);
+                (*point - Vec3::new(0.0, y, 0.0)).norm() - radius
+            }
+            SdfNode::Translate { offset, node } => node.distance(&(*point - *offset)),
+            SdfNode::Union(left, right) => left.distance(point).min(right.distance(point)),
+            SdfNode::Intersection(left, right) => left.distance(point).max(right.distance(point)),
+            SdfNode::Subtraction(left, right) => left.distance(point).max(-right.distance(point)),
+            SdfNode::SmoothUnion {
+                left,
+                right,
+                smoothness,
+            } => smooth_min(left.distance(point), right.distance(point), *smoothness),
+            SdfNode::SmoothSubtraction {
+                left,
+                right,
+                smoothness,
+            } => -smooth_min(-left.distance(point), right.distance(point), *smoothness),
+            SdfNode::Round { node, radius } => node.distance(point) - radius,
+            SdfNode::Repeat { period, node } => {
+                let wrap = |value: f32, period: f32| {
+                    if period > 0.0 {
+                        value - period * libm::roundf(value / period)
+                    } else {
+                        value
+                    }
+                };
+                let local = Vec3::new(
+                    wrap(point.x, period.x),
+                    wrap(point.y, period.y),
+                    wrap(point.z, period.z),
+                );
+                node.distance(&local)
+            }
+            SdfNode::Twist { rate, node } => {
+                let (sin, cos) = libm::sincosf(rate * point.y);
+                let local = Vec3::new(
+                    cos * point.x - sin * point.z,
+                    point.y,
+                    sin * point.x + cos * point.z,
+                );
+                node.distance(&local)
+            }
+            SdfNode::Custom(f) => (f.0)(point),
+        }
+    }
+
+    // Tetrahedral central differences.
+    pub fn gradient(&self, point: &Vec3) -> Vec3 {
+        [
+            Vec3::new(1.0, -1.0, -1.0),
+            Vec3::new(-1.0, -1.0, 1.0),
+            Vec3::new(-1.0, 1.0, -1.0),
+            Vec3::new(1.0, 1.0, 1.0),
+        ]
+        .iter()
+        .map(|offset| *offset * self.distance(&(*point + *offset * GRADIENT_STEP)))
+        .sum::<Vec3>()
+        .normalized()
+    }
+}
+
+// Implicit surface sphere traced inside `aabb`, which must enclose it.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Sdf {
+    pub node: SdfNode,
+    pub aabb: BoundingBox,
+    pub material: Material,
+}
+
+impl Sdf {
+    pub fn new(node: SdfNode, aabb: BoundingBox, material: Material) -> Sdf {
+        Sdf {
+            node,
+            aabb,
+            material,
+        }
+    }
+}
+
+impl Intersect for Sdf {
+    fn ray_intersect(&self, _geometry: &Geometry, ray: Ray) -> Option<Hit> {
+        let (near, far) = self.aabb.ray_range(&ray);
+        let at = |dist: f32| self.node.distance(&(ray.from + ray.dir * dist));
+
+        // rays leaving a refracting surface start inside, march on the distance to the outside
+        let mut dist = near.max(EPSILON);
+        let inside = at(dist) < 0.0;
+        let signed = |dist: f32| if inside { -at(dist) } else { at(dist) };
+        let mut prev = dist;
+
+        let mut steps = 0;
+        loop {
+            if dist > far || steps == MAX_STEPS {
+                return None;
+            }
+            let step = signed(dist);
+            if libm::fabsf(step) < SURFACE_DISTANCE * dist.max(1.0) {
+                break;
+            }
+            if step < 0.0 {
+                // stepped through the surface of a field that overestimates, bisect back to it
+                for _ in 0..16 {
+                    let mid = (prev + dist) * 0.5;
+                    if signed(mid) < 0.0 {
+                        dist = mid;
+                    } else {
+                        prev = mid;
+                    }
+                }
+                break;
+            }
+            prev = dist;
+            dist += step.max(SURFACE_DISTANCE);
+            steps += 1;
+        }
+
+        let point = ray.from + ray.dir * dist;
+        let normal = self.node.gradient(&point);
+        let normal = if normal.x.is_nan() { -ray.dir } else { normal };
+        Some(Hit::new(point, normal, Default::default(), self.material))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sdf_intersect() {
+        let node = SdfNode::Subtraction(
+            Box::new(SdfNode::Box {
+                half_size: Vec3::new(1.0, 1.0, 1.0),
+            }),
+            Box::new(SdfNode::Custom(SdfFn(Arc::new(|point: &Vec3| {
+                libm::sqrtf(point.x * point.x + point.y * point.y) - 0.5
+            })))),
+        );
+        let sdf = Sdf::new(
+            node,
+            BoundingBox::from_min_max(Vec3::new(-1.0, -1.0, -1.0), Vec3::new(1.0, 1.0, 1.0)),
+            Default::default(),
+        );
+        let geometry = Default::default();
+
+        let ray = Ray::new(Vec3::new(0.75, 0.0, 5.0), Vec3::new(0.0, 0.0, -1.0));
+        let hit = sdf.ray_intersect(&geometry, ray).unwrap();
+        assert!((hit.point - Vec3::new(0.75, 0.0, 1.0)).norm() < 1e-3);
+        assert!((hit.normal - Vec3::new(0.0, 0.0, 1.0)).norm() < 1e-2);
+
+        // through the drilled hole
+        let ray = Ray::new(Vec3::new(0.0, 0.0, 5.0), Vec3::new(0.0, 0.0, -1.0));
+        assert!(sdf.ray_intersect(&geometry, ray).is_none());
+
+        let ray = Ray::new(Vec3::new(0.0, 0.0, 0.0), Vec3::new(1.0, 0.0, 0.0));
+        let hit = sdf.ray_intersect(&geometry, ray).unwrap();
+        assert!((hit.point - Vec3::new(0.5, 0.0, 0.0)).norm() < 1e-3);
+        assert!((hit.normal - Vec3::new(-1.0, 0.0, 0.0)).norm() < 1e-2);
+    }
+
+    #[test]
+    fn test_sdf_combinators() {
+        let sphere = || Box::new(SdfNode::Sphere { radius: 1.0 });
+        let repeated = SdfNode::Repeat {
+            period: Vec3::new(4.0, 0.0, 0.0),
+            node: sphere(),
+        };
+        assert!(libm::fabsf(repeated.distance(&Vec3::new(8.0, 2.0, 0.0)) - 1.0) < 1e-5);
+
+        let blended = SdfNode::SmoothUnion {
+            left: sphere(),
+            right: Box::new(SdfNode::Translate {
+                offset: Vec3::new(2.5, 0.0, 0.0),
+                node: sphere(),
+            }),
+            smoothness: 0.5,
+        };
+        // the gap between the spheres is filled in
+        assert!(blended.distance(&Vec3::new(1.25, 0.0, 0.0)) < 0.25);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_sdf_serde() {
+        let node = SdfNode::Round {
+            node: Box::new(SdfNode::Sphere { radius: 1.0 }),
+            radius: 0.1,
+        };
+        let json = serde_json::to_string(&node).unwrap();
+        let restored: SdfNode = serde_json::from_str(&json).unwrap();
+        let origin = Vec3::default();
+        assert_eq!(node.distance(&origin), restored.distance(&origin));
+
+        // a custom node is an error, anywhere in the tree
+        let custom = SdfNode::Union(
+            Box::new(node),
+            Box::new(SdfNode::Custom(SdfFn(Arc::new(|point: &Vec3| point.y)))),
+        );
+        let error = serde_json::to_string(&custom).unwrap_err();
+        assert!(alloc::string::ToString::to_string(&error).contains(UNSERIALIZABLE));
+    }
+}
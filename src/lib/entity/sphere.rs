@@ -1,11 +1,16 @@
-use crate::intersect::Intersect;
+use core::f32::consts::PI;
+
+use crate::entity::bounding_box::BoundingBox;
+use crate::intersect::{Crossings, Hit, Intersect};
 use crate::material::Material;
 use crate::ray::Ray;
-use crate::render::RenderState;
+use crate::scene::Geometry;
 use crate::utils::EPSILON;
-use crate::vec3::{dot_product, Vec3};
+use crate::vec2::Vec2;
+use crate::vec3::{cross_product, dot_product, Vec3};
 
 #[derive(Debug, Copy, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Sphere {
     pub center: Vec3,
     radius: f32,
@@ -21,10 +26,42 @@ impl Sphere {
             material,
         }
     }
+
+    pub fn radius(&self) -> f32 {
+        self.radius
+    }
+
+    // Longitude and latitude of a surface normal, v goes from 0 at the bottom to 1 at the top.
+    fn uv(normal: &Vec3) -> Vec2 {
+        Vec2::new(
+            0.5 + libm::atan2f(normal.x, normal.z) / (2.0 * PI),
+            0.5 + libm::asinf(normal.y.clamp(-1.0, 1.0)) / PI,
+        )
+    }
+
+    // Derivatives of the point along the uv of `Sphere::uv`, except at the poles.
+    fn with_tangents(&self, hit: Hit) -> Hit {
+        let normal = hit.normal;
+        let ring = libm::sqrtf(normal.x * normal.x + normal.z * normal.z);
+        if ring < EPSILON {
+            return hit;
+        }
+        let east = Vec3::new(normal.z, 0.0, -normal.x);
+        Hit {
+            tangent: east * (2.0 * PI * self.radius),
+            bitangent: cross_product(&normal, &east) * (PI * self.radius / ring),
+            ..hit
+        }
+    }
+
+    pub fn bounding_box(&self) -> BoundingBox {
+        let radius = Vec3::new(self.radius, self.radius, self.radius);
+        BoundingBox::from_min_max(self.center - radius, self.center + radius)
+    }
 }
 
 impl Intersect for Sphere {
-    fn ray_intersect(&self, _state: &RenderState, ray: Ray) -> Option<(Vec3, Vec3, Material)> {
+    fn ray_intersect(&self, _geometry: &Geometry, ray: Ray) -> Option<Hit> {
         let l = self.center - ray.from;
         let tca = dot_product(&l, &ray.dir);
         let d2 = dot_product(&l, &l) - tca * tca;
@@ -45,6 +82,24 @@ impl Intersect for Sphere {
 
         let hit = ray.from + ray.dir * dist;
         let normal = (hit - self.center).normalized();
-        Some((hit, normal, self.material))
+        let hit = Hit::new(hit, normal, Sphere::uv(&normal), self.material);
+        Some(self.with_tangents(hit))
+    }
+}
+
+impl Crossings for Sphere {
+    fn crossings(&self, ray: &Ray, visit: &mut dyn FnMut(f32, Vec3, Vec2)) {
+        let l = self.center - ray.from;
+        let tca = dot_product(&l, &ray.dir);
+        let d2 = dot_product(&l, &l) - tca * tca;
+        let self_radius_sq = self.radius * self.radius;
+        if d2 > self_radius_sq {
+            return;
+        }
+        let thc = libm::sqrtf(self_radius_sq - d2);
+        for dist in [tca - thc, tca + thc] {
+            let normal = (ray.from + ray.dir * dist - self.center).normalized();
+            visit(dist, normal, Sphere::uv(&normal));
+        }
     }
 }
@@ -0,0 +1,141 @@
+use core::f32::consts::PI;
+
+use crate::entity::bounding_box::BoundingBox;
+use crate::frame::Frame;
+use crate::intersect::{nearest_crossing, Crossings, Hit, Intersect};
+use crate::material::Material;
+use crate::ray::Ray;
+use crate::scene::Geometry;
+use crate::solver::solve_quartic;
+use crate::vec2::Vec2;
+use crate::vec3::{dot_product, Vec3};
+
+// Ring around `frame.normal`, uv is (angle around the axis, angle around the tube) / 2pi.
+#[derive(Debug, Copy, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Torus {
+    pub center: Vec3,
+    pub frame: Frame,
+    pub major_radius: f32,
+    pub minor_radius: f32,
+    pub material: Material,
+}
+
+impl Torus {
+    // `None` unless the axis is not zero and the major radius exceeds the positive minor one.
+    pub fn new(
+        center: Vec3,
+        axis: Vec3,
+        major_radius: f32,
+        minor_radius: f32,
+        material: Material,
+    ) -> Option<Torus> {
+        let valid = axis.norm() > 0.0 && minor_radius > 0.0 && major_radius > minor_radius;
+        valid.then(|| Torus {
+            center,
+            frame: Frame::from_normal(&axis),
+            major_radius,
+            minor_radius,
+            material,
+        })
+    }
+
+    pub fn bounding_box(&self) -> BoundingBox {
+        BoundingBox::from_disk(self.center, self.frame.normal, self.major_radius)
+            .expanded(self.minor_radius)
+    }
+}
+
+impl Crossings for Torus {
+    fn crossings(&self, ray: &Ray, visit: &mut dyn FnMut(f32, Vec3, Vec2)) {
+        let dir = self.frame.to_local(&ray.dir);
+        let origin = self.frame.to_local(&(ray.from - self.center));
+        // solve from the point on the line closest to the center to keep the quartic well scaled
+        let shift = -dot_product(&origin, &dir);
+        let origin = origin + dir * shift;
+
+        let (o, d) = (
+            [origin.x as f64, origin.y as f64, origin.z as f64],
+            [dir.x as f64, dir.y as f64, dir.z as f64],
+        );
+        let major_sq = (self.major_radius * self.major_radius) as f64;
+        let minor_sq = (self.minor_radius * self.minor_radius) as f64;
+        let k = o[0] * d[0] + o[1] * d[1] + o[2] * d[2];
+        let s = o[0] * o[0] + o[1] * o[1] + o[2] * o[2] + major_sq - minor_sq;
+        let coeffs = [
+            s * s - 4.0 * major_sq * (o[0] * o[0] + o[1] * o[1]),
+            4.0 * k * s - 8.0 * major_sq * (o[0] * d[0] + o[1] * d[1]),
+            4.0 * k * k + 2.0 * s - 4.0 * major_sq * (d[0] * d[0] + d[1] * d[1]),
+            4.0 * k,
+            1.0,
+        ];
+
+        for dist in solve_quartic(coeffs).as_slice() {
+            let dist = *dist as f32;
+            let point = origin + dir * dist;
+            // gradient of (|p|^2 + R^2 - r^2)^2 - 4R^2(x^2 + y^2)
+            let major_sq = self.major_radius * self.major_radius;
+            let sum =
+                dot_product(&point, &point) + major_sq - self.minor_radius * self.minor_radius;
+            let normal = Vec3::new(
+                point.x * (sum - 2.0 * major_sq),
+                point.y * (sum - 2.0 * major_sq),
+                point.z * sum,
+            );
+            let ring = libm::sqrtf(point.x * point.x + point.y * point.y) - self.major_radius;
+            let uv = Vec2::new(
+                (libm::atan2f(point.y, point.x) + PI) / (2.0 * PI),
+                (libm::atan2f(point.z, ring) + PI) / (2.0 * PI),
+            );
+            visit(dist + shift, self.frame.to_world(&normal.normalized()), uv);
+        }
+    }
+}
+
+impl Intersect for Torus {
+    fn ray_intersect(&self, _geometry: &Geometry, ray: Ray) -> Option<Hit> {
+        let (dist, normal, uv) = nearest_crossing(self, &ray)?;
+        Some(Hit::new(
+            ray.from + ray.dir * dist,
+            normal,
+            uv,
+            self.material,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_torus_intersect() {
+        let torus = Torus::new(
+            Vec3::new(0.0, 0.0, -10.0),
+            Vec3::new(0.0, 1.0, 0.0),
+            3.0,
+            1.0,
+            Default::default(),
+        )
+        .unwrap();
+        let geometry = Default::default();
+
+        // straight through the hole
+        let ray = Ray::new(Vec3::new(0.0, 10.0, -10.0), Vec3::new(0.0, -1.0, 0.0));
+        assert!(torus.ray_intersect(&geometry, ray).is_none());
+
+        let ray = Ray::new(Vec3::new(0.0, 0.0, 0.0), Vec3::new(0.0, 0.0, -1.0));
+        let hit = torus.ray_intersect(&geometry, ray).unwrap();
+        assert!((hit.point - Vec3::new(0.0, 0.0, -6.0)).norm() < 1e-4);
+        assert!((hit.normal - Vec3::new(0.0, 0.0, 1.0)).norm() < 1e-4);
+
+        let ray = Ray::new(Vec3::new(3.0, 10.0, -10.0), Vec3::new(0.0, -1.0, 0.0));
+        let hit = torus.ray_intersect(&geometry, ray).unwrap();
+        assert!((hit.point - Vec3::new(3.0, 1.0, -10.0)).norm() < 1e-4);
+        assert!((hit.normal - Vec3::new(0.0, 1.0, 0.0)).norm() < 1e-4);
+
+        let (center, axis) = (Vec3::default(), Vec3::new(0.0, 1.0, 0.0));
+        assert!(Torus::new(center, axis, 1.0, 1.0, Default::default()).is_none());
+        assert!(Torus::new(center, Vec3::default(), 3.0, 1.0, Default::default()).is_none());
+    }
+}
@@ -1,23 +1,28 @@
-use crate::intersect::Intersect;
-use crate::material::Material;
+use crate::entity::bounding_box::BoundingBox;
+use crate::intersect::{Hit, Intersect};
 use crate::ray::Ray;
-use crate::render::RenderState;
-use crate::utils::{EPSILON, MaterialIdx, Vec3Idx};
+use crate::scene::Geometry;
+use crate::utils::{EPSILON, MaterialIdx, NormalIdx, UvIdx, Vec3Idx};
+use crate::vec2::Vec2;
 use crate::vec3::{cross_product, dot_product, Vec3};
 
 #[derive(Debug, Copy, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Triangle {
     pub points: [Vec3Idx; 3],
     pub normal: Vec3,
+    // vertex normals for smooth shading, indexing `Geometry::normal_buf`
+    pub normals: Option<[NormalIdx; 3]>,
+    pub uvs: Option<[UvIdx; 3]>,
     pub distance: f32,
     pub material: MaterialIdx,
 }
 
 impl Triangle {
-    pub fn new(state: &RenderState, points: [Vec3Idx; 3], material: MaterialIdx) -> Triangle {
-        let a = state.vec_buf.load(points[0]);
-        let b = state.vec_buf.load(points[1]);
-        let c = state.vec_buf.load(points[2]);
+    pub fn new(geometry: &Geometry, points: [Vec3Idx; 3], material: MaterialIdx) -> Triangle {
+        let a = geometry.vec_buf.load(points[0]);
+        let b = geometry.vec_buf.load(points[1]);
+        let c = geometry.vec_buf.load(points[2]);
 
         let normal = cross_product(&(*b - *a), &(*c - *b)).normalized();
         let distance = dot_product(a, &normal);
@@ -25,21 +30,23 @@ impl Triangle {
         Triangle {
             points,
             normal,
+            normals: None,
+            uvs: None,
             distance,
             material,
         }
     }
+
+    pub fn bounding_box(&self, geometry: &Geometry) -> BoundingBox {
+        BoundingBox::new(&self.points.map(|idx| *geometry.vec_buf.load(idx)))
+    }
 }
 
 impl Intersect for Triangle {
-    fn ray_intersect(&self, state: &RenderState, ray: Ray) -> Option<(Vec3, Vec3, Material)> {
-        if dot_product(&ray.dir, &self.normal) > EPSILON {
-            return None;
-        }
-
-        let a = state.vec_buf.load(self.points[0]);
-        let b = state.vec_buf.load(self.points[1]);
-        let c = state.vec_buf.load(self.points[2]);
+    fn ray_intersect(&self, geometry: &Geometry, ray: Ray) -> Option<Hit> {
+        let a = geometry.vec_buf.load(self.points[0]);
+        let b = geometry.vec_buf.load(self.points[1]);
+        let c = geometry.vec_buf.load(self.points[2]);
 
         let a_to_b = *b - *a;
         let a_to_c = *c - *a;
@@ -50,11 +57,9 @@ impl Intersect for Triangle {
         if libm::fabsf(det) < EPSILON {
             return None;
         }
-        let normal = if det < EPSILON {
-            -self.normal
-        } else {
-            self.normal
-        };
+        // back faces are hit too, with the normal of the winding order so that rays can tell
+        // leaving a closed mesh from entering it; shading flips it towards the viewer
+        let normal = self.normal;
 
         let inv_det = 1.0 / det;
 
@@ -76,9 +81,38 @@ impl Intersect for Triangle {
         let dist = dot_product(&a_to_c, &v_vec) * inv_det;
 
         if dist > EPSILON {
-            let self_material = state.material_buf.load(self.material);
-            let hit = ray.from + ray.dir * dist;
-            Some((hit, normal, *self_material))
+            let normal = match self.normals {
+                Some(normals) => {
+                    let [na, nb, nc] = normals.map(|idx| *geometry.normal_buf.load(idx));
+                    let shading = (na * (1.0 - u - v) + nb * u + nc * v).normalized();
+                    // keep the shading normal on the same side as the face normal it replaces
+                    if dot_product(&shading, &normal) < 0.0 {
+                        -shading
+                    } else {
+                        shading
+                    }
+                }
+                None => normal,
+            };
+            let [ta, tb, tc] = match self.uvs {
+                Some(uvs) => uvs.map(|idx| *geometry.uv_buf.load(idx)),
+                None => [Vec2::new(0.0, 0.0), Vec2::new(1.0, 0.0), Vec2::new(0.0, 1.0)],
+            };
+            let uv = ta * (1.0 - u - v) + tb * u + tc * v;
+            let self_material = geometry.material_buf.load(self.material);
+            let hit = Hit::new(ray.from + ray.dir * dist, normal, uv, *self_material);
+
+            // solve the edges for the derivatives along u and v, unless the uvs are degenerate
+            let (duv_b, duv_c) = (tb - ta, tc - ta);
+            let uv_det = duv_b.x * duv_c.y - duv_c.x * duv_b.y;
+            if libm::fabsf(uv_det) < 1e-12 {
+                return Some(hit);
+            }
+            Some(Hit {
+                tangent: (a_to_b * duv_c.y - a_to_c * duv_b.y) * uv_det.recip(),
+                bitangent: (a_to_c * duv_b.x - a_to_b * duv_c.x) * uv_det.recip(),
+                ..hit
+            })
         } else {
             None
         }
@@ -0,0 +1,339 @@
+use alloc::vec::Vec;
+use core::f32::consts::PI;
+
+use crate::intersect::Hit;
+use crate::material::Material;
+use crate::sampling::Rng;
+use crate::texture::Texture;
+use crate::utils::{TextureBuf, TextureIdx};
+use crate::vec2::Vec2;
+use crate::vec3::Vec3;
+
+// Cells of the luminance distribution along u for textures without a resolution of their
+// own, and half as many along the latitude.
+const GRID_WIDTH: usize = 512;
+
+// How the textures of an environment wrap around the scene, given by handle to the builder.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum Projection<T = TextureIdx> {
+    // equirectangular, +y at the top row and -z in the middle column
+    LatLong(T),
+    // faces looking along +x, -x, +y, -y, +z and -z from the inside, as in OpenGL
+    CubeMap([T; 6]),
+}
+
+// Light coming from infinitely far in every direction, seen as the background. Its texture
+// is rotated about +y by `rotation` radians and scaled by `intensity`. Directions are
+// importance sampled by luminance over a latitude-longitude grid, whatever the projection.
+#[derive(Debug, Clone)]
+pub struct Environment {
+    pub projection: Projection,
+    pub rotation: f32,
+    pub intensity: f32,
+    width: usize,
+    height: usize,
+    // running totals of the cell weights along each row, and of the row totals
+    cdf: Vec<f32>,
+    row_cdf: Vec<f32>,
+}
+
+// What an environment is stored as, its tables rebuilt from the textures by the scene when
+// loaded.
+#[cfg(feature = "serde")]
+#[derive(serde::Serialize, serde::Deserialize)]
+pub(crate) struct EnvironmentData {
+    projection: Projection,
+    rotation: f32,
+    intensity: f32,
+}
+
+#[cfg(feature = "serde")]
+impl EnvironmentData {
+    pub(crate) fn build(self, textures: &TextureBuf) -> Environment {
+        Environment::new(self.projection, self.rotation, self.intensity, textures)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for Environment {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let data = EnvironmentData {
+            projection: self.projection,
+            rotation: self.rotation,
+            intensity: self.intensity,
+        };
+        data.serialize(serializer)
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct EnvironmentSample {
+    pub dir: Vec3,
+    pub radiance: Vec3,
+    // over solid angle
+    pub pdf: f32,
+}
+
+impl Environment {
+    pub fn new(
+        projection: Projection,
+        rotation: f32,
+        intensity: f32,
+        textures: &TextureBuf,
+    ) -> Environment {
+        let (width, height) = match projection {
+            Projection::LatLong(idx) => match textures.get(idx) {
+                Some(Texture::Image(image)) => (image.width() as usize, image.height() as usize),
+                _ => (GRID_WIDTH, GRID_WIDTH / 2),
+            },
+            Projection::CubeMap(_) => (GRID_WIDTH, GRID_WIDTH / 2),
+        };
+        let mut environment = Environment {
+            projection,
+            rotation,
+            intensity,
+            width,
+            height,
+            cdf: Vec::with_capacity(width * height),
+            row_cdf: Vec::with_capacity(height),
+        };
+        let mut total = 0.0;
+        for row in 0..height {
+            let latitude = (row as f32 + 0.5) / height as f32;
+            // cells shrink towards the poles
+            let sin = libm::sinf(latitude * PI);
+            let mut sum = 0.0;
+            for column in 0..width {
+                let dir = grid_dir((column as f32 + 0.5) / width as f32, latitude);
+                sum += luminance(&environment.lookup(textures, &dir)) * sin;
+                environment.cdf.push(sum);
+            }
+            total += sum;
+            environment.row_cdf.push(total);
+        }
+        environment
+    }
+
+    // Radiance arriving from `dir`, a unit vector in world space.
+    pub fn radiance(&self, textures: &TextureBuf, dir: &Vec3) -> Vec3 {
+        self.lookup(textures, &rotated(dir, -self.rotation)) * self.intensity
+    }
+
+    fn lookup(&self, textures: &TextureBuf, dir: &Vec3) -> Vec3 {
+        let (idx, uv) = match self.projection {
+            Projection::LatLong(idx) => {
+                let (u, latitude) = grid_uv(dir);
+                (idx, Vec2::new(u, 1.0 - latitude))
+            }
+            Projection::CubeMap(faces) => {
+                let (face, uv) = cube_uv(dir);
+                (faces[face], uv)
+            }
+        };
+        let Some(texture) = textures.get(idx) else {
+            return Default::default();
+        };
+        // procedural textures in world or object space see the direction as the point
+        let hit = Hit {
+            object_point: *dir,
+            ..Hit::new(*dir, -*dir, uv, Material::default())
+        };
+        texture.sample(&hit, 0.0)
+    }
+
+    fn total(&self) -> f32 {
+        self.row_cdf.last().copied().unwrap_or(0.0)
+    }
+
+    // Direction picked in proportion to the luminance, `None` for a black environment.
+    pub fn sample(&self, textures: &TextureBuf, rng: &mut Rng) -> Option<EnvironmentSample> {
+        let total = self.total();
+        if total <= 0.0 {
+            return None;
+        }
+        let target = rng.next_f32() * total;
+        let row = self
+            .row_cdf
+            .partition_point(|sum| *sum < target)
+            .min(self.height - 1);
+        let cells = &self.cdf[row * self.width..(row + 1) * self.width];
+        let target = rng.next_f32() * cells[self.width - 1];
+        let column = cells
+            .partition_point(|sum| *sum < target)
+            .min(self.width - 1);
+        let jitter = rng.next_vec2();
+        let u = (column as f32 + jitter.x) / self.width as f32;
+        let latitude = (row as f32 + jitter.y) / self.height as f32;
+        let dir = rotated(&grid_dir(u, latitude), self.rotation);
+        let pdf = self.pdf(&dir);
+        (pdf > 0.0).then(|| EnvironmentSample {
+            dir,
+            radiance: self.radiance(textures, &dir),
+            pdf,
+        })
+    }
+
+    // Density of `sample` picking `dir`, over solid angle.
+    pub fn pdf(&self, dir: &Vec3) -> f32 {
+        let total = self.total();
+        if total <= 0.0 {
+            return 0.0;
+        }
+        let (u, latitude) = grid_uv(&rotated(dir, -self.rotation));
+        let sin_theta = libm::sinf(latitude * PI);
+        if sin_theta <= 0.0 {
+            return 0.0;
+        }
+        let column = ((u * self.width as f32) as usize).min(self.width - 1);
+        let row = ((latitude * self.height as f32) as usize).min(self.height - 1);
+        let idx = row * self.width + column;
+        let weight = self.cdf[idx] - if column > 0 { self.cdf[idx - 1] } else { 0.0 };
+        let pdf_uv = weight * (self.width * self.height) as f32 / total;
+        pdf_uv / (2.0 * PI * PI * sin_theta)
+    }
+}
+
+// `dir` rotated about +y by `angle` radians.
+fn rotated(dir: &Vec3, angle: f32) -> Vec3 {
+    let (sin, cos) = libm::sincosf(angle);
+    Vec3::new(cos * dir.x + sin * dir.z, dir.y, cos * dir.z - sin * dir.x)
+}
+
+fn luminance(color: &Vec3) -> f32 {
+    (0.2126 * color.x + 0.7152 * color.y + 0.0722 * color.z).max(0.0)
+}
+
+// Position of `dir` around the vertical axis from -z, and down from +y, both in [0, 1].
+fn grid_uv(dir: &Vec3) -> (f32, f32) {
+    let u = 0.5 + libm::atan2f(dir.x, -dir.z) / (2.0 * PI);
+    let latitude = libm::acosf(dir.y.clamp(-1.0, 1.0)) / PI;
+    (u, latitude)
+}
+
+fn grid_dir(u: f32, latitude: f32) -> Vec3 {
+    let (sin_theta, cos_theta) = libm::sincosf(latitude * PI);
+    let (sin_phi, cos_phi) = libm::sincosf((u - 0.5) * 2.0 * PI);
+    Vec3::new(sin_theta * sin_phi, cos_theta, -sin_theta * cos_phi)
+}
+
+// Face of the cube `dir` points at, and where on it.
+fn cube_uv(dir: &Vec3) -> (usize, Vec2) {
+    let (x, y, z) = (dir.x, dir.y, dir.z);
+    let (ax, ay, az) = (libm::fabsf(x), libm::fabsf(y), libm::fabsf(z));
+    let (face, major, s, t) = if ax >= ay && ax >= az {
+        if x > 0.0 {
+            (0, ax, -z, -y)
+        } else {
+            (1, ax, z, -y)
+        }
+    } else if ay >= az {
+        if y > 0.0 {
+            (2, ay, x, z)
+        } else {
+            (3, ay, x, -z)
+        }
+    } else if z > 0.0 {
+        (4, az, x, -y)
+    } else {
+        (5, az, -x, -y)
+    };
+    // t goes down the image, v up it
+    (
+        face,
+        Vec2::new(0.5 * (s / major + 1.0), 0.5 - 0.5 * (t / major)),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::texture::{Filter, ImageTexture, Texture, WrapMode};
+    use alloc::vec;
+
+    #[test]
+    fn test_environment_sampling() {
+        // a dim sky with one bright texel
+        let (width, height) = (16, 8);
+        let mut texels = vec![Vec3::new(0.1, 0.1, 0.1); width * height];
+        let sun = 2 * width + 11;
+        texels[sun] = Vec3::new(500.0, 500.0, 500.0);
+        let mut textures = TextureBuf::default();
+        let image = ImageTexture::new(
+            width as u32,
+            height as u32,
+            texels,
+            WrapMode::Repeat,
+            Filter::Nearest,
+        )
+        .unwrap();
+        let idx = textures.push(Texture::Image(image));
+        let environment = Environment::new(Projection::LatLong(idx), 1.0, 2.0, &textures);
+
+        let mut rng = Rng::new(5);
+        let count = 20000;
+        let samples: Vec<_> = (0..count)
+            .filter_map(|_| environment.sample(&textures, &mut rng))
+            .collect();
+        assert_eq!(count, samples.len());
+        let bright = samples
+            .iter()
+            .filter(|sample| sample.radiance.x > 100.0)
+            .count();
+        assert!(bright as f32 > 0.9 * count as f32);
+        for sample in &samples[..100] {
+            assert!((sample.pdf - environment.pdf(&sample.dir)).abs() <= 1e-3 * sample.pdf);
+        }
+
+        // the estimate of the irradiance on the sphere matches the sum over the texels
+        let estimate = samples
+            .iter()
+            .map(|sample| sample.radiance.x / sample.pdf)
+            .sum::<f32>()
+            / count as f32;
+        let expected: f32 = (0..width * height)
+            .map(|idx| {
+                let row = (idx / width) as f32;
+                let band = libm::cosf(row * PI / height as f32)
+                    - libm::cosf((row + 1.0) * PI / height as f32);
+                let value = if idx == sun { 500.0 } else { 0.1 };
+                value * 2.0 * band * 2.0 * PI / width as f32
+            })
+            .sum();
+        assert!(
+            (estimate - expected).abs() < 0.03 * expected,
+            "{estimate} {expected}"
+        );
+
+        // rotating turns the radiance and the density along with the texture
+        let unrotated = Environment::new(Projection::LatLong(idx), 0.0, 2.0, &textures);
+        for sample in &samples[..100] {
+            let turned = rotated(&sample.dir, -1.0);
+            assert_eq!(
+                sample.radiance,
+                environment.radiance(&textures, &sample.dir)
+            );
+            assert!((sample.radiance - unrotated.radiance(&textures, &turned)).norm() < 1e-3);
+            let pdf = unrotated.pdf(&turned);
+            assert!((sample.pdf - pdf).abs() <= 1e-3 * pdf);
+        }
+
+        // cube faces meet where the directions do
+        assert_eq!(cube_uv(&Vec3::new(1.0, 0.0, 0.0)), (0, Vec2::new(0.5, 0.5)));
+        assert_eq!(cube_uv(&Vec3::new(0.0, -1.0, 0.0)).0, 3);
+        let near = |a: Vec2, b: Vec2| libm::fabsf(a.x - b.x) + libm::fabsf(a.y - b.y) < 1e-2;
+        for along in [-0.5, 0.0, 0.7] {
+            // either side of the edge between +x and +z, the rows lining up
+            let (face, uv) = cube_uv(&Vec3::new(1.0, along, 0.999));
+            let (next, next_uv) = cube_uv(&Vec3::new(0.999, along, 1.0));
+            assert_eq!((0, 4), (face, next));
+            assert!(near(Vec2::new(0.0, uv.y), uv) && near(Vec2::new(1.0, uv.y), next_uv));
+            // and between +x and +y, the top row of one running down the side of the other
+            let (face, uv) = cube_uv(&Vec3::new(1.0, 0.999, along));
+            let (next, next_uv) = cube_uv(&Vec3::new(0.999, 1.0, along));
+            assert_eq!((0, 2), (face, next));
+            assert!(near(Vec2::new(uv.x, 1.0), uv) && near(Vec2::new(1.0, uv.x), next_uv));
+        }
+    }
+}
@@ -0,0 +1,34 @@
+use crate::vec3::{dot_product, orthonormal_basis, Vec3};
+
+// Orthonormal basis with `normal` as the local z axis.
+#[derive(Debug, Copy, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Frame {
+    pub tangent: Vec3,
+    pub bitangent: Vec3,
+    pub normal: Vec3,
+}
+
+impl Frame {
+    pub fn from_normal(normal: &Vec3) -> Frame {
+        let normal = normal.normalized();
+        let (tangent, bitangent) = orthonormal_basis(&normal);
+        Frame {
+            tangent,
+            bitangent,
+            normal,
+        }
+    }
+
+    pub fn to_local(&self, vec: &Vec3) -> Vec3 {
+        Vec3::new(
+            dot_product(vec, &self.tangent),
+            dot_product(vec, &self.bitangent),
+            dot_product(vec, &self.normal),
+        )
+    }
+
+    pub fn to_world(&self, vec: &Vec3) -> Vec3 {
+        self.tangent * vec.x + self.bitangent * vec.y + self.normal * vec.z
+    }
+}
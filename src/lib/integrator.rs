@@ -0,0 +1,669 @@
+use core::f32::consts::PI;
+
+use crate::bsdf::{fresnel_dielectric, Subsurface};
+use crate::frame::Frame;
+use crate::interior::{Boundary, Interior};
+use crate::intersect::Intersect;
+use crate::material::{MaterialKind, MaterialTextures};
+use crate::medium::{sample_henyey_greenstein, Medium};
+use crate::ray::Ray;
+use crate::render::{facing, reflect, refract, refract_full, Renderer};
+use crate::sampling::{cosine_hemisphere, Rng};
+use crate::spectrum::spectral;
+use crate::utils::EPSILON;
+use crate::vec3::{dot_product, Vec3};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(
+    feature = "serde",
+    serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)
+)]
+pub enum Integrator {
+    // recursive reflection and refraction lit by the point lights only, with one ray per
+    // glossy surface (distribution ray tracing) averaged over the samples, ignoring media.
+    // Emissive surfaces and the environment are seen, in reflections too, but light nothing.
+    Whitted {
+        #[cfg_attr(feature = "serde", serde(default = "one"))]
+        samples_per_pixel: u32,
+    },
+    // Monte Carlo path tracing, averaging paths of up to `recursion_limit` bounces
+    Path {
+        #[cfg_attr(feature = "serde", serde(default = "one"))]
+        samples_per_pixel: u32,
+    },
+}
+
+impl Default for Integrator {
+    fn default() -> Self {
+        Integrator::Whitted {
+            samples_per_pixel: 1,
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+fn one() -> u32 {
+    1
+}
+
+// Subsurface walks longer than this are given up on.
+const MAX_WALK: usize = 256;
+
+// Paths always run a few bounces before Russian roulette may end them.
+const ROULETTE_DEPTH: usize = 3;
+
+fn max_component(color: &Vec3) -> f32 {
+    color.x.max(color.y).max(color.z)
+}
+
+// Whether a path goes on after `depth` bounces, making up for the ended ones in `throughput`.
+fn survives(throughput: &mut Vec3, depth: usize, rng: &mut Rng) -> bool {
+    if depth + 1 < ROULETTE_DEPTH {
+        return true;
+    }
+    let survival = max_component(throughput).clamp(0.05, 1.0);
+    if rng.next_f32() >= survival {
+        return false;
+    }
+    *throughput = *throughput * (1.0 / survival);
+    true
+}
+
+// Weight of a sample from the strategy with density `pdf` against one with density `other`.
+fn power_heuristic(pdf: f32, other: f32) -> f32 {
+    let (pdf2, other2) = (pdf * pdf, other * other);
+    if pdf2 + other2 > 0.0 {
+        pdf2 / (pdf2 + other2)
+    } else {
+        0.0
+    }
+}
+
+impl Renderer<'_> {
+    // One path sample of the light coming back along `ray`. Point lights are sampled at every
+    // bounce, and `Phong` materials pick one of their diffuse, mirror and refraction lobes.
+    // Emissive surfaces are both sampled directly and found by the bounces, and the two are
+    // combined by multiple importance sampling. Media scatter the path where delta tracking
+    // finds a collision, and subsurface materials send it on from where a random walk beneath
+    // the surface leaves.
+    pub(crate) fn trace_path(
+        &self,
+        mut ray: Ray,
+        offsets: &[Ray; 2],
+        rng: &mut Rng,
+        wavelength: Option<f32>,
+    ) -> Vec3 {
+        let geometry = self.scene.geometry();
+        let mut radiance = Vec3::default();
+        let mut throughput = Vec3::new(1.0, 1.0, 1.0);
+        let mut interior = Interior::default();
+        // where the last bounce was sampled and its density, `None` for the camera and
+        // specular bounces, which light sampling can not reproduce
+        let mut scattered: Option<(Vec3, f32)> = None;
+
+        for depth in 0..self.settings.recursion_limit {
+            let hit = self.scene.root().ray_intersect(geometry, ray);
+            let max_dist = hit
+                .as_ref()
+                .map_or(f32::INFINITY, |hit| (hit.point - ray.from).norm());
+            let collision = self
+                .medium(&interior)
+                .and_then(|medium| Some((medium, medium.sample_collision(&ray, max_dist, rng)?)));
+            if let Some((medium, dist)) = collision {
+                let point = ray.from + ray.dir * dist;
+                throughput = throughput
+                    * interior.transmittance(dist)
+                    * spectral(&medium.albedo, wavelength);
+                let phase = |wi: &Vec3| {
+                    let value = medium.phase(&ray.dir, wi);
+                    (Vec3::new(value, value, value), value)
+                };
+                radiance = radiance
+                    + throughput * self.point_lights(&point, &interior, rng, wavelength, phase);
+                radiance = radiance
+                    + throughput * self.sample_emitters(&point, &interior, rng, wavelength, phase);
+
+                let dir = medium.sample_phase(&ray.dir, rng.next_vec2());
+                scattered = Some((point, medium.phase(&ray.dir, &dir)));
+                if !survives(&mut throughput, depth, rng) {
+                    break;
+                }
+                ray = Ray::new(point, dir);
+                continue;
+            }
+            let Some(hit) = hit else {
+                let escaped = throughput * interior.transmittance(f32::INFINITY);
+                // the environment is sampled along with the emitters
+                let weight = match (self.scene.environment(), scattered) {
+                    (Some(environment), Some((_, pdf))) => {
+                        power_heuristic(pdf, environment.pdf(&ray.dir.normalized()))
+                    }
+                    _ => 1.0,
+                };
+                return radiance + escaped * self.background(&ray.dir, wavelength) * weight;
+            };
+            throughput = throughput * interior.transmittance((hit.point - ray.from).norm());
+            // ray differentials only make sense before the first scattering
+            let plain =
+                hit.material.textures == MaterialTextures::default() && !hit.material.composite();
+            let footprint = if depth > 0 || plain {
+                0.0
+            } else {
+                self.footprint(&hit, offsets)
+            };
+            let (resolved, tint) = hit
+                .material
+                .resolve(geometry, &hit, &ray.dir, footprint, rng);
+            throughput = throughput * spectral(&tint, wavelength);
+            let normal = resolved.shading_normal(&geometry.texture_buf, &hit, footprint);
+            let material = resolved
+                .textured(&geometry.texture_buf, &hit, footprint)
+                .at_wavelength(wavelength);
+            let facing_normal = facing(&normal, &ray.dir);
+
+            if material.emissive() {
+                let weight = scattered.map_or(1.0, |(from, pdf)| {
+                    power_heuristic(
+                        pdf,
+                        self.scene.emitters().pdf(&from, &hit.point, &hit.normal),
+                    )
+                });
+                radiance = radiance + throughput * material.emission * weight;
+            }
+
+            let mut origin = hit.point;
+            let dir = match material.kind {
+                MaterialKind::Phong => {
+                    let direct: Vec3 = self
+                        .scene
+                        .lights()
+                        .iter()
+                        .map(|light| {
+                            let (diffuse, specular) = light.get_light_scales(
+                                &hit.point,
+                                &ray.dir,
+                                &facing_normal,
+                                &material,
+                            );
+                            let reach = self.transmittance(
+                                &hit.point,
+                                &light.position,
+                                interior,
+                                rng,
+                                wavelength,
+                            );
+                            (material.diffuse_color * (diffuse * material.albedo[0])
+                                + Vec3::new(1.0, 1.0, 1.0) * (specular * material.albedo[1]))
+                                * reach
+                        })
+                        .sum();
+                    radiance = radiance + throughput * direct;
+
+                    let diffuse_color = material.diffuse_color * material.albedo[0].max(0.0);
+                    let weights = [
+                        max_component(&diffuse_color),
+                        material.albedo[2].max(0.0),
+                        material.albedo[3].max(0.0),
+                    ];
+                    let total: f32 = weights.iter().sum();
+                    if total <= 0.0 {
+                        break;
+                    }
+                    // the density of picking the diffuse lobe and then `wi`
+                    let diffuse_pdf = |wi: &Vec3| {
+                        let cos = dot_product(wi, &facing_normal).max(0.0);
+                        (diffuse_color * (cos / PI), weights[0] / total * cos / PI)
+                    };
+                    if weights[0] > 0.0 {
+                        radiance = radiance
+                            + throughput
+                                * self.sample_emitters(
+                                    &hit.point,
+                                    &interior,
+                                    rng,
+                                    wavelength,
+                                    diffuse_pdf,
+                                );
+                    }
+                    let mut pick = rng.next_f32() * total;
+                    let lobe = weights
+                        .iter()
+                        .position(|weight| {
+                            pick -= weight;
+                            pick < 0.0
+                        })
+                        .unwrap_or(2);
+                    match lobe {
+                        0 => {
+                            throughput = throughput * diffuse_color * (total / weights[0]);
+                            let frame = Frame::from_normal(&facing_normal);
+                            let dir = frame.to_world(&cosine_hemisphere(rng.next_vec2()));
+                            scattered = Some((hit.point, diffuse_pdf(&dir).1));
+                            dir
+                        }
+                        1 => {
+                            throughput = throughput * total;
+                            scattered = None;
+                            reflect(&ray.dir, &normal)
+                        }
+                        _ => {
+                            throughput = throughput * total;
+                            scattered = None;
+                            refract(&ray.dir, &normal, material.refract_index)
+                                .unwrap_or_else(|| reflect(&ray.dir, &normal))
+                        }
+                    }
+                }
+                MaterialKind::Dielectric(dielectric) => {
+                    let entering = dot_product(&ray.dir, &hit.normal) < 0.0;
+                    match interior.cross(&dielectric, entering) {
+                        Boundary::Hidden(inside) => {
+                            interior = inside;
+                            ray.dir
+                        }
+                        // nothing bends at the surface of a medium in a matching dielectric
+                        Boundary::Visible {
+                            eta_i,
+                            eta_t,
+                            refracted,
+                        } if eta_i == eta_t => {
+                            interior = refracted;
+                            ray.dir
+                        }
+                        Boundary::Visible {
+                            eta_i,
+                            eta_t,
+                            refracted,
+                        } => {
+                            let frame = Frame::from_normal(&facing_normal);
+                            let wo = frame.to_local(&-ray.dir);
+                            let facet = frame.to_world(&dielectric.facet(&wo, rng));
+                            let cos_i = dot_product(&-ray.dir, &facet);
+                            let reflectance = fresnel_dielectric(cos_i, eta_i, eta_t);
+                            let (dir, side) = match refract_full(&ray.dir, &facet, eta_t, eta_i) {
+                                Some(dir) if rng.next_f32() >= reflectance => {
+                                    interior = refracted;
+                                    (dir, -1.0)
+                                }
+                                _ => (reflect(&ray.dir, &facet), 1.0),
+                            };
+                            // lost when the facet sends it to the wrong side of the surface
+                            let wi = frame.to_local(&dir);
+                            if wi.z * side <= 0.0 {
+                                break;
+                            }
+                            throughput = throughput * dielectric.facet_weight(&wo, &wi);
+                            scattered = None;
+                            dir
+                        }
+                    }
+                }
+                MaterialKind::Pbr(pbr) => {
+                    let frame = Frame::from_normal(&facing_normal);
+                    let wo = frame.to_local(&-ray.dir);
+                    radiance = radiance
+                        + throughput
+                            * self.direct_light(&pbr, &frame, &wo, &hit.point, |light| {
+                                self.transmittance(
+                                    &hit.point,
+                                    &light.position,
+                                    interior,
+                                    rng,
+                                    wavelength,
+                                )
+                            });
+                    let eval = |dir: &Vec3| {
+                        let wi = frame.to_local(dir);
+                        (pbr.eval(&wo, &wi), pbr.pdf(&wo, &wi))
+                    };
+                    radiance = radiance
+                        + throughput
+                            * self.sample_emitters(&hit.point, &interior, rng, wavelength, eval);
+
+                    let Some(sample) = pbr.sample(&wo, rng) else {
+                        break;
+                    };
+                    throughput = throughput * sample.weight;
+                    scattered = Some((hit.point, sample.pdf));
+                    frame.to_world(&sample.wi)
+                }
+                // resolved above
+                MaterialKind::Mix(_) | MaterialKind::Layered(_) => break,
+                MaterialKind::Subsurface(subsurface) => {
+                    let outside = interior.ior();
+                    let cos_i = dot_product(&-ray.dir, &facing_normal);
+                    let reflectance = fresnel_dielectric(cos_i, outside, subsurface.ior);
+                    match refract_full(&ray.dir, &facing_normal, subsurface.ior, outside) {
+                        Some(inward) if rng.next_f32() >= reflectance => {
+                            let walk =
+                                self.random_walk(&hit.point, &inward, &subsurface, outside, rng);
+                            let Some((exit, exit_normal, weight)) = walk else {
+                                break;
+                            };
+                            throughput = throughput * weight;
+                            // light leaves diffusely, as off a white Lambertian surface
+                            let lambert = |wi: &Vec3| {
+                                let pdf = dot_product(wi, &exit_normal).max(0.0) / PI;
+                                (Vec3::new(pdf, pdf, pdf), pdf)
+                            };
+                            radiance = radiance
+                                + throughput
+                                    * self.point_lights(&exit, &interior, rng, wavelength, lambert);
+                            radiance = radiance
+                                + throughput
+                                    * self.sample_emitters(
+                                        &exit, &interior, rng, wavelength, lambert,
+                                    );
+                            let dir = Frame::from_normal(&exit_normal)
+                                .to_world(&cosine_hemisphere(rng.next_vec2()));
+                            scattered = Some((exit, lambert(&dir).1));
+                            origin = exit;
+                            dir
+                        }
+                        _ => {
+                            scattered = None;
+                            reflect(&ray.dir, &facing_normal)
+                        }
+                    }
+                }
+            };
+
+            if !survives(&mut throughput, depth, rng) {
+                break;
+            }
+            ray = Ray::new(origin, dir.normalized());
+        }
+        radiance
+    }
+
+    // Light from one point sampled on the emitters and one direction sampled on the
+    // environment, `bsdf` giving the reflected fraction times the cosine and the density of
+    // scattering the same way for a direction.
+    fn sample_emitters(
+        &self,
+        point: &Vec3,
+        interior: &Interior,
+        rng: &mut Rng,
+        wavelength: Option<f32>,
+        bsdf: impl Fn(&Vec3) -> (Vec3, f32),
+    ) -> Vec3 {
+        self.sample_surfaces(point, interior, rng, wavelength, &bsdf)
+            + self.sample_environment(point, interior, rng, wavelength, &bsdf)
+    }
+
+    fn sample_surfaces(
+        &self,
+        point: &Vec3,
+        interior: &Interior,
+        rng: &mut Rng,
+        wavelength: Option<f32>,
+        bsdf: impl Fn(&Vec3) -> (Vec3, f32),
+    ) -> Vec3 {
+        let Some(sample) = self.scene.emitters().sample(point, rng) else {
+            return Default::default();
+        };
+        let to_light = sample.point - *point;
+        let dist = to_light.norm();
+        let (value, pdf) = bsdf(&(to_light * (1.0 / dist)));
+        if max_component(&value) <= 0.0 {
+            return Default::default();
+        }
+        let reach = self.transmittance(point, &sample.point, *interior, rng, wavelength);
+        value
+            * reach
+            * spectral(&sample.emission, wavelength)
+            * (power_heuristic(sample.pdf, pdf) / sample.pdf)
+    }
+
+    fn sample_environment(
+        &self,
+        point: &Vec3,
+        interior: &Interior,
+        rng: &mut Rng,
+        wavelength: Option<f32>,
+        bsdf: impl Fn(&Vec3) -> (Vec3, f32),
+    ) -> Vec3 {
+        let Some(environment) = self.scene.environment() else {
+            return Default::default();
+        };
+        let Some(sample) = environment.sample(&self.scene.geometry().texture_buf, rng) else {
+            return Default::default();
+        };
+        let (value, pdf) = bsdf(&sample.dir);
+        if max_component(&value) <= 0.0 {
+            return Default::default();
+        }
+        let ray = Ray::new(*point, sample.dir);
+        let reach = self.transmittance_along(ray, f32::INFINITY, *interior, rng, wavelength);
+        value
+            * reach
+            * spectral(&sample.radiance, wavelength)
+            * (power_heuristic(sample.pdf, pdf) / sample.pdf)
+    }
+
+    // Light from the point lights, `bsdf` as for `sample_emitters`.
+    fn point_lights(
+        &self,
+        point: &Vec3,
+        interior: &Interior,
+        rng: &mut Rng,
+        wavelength: Option<f32>,
+        bsdf: impl Fn(&Vec3) -> (Vec3, f32),
+    ) -> Vec3 {
+        self.scene
+            .lights()
+            .iter()
+            .map(|light| {
+                let (value, _) = bsdf(&(light.position - *point).normalized());
+                let reach = self.transmittance(point, &light.position, *interior, rng, wavelength);
+                value * (PI * light.intensity) * reach
+            })
+            .sum()
+    }
+
+    // Walk beneath the surface of `subsurface` from `point` along `dir` until the light leaves
+    // through it, giving where, the outward normal there and the weight of the walk. Any
+    // surface met bounds the walk.
+    fn random_walk(
+        &self,
+        point: &Vec3,
+        dir: &Vec3,
+        subsurface: &Subsurface,
+        outside: f32,
+        rng: &mut Rng,
+    ) -> Option<(Vec3, Vec3, Vec3)> {
+        let mut ray = Ray::new(*point, dir.normalized());
+        let channel = ((rng.next_f32() * 3.0) as usize).min(2);
+        let (mut value, mut pdf) = (Vec3::new(1.0, 1.0, 1.0), Vec3::new(1.0, 1.0, 1.0));
+        for _ in 0..MAX_WALK {
+            let hit = self
+                .scene
+                .root()
+                .ray_intersect(self.scene.geometry(), ray)?;
+            let dist = (hit.point - ray.from).norm();
+            let (step, step_value, step_pdf) = subsurface.sample_distance(channel, dist, rng);
+            // only the ratio matters, rescaled to stay in range over long walks
+            let scale = max_component(&(pdf * step_pdf));
+            if scale <= 0.0 || scale.is_nan() {
+                return None;
+            }
+            value = value * step_value * (1.0 / scale);
+            pdf = pdf * step_pdf * (1.0 / scale);
+            if step < dist {
+                let dir =
+                    sample_henyey_greenstein(subsurface.anisotropy, &ray.dir, rng.next_vec2());
+                ray = Ray::new(ray.from + ray.dir * step, dir);
+                continue;
+            }
+            let outward = -facing(&hit.normal, &ray.dir);
+            let cos = dot_product(&ray.dir, &outward);
+            if rng.next_f32() < fresnel_dielectric(cos, subsurface.ior, outside) {
+                ray = Ray::new(hit.point, reflect(&ray.dir, &outward));
+                continue;
+            }
+            return Some((hit.point, outward, value * (3.0 / (pdf.x + pdf.y + pdf.z))));
+        }
+        None
+    }
+
+    // Fraction of the light getting from `from` to `to`, going through the surfaces that do not
+    // bend it: hidden ones, and those of media in dielectrics matching the index around them.
+    fn transmittance(
+        &self,
+        from: &Vec3,
+        to: &Vec3,
+        interior: Interior,
+        rng: &mut Rng,
+        wavelength: Option<f32>,
+    ) -> Vec3 {
+        let dist = (*to - *from).norm();
+        let ray = Ray::new(*from, (*to - *from) * (1.0 / dist));
+        self.transmittance_along(ray, dist, interior, rng, wavelength)
+    }
+
+    // As `transmittance` over the first `left` of the ray, which may be infinite.
+    fn transmittance_along(
+        &self,
+        mut ray: Ray,
+        mut left: f32,
+        mut interior: Interior,
+        rng: &mut Rng,
+        wavelength: Option<f32>,
+    ) -> Vec3 {
+        let mut transmittance = Vec3::new(1.0, 1.0, 1.0);
+        loop {
+            let hit = self.scene.root().ray_intersect(self.scene.geometry(), ray);
+            let dist = hit
+                .as_ref()
+                .map_or(left, |hit| (hit.point - ray.from).norm().min(left));
+            transmittance = transmittance * interior.transmittance(dist);
+            if let Some(medium) = self.medium(&interior) {
+                transmittance = transmittance * medium.transmittance(&ray, dist, rng);
+            }
+            let Some(hit) = hit.filter(|_| dist < left - EPSILON) else {
+                return transmittance;
+            };
+            let MaterialKind::Dielectric(dielectric) = hit.material.at_wavelength(wavelength).kind
+            else {
+                return Default::default();
+            };
+            let entering = dot_product(&ray.dir, &hit.normal) < 0.0;
+            interior = match interior.cross(&dielectric, entering) {
+                Boundary::Hidden(inside) => inside,
+                Boundary::Visible {
+                    eta_i,
+                    eta_t,
+                    refracted,
+                } if eta_i == eta_t => refracted,
+                Boundary::Visible { .. } => return Default::default(),
+            };
+            left -= dist;
+            ray = Ray::new(hit.point, ray.dir);
+        }
+    }
+
+    // Participating medium around a path, the atmosphere outside of dielectrics.
+    fn medium(&self, interior: &Interior) -> Option<&Medium> {
+        let idx = interior.medium(self.scene.atmosphere())?;
+        self.scene.geometry().medium_buf.get(idx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::builder::SceneBuilder;
+    use crate::camera::Camera;
+    use crate::material::Material;
+    use crate::render::RenderSettings;
+    use crate::scene::Scene;
+    use alloc::vec::Vec;
+
+    // Results of `count` walks from `point` along `dir` beneath `subsurface`.
+    fn walk_all(
+        scene: &Scene,
+        point: Vec3,
+        dir: Vec3,
+        subsurface: &Subsurface,
+        count: usize,
+    ) -> Vec<Option<(Vec3, Vec3, Vec3)>> {
+        let camera = Camera::new(Default::default(), Vec3::new(0.0, 0.0, -1.0), 1.0).unwrap();
+        let settings = RenderSettings::new(1, 1);
+        let renderer = Renderer::new(scene, &camera, &settings);
+        let mut rng = Rng::new(5);
+        (0..count)
+            .map(|_| renderer.random_walk(&point, &dir, subsurface, 1.0, &mut rng))
+            .collect()
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_integrator_defaults() {
+        let path: Integrator = serde_json::from_str(r#"{ "type": "path" }"#).unwrap();
+        assert_eq!(
+            Integrator::Path {
+                samples_per_pixel: 1
+            },
+            path
+        );
+    }
+
+    #[test]
+    fn test_subsurface_energy() {
+        // a white slab ten mean free paths thick takes in light from straight above, and
+        // sends all of it back out, mostly upward, bar the few walks running too long
+        let white = Subsurface::new(1.33, Vec3::new(1.0, 1.0, 1.0), Vec3::new(0.1, 0.1, 0.1));
+        let mut builder = SceneBuilder::new();
+        let slab = builder.add_material(Material::subsurface(white)).unwrap();
+        builder
+            .add_cuboid(
+                Vec3::new(-100.0, -1.0, -100.0),
+                Vec3::new(100.0, 0.0, 100.0),
+                slab,
+            )
+            .unwrap();
+        let scene = builder.build();
+        let count = 20000;
+        let walks = walk_all(
+            &scene,
+            Vec3::default(),
+            Vec3::new(0.0, -1.0, 0.0),
+            &white,
+            count,
+        );
+        let (mut up, mut down) = (Vec3::default(), Vec3::default());
+        for (_, normal, weight) in walks.into_iter().flatten() {
+            if normal.y > 0.0 {
+                up = up + weight * (1.0 / count as f32);
+            } else {
+                down = down + weight * (1.0 / count as f32);
+            }
+        }
+        let total = up + down;
+        assert!((total.x - 1.0).abs() < 0.04, "{up:?} {down:?}");
+        assert!((total - Vec3::new(total.x, total.x, total.x)).norm() < 1e-4);
+        assert!(up.x > 3.0 * down.x);
+    }
+
+    #[test]
+    fn test_subsurface_walk_ends() {
+        // a sphere whose surface reflects nearly everything inside traps the walks, which
+        // are then given up on rather than followed forever
+        let trapping = Subsurface::new(1e4, Vec3::new(1.0, 1.0, 1.0), Vec3::new(0.1, 0.1, 0.1));
+        let mut builder = SceneBuilder::new();
+        let ball = builder
+            .add_material(Material::subsurface(trapping))
+            .unwrap();
+        builder.add_sphere(Vec3::default(), 1.0, ball).unwrap();
+        let scene = builder.build();
+        let walks = walk_all(
+            &scene,
+            Vec3::new(0.0, 1.0, 0.0),
+            Vec3::new(0.0, -1.0, 0.0),
+            &trapping,
+            100,
+        );
+        assert!(walks.iter().all(Option::is_none));
+    }
+}
@@ -0,0 +1,157 @@
+use crate::bsdf::Dielectric;
+use crate::utils::MediumIdx;
+use crate::vec3::Vec3;
+
+const MAX_DEPTH: usize = 8;
+
+// Dielectrics a ray is inside of, for nested dielectrics (Schmidt and Budge 2002). Surfaces
+// of a medium hidden inside one with a higher priority do not refract.
+#[derive(Debug, Clone, Copy)]
+pub struct Interior {
+    media: [Dielectric; MAX_DEPTH],
+    len: usize,
+}
+
+// What happens to a ray crossing the surface of a dielectric.
+#[derive(Debug, Clone, Copy)]
+pub enum Boundary {
+    // the surface is hidden, the ray goes on straight
+    Hidden(Interior),
+    // the ray refracts from `eta_i` into `eta_t`, and is inside `refracted` if it does
+    Visible {
+        eta_i: f32,
+        eta_t: f32,
+        refracted: Interior,
+    },
+}
+
+impl Default for Interior {
+    fn default() -> Self {
+        Interior {
+            media: [Dielectric::new(1.0); MAX_DEPTH],
+            len: 0,
+        }
+    }
+}
+
+impl Interior {
+    // The medium with the highest priority, the innermost among equals.
+    fn top(&self) -> Option<&Dielectric> {
+        self.media[..self.len]
+            .iter()
+            .rev()
+            .max_by_key(|medium| medium.priority)
+    }
+
+    // Index of refraction around the ray, vacuum outside of everything.
+    pub fn ior(&self) -> f32 {
+        self.top().map_or(1.0, |medium| medium.ior)
+    }
+
+    // Medium around the ray, `outside` when not inside any dielectric.
+    pub fn medium(&self, outside: Option<MediumIdx>) -> Option<MediumIdx> {
+        match self.top() {
+            Some(medium) => medium.medium,
+            None => outside,
+        }
+    }
+
+    // Fraction of the light left after going `dist` through the medium around the ray.
+    pub fn transmittance(&self, dist: f32) -> Vec3 {
+        let absorption = self.top().map_or(Vec3::default(), |medium| medium.absorption);
+        absorption.map(|coefficient| {
+            if coefficient > 0.0 {
+                libm::expf(-coefficient * dist)
+            } else {
+                1.0
+            }
+        })
+    }
+
+    fn entered(&self, medium: &Dielectric) -> Interior {
+        let mut interior = *self;
+        if interior.len == MAX_DEPTH {
+            interior.media.copy_within(1.., 0);
+            interior.len -= 1;
+        }
+        interior.media[interior.len] = *medium;
+        interior.len += 1;
+        interior
+    }
+
+    fn left(&self, medium: &Dielectric) -> Interior {
+        let mut interior = *self;
+        if let Some(idx) = self.media[..self.len].iter().rposition(|inside| inside == medium) {
+            interior.media.copy_within(idx + 1..self.len, idx);
+            interior.len -= 1;
+        }
+        interior
+    }
+
+    pub fn cross(&self, medium: &Dielectric, entering: bool) -> Boundary {
+        let (inner, outer) = if entering {
+            (self.entered(medium), *self)
+        } else {
+            (*self, self.left(medium))
+        };
+        if outer.top().is_some_and(|top| top.priority > medium.priority) {
+            return Boundary::Hidden(if entering { inner } else { outer });
+        }
+        if entering {
+            Boundary::Visible {
+                eta_i: outer.ior(),
+                eta_t: medium.ior,
+                refracted: inner,
+            }
+        } else {
+            Boundary::Visible {
+                eta_i: medium.ior,
+                eta_t: outer.ior(),
+                refracted: outer,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_glass_in_water() {
+        let water = Dielectric {
+            absorption: Vec3::new(0.5, 0.1, 0.0),
+            ..Dielectric::new(1.33)
+        };
+        let glass = Dielectric {
+            priority: 1,
+            ..Dielectric::new(1.5)
+        };
+        let visible = |boundary: Boundary| match boundary {
+            Boundary::Visible {
+                eta_i,
+                eta_t,
+                refracted,
+            } => (eta_i, eta_t, refracted),
+            Boundary::Hidden(_) => panic!("surface should refract"),
+        };
+
+        let (eta_i, eta_t, in_water) = visible(Interior::default().cross(&water, true));
+        assert_eq!((1.0, 1.33), (eta_i, eta_t));
+        let (eta_i, eta_t, in_glass) = visible(in_water.cross(&glass, true));
+        assert_eq!((1.33, 1.5), (eta_i, eta_t));
+        // only the water absorbs, and not where the glass displaces it
+        let tint = in_water.transmittance(2.0);
+        assert!((tint.x - libm::expf(-1.0)).abs() < 1e-6 && tint.z == 1.0);
+        assert_eq!(Vec3::new(1.0, 1.0, 1.0), in_glass.transmittance(2.0));
+
+        // the water surface inside the glass does not count
+        let Boundary::Hidden(glass_only) = in_glass.cross(&water, false) else {
+            panic!("surface should be hidden");
+        };
+        assert_eq!(1.5, glass_only.ior());
+        let (eta_i, eta_t, outside) = visible(glass_only.cross(&glass, false));
+        assert_eq!((1.5, 1.0), (eta_i, eta_t));
+        assert_eq!(1.0, outside.ior());
+    }
+}
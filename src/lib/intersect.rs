@@ -1,8 +1,55 @@
 use crate::ray::Ray;
-use crate::render::RenderState;
+use crate::scene::Geometry;
+use crate::utils::EPSILON;
 use super::material::Material;
-use super::vec3::Vec3;
+use super::vec2::Vec2;
+use super::vec3::{orthonormal_basis, Vec3};
+
+#[derive(Debug, Copy, Clone)]
+pub struct Hit {
+    pub point: Vec3,
+    pub normal: Vec3,
+    pub uv: Vec2,
+    // derivatives of the point along u and v, not normalized
+    pub tangent: Vec3,
+    pub bitangent: Vec3,
+    // the point before any instance transforms, for object space textures
+    pub object_point: Vec3,
+    pub material: Material,
+}
+
+impl Hit {
+    // Surfaces without a natural uv parametrization get an arbitrary unit frame.
+    pub fn new(point: Vec3, normal: Vec3, uv: Vec2, material: Material) -> Hit {
+        let (tangent, bitangent) = orthonormal_basis(&normal);
+        Hit {
+            point,
+            normal,
+            uv,
+            tangent,
+            bitangent,
+            object_point: point,
+            material,
+        }
+    }
+}
 
 pub trait Intersect {
-    fn ray_intersect(&self, state: &RenderState, ray: Ray) -> Option<(Vec3, Vec3, Material)>;
+    fn ray_intersect(&self, geometry: &Geometry, ray: Ray) -> Option<Hit>;
+}
+
+// Implemented by analytic surfaces that can report every point where the line through a ray
+// crosses them, at any distance and in no particular order, with world space normals.
+pub(crate) trait Crossings {
+    fn crossings(&self, ray: &Ray, visit: &mut dyn FnMut(f32, Vec3, Vec2));
+}
+
+pub(crate) fn nearest_crossing(surface: &impl Crossings, ray: &Ray) -> Option<(f32, Vec3, Vec2)> {
+    let mut nearest: Option<(f32, Vec3, Vec2)> = None;
+    surface.crossings(ray, &mut |dist, normal, uv| {
+        if dist > EPSILON && nearest.is_none_or(|(best, _, _)| dist < best) {
+            nearest = Some((dist, normal, uv));
+        }
+    });
+    nearest
 }
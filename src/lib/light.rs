@@ -5,6 +5,7 @@ use crate::render::reflect;
 use crate::vec3::{dot_product, Vec3};
 
 #[derive(Debug, Copy, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Light {
     pub position: Vec3,
     pub intensity: f32,
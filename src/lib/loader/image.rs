@@ -0,0 +1,72 @@
+use alloc::string::ToString;
+use alloc::vec::Vec;
+
+use image::DynamicImage;
+
+use super::LoadError;
+use crate::texture::{ColorSpace, Filter, ImageTexture, WrapMode};
+use crate::vec3::Vec3;
+
+// Decodes a PNG, JPEG or Radiance HDR image, guessing the format from its contents. Integer
+// images are converted from `color_space`, floating point ones are always linear.
+pub fn load_image(
+    data: &[u8],
+    color_space: ColorSpace,
+    wrap: WrapMode,
+    filter: Filter,
+) -> Result<ImageTexture, LoadError> {
+    let image = image::load_from_memory(data)
+        .map_err(|error| LoadError::InvalidImage(error.to_string()))?;
+    let (width, height) = (image.width(), image.height());
+
+    let texels: Vec<Vec3> = match image {
+        DynamicImage::ImageRgb32F(_) | DynamicImage::ImageRgba32F(_) => image
+            .into_rgb32f()
+            .pixels()
+            .map(|pixel| Vec3::new(pixel[0], pixel[1], pixel[2]))
+            .collect(),
+        image => {
+            let decode = |value: u16| color_space.to_linear(value as f32 / u16::MAX as f32);
+            image
+                .into_rgb16()
+                .pixels()
+                .map(|pixel| Vec3::new(decode(pixel[0]), decode(pixel[1]), decode(pixel[2])))
+                .collect()
+        }
+    };
+    ImageTexture::new(width, height, texels, wrap, filter)
+        .ok_or_else(|| LoadError::InvalidImage("image is empty".into()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{ImageOutputFormat, RgbImage};
+    use std::io::Cursor;
+
+    #[test]
+    fn test_load_png() {
+        let image = RgbImage::from_fn(4, 2, |x, _| [if x < 2 { 255 } else { 128 }, 0, 0].into());
+        let mut data = Vec::new();
+        DynamicImage::ImageRgb8(image)
+            .write_to(&mut Cursor::new(&mut data), ImageOutputFormat::Png)
+            .unwrap();
+
+        let texture =
+            load_image(&data, ColorSpace::Srgb, WrapMode::Clamp, Filter::Nearest).unwrap();
+        assert_eq!(
+            (4, 2, 3),
+            (texture.width(), texture.height(), texture.mip_levels())
+        );
+        let right = texture.sample(crate::vec2::Vec2::new(0.9, 0.5), 0.0);
+        assert!((right.x - 0.216).abs() < 1e-3 && right.y == 0.0);
+
+        assert!(load_image(
+            &data[..10],
+            ColorSpace::Srgb,
+            WrapMode::Clamp,
+            Filter::Nearest
+        )
+        .is_err());
+    }
+}
@@ -0,0 +1,276 @@
+#[cfg(feature = "std")]
+pub mod image;
+pub mod obj;
+pub mod ply;
+pub mod stl;
+
+use alloc::collections::BTreeMap;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt;
+use core::ops::Range;
+
+use crate::entity::model::Model;
+use crate::entity::triangle::Triangle;
+use crate::scene::Geometry;
+use crate::utils::{MaterialIdx, NormalIdx, UvIdx, Vec3Idx};
+use crate::vec2::Vec2;
+use crate::vec3::{cross_product, dot_product, Vec3};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum LoadError {
+    UnexpectedEof,
+    InvalidHeader(String),
+    UnsupportedFormat(String),
+    InvalidNumber(String),
+    InvalidUtf8,
+    IndexOutOfRange { face: usize, index: usize },
+    EmptyMesh,
+    InvalidImage(String),
+}
+
+impl fmt::Display for LoadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LoadError::UnexpectedEof => write!(f, "unexpected end of data"),
+            LoadError::InvalidHeader(line) => write!(f, "invalid header line: {line:?}"),
+            LoadError::UnsupportedFormat(format) => write!(f, "unsupported format: {format}"),
+            LoadError::InvalidNumber(token) => write!(f, "failed to parse number: {token:?}"),
+            LoadError::InvalidUtf8 => write!(f, "text data is not valid utf-8"),
+            LoadError::IndexOutOfRange { face, index } => {
+                write!(f, "face {face} references missing vertex {index}")
+            }
+            LoadError::EmptyMesh => write!(f, "mesh has no faces"),
+            LoadError::InvalidImage(message) => write!(f, "failed to decode image: {message}"),
+        }
+    }
+}
+
+impl core::error::Error for LoadError {}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum MeshFormat {
+    Obj,
+    Ply,
+    Stl,
+}
+
+impl MeshFormat {
+    pub fn from_path(path: &str) -> Option<MeshFormat> {
+        let (_, ext) = path.rsplit_once('.')?;
+        [
+            ("obj", MeshFormat::Obj),
+            ("ply", MeshFormat::Ply),
+            ("stl", MeshFormat::Stl),
+        ]
+        .into_iter()
+        .find(|(name, _)| ext.eq_ignore_ascii_case(name))
+        .map(|(_, format)| format)
+    }
+}
+
+// How triangle normals are shaded. `Smooth` uses the vertex normals of the file when it has
+// them, and otherwise averages face normals around each position, weighted by the corner angle,
+// across edges whose faces meet at less than `crease_angle` radians.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum Shading {
+    #[default]
+    Flat,
+    Smooth { crease_angle: f32 },
+}
+
+#[derive(Debug, Clone)]
+pub struct Mesh {
+    pub model: Model,
+    pub vertices: Range<Vec3Idx>,
+    pub normals: Option<Vec<Vec3>>,
+    pub uvs: Option<Vec<Vec2>>,
+    pub colors: Option<Vec<Vec3>>,
+}
+
+pub fn load_mesh(
+    geometry: &mut Geometry,
+    format: MeshFormat,
+    data: &[u8],
+    material: MaterialIdx,
+    shading: Shading,
+) -> Result<Mesh, LoadError> {
+    let mut mesh = match format {
+        MeshFormat::Obj => obj::load_obj(geometry, data, material),
+        MeshFormat::Ply => ply::load_ply(geometry, data, material),
+        MeshFormat::Stl => stl::load_stl(geometry, data, material),
+    }?;
+    mesh.attach_uvs(geometry);
+    mesh.shade(geometry, shading);
+    Ok(mesh)
+}
+
+impl Mesh {
+    fn attach_uvs(&mut self, geometry: &mut Geometry) {
+        let Some(uvs) = &self.uvs else {
+            return;
+        };
+        let offset = geometry.uv_buf.uvs.len() as UvIdx;
+        geometry.uv_buf.uvs.extend(uvs);
+        for triangle in self.model.triangles.iter_mut() {
+            triangle.uvs = Some(triangle.points.map(|idx| idx - self.vertices.start + offset));
+        }
+    }
+
+    pub fn shade(&mut self, geometry: &mut Geometry, shading: Shading) {
+        let Shading::Smooth { crease_angle } = shading else {
+            return;
+        };
+        match &self.normals {
+            Some(normals) => {
+                let offset = geometry.normal_buf.normals.len() as NormalIdx;
+                geometry.normal_buf.normals.extend(normals);
+                for triangle in self.model.triangles.iter_mut() {
+                    triangle.normals = Some(
+                        triangle
+                            .points
+                            .map(|idx| idx - self.vertices.start + offset),
+                    );
+                }
+            }
+            None => smooth_normals(geometry, &mut self.model.triangles, crease_angle),
+        }
+    }
+}
+
+fn corner_angle(geometry: &Geometry, triangle: &Triangle, corner: usize) -> f32 {
+    let load = |idx: usize| *geometry.vec_buf.load(triangle.points[idx % 3]);
+    let origin = load(corner);
+    let to_next = (load(corner + 1) - origin).normalized();
+    let to_prev = (load(corner + 2) - origin).normalized();
+    libm::acosf(dot_product(&to_next, &to_prev).clamp(-1.0, 1.0))
+}
+
+// Vertices split apart for their attributes, such as along uv seams, still share a position.
+fn position_key(geometry: &Geometry, idx: Vec3Idx) -> [u32; 3] {
+    let point = geometry.vec_buf.load(idx);
+    // adding zero turns -0 into +0
+    [point.x, point.y, point.z].map(|value| (value + 0.0).to_bits())
+}
+
+// Angle weighted vertex normals, computed per corner so that creases stay sharp. Faces are
+// gathered around positions rather than vertices so that seams stay smooth.
+fn smooth_normals(geometry: &mut Geometry, triangles: &mut [Triangle], crease_angle: f32) {
+    let mut incident: BTreeMap<[u32; 3], Vec<(Vec3, f32)>> = BTreeMap::new();
+    for triangle in triangles.iter() {
+        for (corner, idx) in triangle.points.iter().enumerate() {
+            let weight = corner_angle(geometry, triangle, corner);
+            incident
+                .entry(position_key(geometry, *idx))
+                .or_default()
+                .push((triangle.normal, weight));
+        }
+    }
+
+    let min_cos = libm::cosf(crease_angle);
+    let mut shared = BTreeMap::new();
+    for triangle in triangles.iter_mut() {
+        let normals = triangle.points.map(|idx| {
+            let position = position_key(geometry, idx);
+            let normal = incident[&position]
+                .iter()
+                .filter(|(normal, _)| dot_product(normal, &triangle.normal) >= min_cos)
+                .map(|(normal, weight)| *normal * *weight)
+                .sum::<Vec3>()
+                .normalized();
+            let key = (position, [normal.x, normal.y, normal.z].map(f32::to_bits));
+            *shared
+                .entry(key)
+                .or_insert_with(|| geometry.push_normal(normal))
+        });
+        triangle.normals = Some(normals);
+    }
+}
+
+// Pushes `points` to the shared buffer and builds a model from faces indexed relative to them.
+// Faces with repeated or collinear vertices are dropped, since they have no normal.
+pub(crate) fn build_mesh(
+    geometry: &mut Geometry,
+    points: Vec<Vec3>,
+    faces: &[[u32; 3]],
+    material: MaterialIdx,
+) -> Result<Mesh, LoadError> {
+    for (face, indexes) in faces.iter().enumerate() {
+        if let Some(index) = indexes.iter().find(|idx| **idx as usize >= points.len()) {
+            return Err(LoadError::IndexOutOfRange {
+                face,
+                index: *index as usize,
+            });
+        }
+    }
+
+    let offset = geometry.vec_buf.points.len() as Vec3Idx;
+    let triangles = faces
+        .iter()
+        .filter(|[a, b, c]| {
+            let (a, b, c) = (points[*a as usize], points[*b as usize], points[*c as usize]);
+            cross_product(&(b - a), &(c - a)).norm() > 0.0
+        })
+        .map(|face| face.map(|idx| idx + offset))
+        .collect::<Vec<[Vec3Idx; 3]>>();
+
+    if triangles.is_empty() {
+        return Err(LoadError::EmptyMesh);
+    }
+
+    let vertices = offset..offset + points.len() as Vec3Idx;
+    geometry.vec_buf.points.extend(points);
+
+    let triangles = triangles
+        .into_iter()
+        .map(|points| Triangle::new(geometry, points, material))
+        .collect();
+
+    Ok(Mesh {
+        model: Model::from_faces(geometry, triangles),
+        vertices,
+        normals: None,
+        uvs: None,
+        colors: None,
+    })
+}
+
+pub(crate) fn parse_f32(token: &str) -> Result<f32, LoadError> {
+    token
+        .parse::<f32>()
+        .map_err(|_| LoadError::InvalidNumber(token.into()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec;
+
+    #[test]
+    fn test_smooth_shading_crease() {
+        // two faces folded by 90 degrees along the y axis
+        let points = vec![
+            Vec3::new(0.0, 0.0, 0.0),
+            Vec3::new(0.0, 1.0, 0.0),
+            Vec3::new(1.0, 0.0, 0.0),
+            Vec3::new(0.0, 0.0, 1.0),
+        ];
+        let faces = [[0, 2, 1], [0, 1, 3]];
+        let shaded_normal = |crease_angle: f32| {
+            let mut geometry = Geometry::default();
+            let mut mesh = build_mesh(&mut geometry, points.clone(), &faces, 0).unwrap();
+            mesh.shade(&mut geometry, Shading::Smooth { crease_angle });
+            // the positions are left alone
+            assert_eq!(points, geometry.vec_buf.points);
+            let triangle = mesh.model.triangles[0];
+            *geometry.normal_buf.load(triangle.normals.unwrap()[0])
+        };
+
+        let face = Vec3::new(0.0, 0.0, 1.0);
+        assert!((shaded_normal(1.0) - face).norm() < 1e-5);
+        let average = Vec3::new(1.0, 0.0, 1.0).normalized();
+        assert!((shaded_normal(2.0) - average).norm() < 1e-5);
+    }
+}
@@ -0,0 +1,191 @@
+use alloc::collections::BTreeMap;
+use alloc::string::ToString;
+use alloc::vec::Vec;
+
+use crate::loader::{build_mesh, parse_f32, LoadError, Mesh};
+use crate::scene::Geometry;
+use crate::utils::MaterialIdx;
+use crate::vec2::Vec2;
+use crate::vec3::Vec3;
+
+// Resolves a 1-based (or negative, relative to the end) OBJ index into a 0-based one.
+fn parse_index(token: &str, len: usize) -> Result<u32, LoadError> {
+    let index = token
+        .parse::<i64>()
+        .map_err(|_| LoadError::InvalidNumber(token.to_string()))?;
+    let resolved = if index < 0 {
+        len as i64 + index
+    } else {
+        index - 1
+    };
+    u32::try_from(resolved).map_err(|_| LoadError::InvalidNumber(token.to_string()))
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Corner {
+    position: u32,
+    uv: Option<u32>,
+    normal: Option<u32>,
+}
+
+// A `v`, `v/vt`, `v//vn` or `v/vt/vn` face corner.
+fn parse_corner(token: &str, lens: [usize; 3]) -> Result<Corner, LoadError> {
+    let mut parts = token.split('/');
+    let mut next = |len| match parts.next() {
+        Some(part) if !part.is_empty() => parse_index(part, len).map(Some),
+        _ => Ok(None),
+    };
+    let position = next(lens[0])?.ok_or_else(|| LoadError::InvalidNumber(token.to_string()))?;
+    Ok(Corner {
+        position,
+        uv: next(lens[1])?,
+        normal: next(lens[2])?,
+    })
+}
+
+// Vertex attributes are only used when every corner has them. Each distinct combination of
+// position and used attributes becomes its own vertex, so that attributes are stored per vertex.
+fn split_vertices(
+    points: &[Vec3],
+    uvs: &[Vec2],
+    normals: &[Vec3],
+    corners: &[[Corner; 3]],
+) -> Result<SplitMesh, LoadError> {
+    let use_uvs = corners.iter().flatten().all(|corner| corner.uv.is_some());
+    let use_normals = corners
+        .iter()
+        .flatten()
+        .all(|corner| corner.normal.is_some());
+
+    let mut vertices = BTreeMap::new();
+    let mut mesh = SplitMesh::default();
+    for (face, face_corners) in corners.iter().enumerate() {
+        let mut indexes = [0; 3];
+        for (slot, corner) in indexes.iter_mut().zip(face_corners) {
+            let uv = corner.uv.filter(|_| use_uvs);
+            let normal = corner.normal.filter(|_| use_normals);
+            let out_of_range = [
+                (Some(corner.position), points.len()),
+                (uv, uvs.len()),
+                (normal, normals.len()),
+            ]
+            .into_iter()
+            .find_map(|(index, len)| index.filter(|index| *index as usize >= len));
+            if let Some(index) = out_of_range {
+                let index = index as usize;
+                return Err(LoadError::IndexOutOfRange { face, index });
+            }
+
+            *slot = *vertices
+                .entry((corner.position, uv, normal))
+                .or_insert_with(|| {
+                    mesh.points.push(points[corner.position as usize]);
+                    if let Some(uv) = uv {
+                        mesh.uvs.push(uvs[uv as usize]);
+                    }
+                    if let Some(normal) = normal {
+                        mesh.normals.push(normals[normal as usize]);
+                    }
+                    (mesh.points.len() - 1) as u32
+                });
+        }
+        mesh.faces.push(indexes);
+    }
+    Ok(mesh)
+}
+
+#[derive(Default)]
+struct SplitMesh {
+    points: Vec<Vec3>,
+    uvs: Vec<Vec2>,
+    normals: Vec<Vec3>,
+    faces: Vec<[u32; 3]>,
+}
+
+pub fn load_obj(
+    geometry: &mut Geometry,
+    data: &[u8],
+    material: MaterialIdx,
+) -> Result<Mesh, LoadError> {
+    let text = core::str::from_utf8(data).map_err(|_| LoadError::InvalidUtf8)?;
+
+    let mut points = Vec::new();
+    let mut uvs = Vec::new();
+    let mut normals = Vec::new();
+    let mut corners = Vec::new();
+
+    for line in text.lines() {
+        let mut tokens = line.split_ascii_whitespace();
+        let keyword = tokens.next();
+        let mut next = || parse_f32(tokens.next().ok_or(LoadError::UnexpectedEof)?);
+        match keyword {
+            Some("v") => points.push(Vec3::new(next()?, next()?, next()?)),
+            Some("vt") => uvs.push(Vec2::new(next()?, next()?)),
+            Some("vn") => normals.push(Vec3::new(next()?, next()?, next()?).normalized()),
+            Some("f") => {
+                let lens = [points.len(), uvs.len(), normals.len()];
+                let face = line
+                    .split_ascii_whitespace()
+                    .skip(1)
+                    .map(|token| parse_corner(token, lens))
+                    .collect::<Result<Vec<Corner>, LoadError>>()?;
+                for i in 2..face.len() {
+                    corners.push([face[0], face[i - 1], face[i]]);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    let split = split_vertices(&points, &uvs, &normals, &corners)?;
+    let vertex_count = split.points.len();
+    let mut mesh = build_mesh(geometry, split.points, &split.faces, material)?;
+    if !split.normals.is_empty() && split.normals.len() == vertex_count {
+        mesh.normals = Some(split.normals);
+    }
+    if !split.uvs.is_empty() && split.uvs.len() == vertex_count {
+        mesh.uvs = Some(split.uvs);
+    }
+    Ok(mesh)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::loader::Shading;
+
+    #[test]
+    fn test_load_obj_normals() {
+        let data = b"v 0 0 0\nv 1 0 0\nv 1 1 0\nv 0 1 0\n\
+            vn 0 0 1\nvn 0 0 2\n\
+            f 1//1 2//1 3//2\nf -4//1 -2//1 -1//-2\n";
+        let mut geometry = Geometry::default();
+        let mesh = load_obj(&mut geometry, data, 0).unwrap();
+
+        // vertex 3 is used with both normals
+        assert_eq!(5, mesh.vertices.len());
+        assert_eq!(2, mesh.model.triangles.len());
+        let normals = mesh.normals.unwrap();
+        assert!(normals
+            .iter()
+            .all(|normal| *normal == Vec3::new(0.0, 0.0, 1.0)));
+    }
+
+    #[test]
+    fn test_smooth_across_uv_seam() {
+        // two faces folded along the y axis, the shared edge split by a uv seam
+        let data = b"v 0 0 0\nv 0 1 0\nv 1 0 0\nv 0 0 1\n\
+            vt 0 0\nvt 0 1\nvt 1 0\nvt 0.5 0\nvt 0.5 1\nvt 0 0.5\n\
+            f 1/1 3/3 2/2\nf 1/4 2/5 4/6\n";
+        let mut geometry = Geometry::default();
+        let mut mesh = load_obj(&mut geometry, data, 0).unwrap();
+        assert_eq!(6, mesh.vertices.len());
+        mesh.shade(&mut geometry, Shading::Smooth { crease_angle: 2.0 });
+
+        let [first, second] = [0, 1].map(|face| mesh.model.triangles[face].normals.unwrap());
+        let average = Vec3::new(1.0, 0.0, 1.0).normalized();
+        for normal in [first[0], second[0], first[2], second[1]] {
+            assert!((*geometry.normal_buf.load(normal) - average).norm() < 1e-5);
+        }
+    }
+}
@@ -0,0 +1,465 @@
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::str::SplitAsciiWhitespace;
+
+use crate::loader::{build_mesh, LoadError, Mesh};
+use crate::scene::Geometry;
+use crate::utils::MaterialIdx;
+use crate::vec2::Vec2;
+use crate::vec3::Vec3;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Encoding {
+    Ascii,
+    BinaryLittleEndian,
+    BinaryBigEndian,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum ScalarType {
+    I8,
+    U8,
+    I16,
+    U16,
+    I32,
+    U32,
+    F32,
+    F64,
+}
+
+impl ScalarType {
+    fn parse(name: &str) -> Result<ScalarType, LoadError> {
+        Ok(match name {
+            "char" | "int8" => ScalarType::I8,
+            "uchar" | "uint8" => ScalarType::U8,
+            "short" | "int16" => ScalarType::I16,
+            "ushort" | "uint16" => ScalarType::U16,
+            "int" | "int32" => ScalarType::I32,
+            "uint" | "uint32" => ScalarType::U32,
+            "float" | "float32" => ScalarType::F32,
+            "double" | "float64" => ScalarType::F64,
+            _ => return Err(LoadError::UnsupportedFormat(format!("ply type {name}"))),
+        })
+    }
+
+    // Scale applied to color channels so that integer colors end up in `0.0..=1.0`.
+    fn color_scale(&self) -> f32 {
+        match self {
+            ScalarType::U8 => 255.0,
+            ScalarType::U16 => 65535.0,
+            _ => 1.0,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+enum PropertyKind {
+    Scalar(ScalarType),
+    List { count: ScalarType, item: ScalarType },
+}
+
+#[derive(Debug, Clone)]
+struct Property {
+    name: String,
+    kind: PropertyKind,
+}
+
+#[derive(Debug, Clone)]
+struct Element {
+    name: String,
+    count: usize,
+    properties: Vec<Property>,
+}
+
+impl Element {
+    fn find(&self, names: &[&str]) -> Option<usize> {
+        self.properties
+            .iter()
+            .position(|property| names.contains(&property.name.as_str()))
+    }
+}
+
+trait ValueReader {
+    fn read(&mut self, ty: ScalarType) -> Result<f64, LoadError>;
+}
+
+struct AsciiReader<'a> {
+    tokens: SplitAsciiWhitespace<'a>,
+}
+
+impl ValueReader for AsciiReader<'_> {
+    fn read(&mut self, ty: ScalarType) -> Result<f64, LoadError> {
+        let token = self.tokens.next().ok_or(LoadError::UnexpectedEof)?;
+        let value = token
+            .parse::<f64>()
+            .map_err(|_| LoadError::InvalidNumber(token.into()))?;
+        match ty {
+            ScalarType::F32 | ScalarType::F64 => Ok(value),
+            _ if libm::trunc(value) == value => Ok(value),
+            _ => Err(LoadError::InvalidNumber(token.into())),
+        }
+    }
+}
+
+struct BinaryReader<'a> {
+    data: &'a [u8],
+    big_endian: bool,
+}
+
+impl BinaryReader<'_> {
+    fn take<const N: usize>(&mut self) -> Result<[u8; N], LoadError> {
+        if self.data.len() < N {
+            return Err(LoadError::UnexpectedEof);
+        }
+        let (head, tail) = self.data.split_at(N);
+        self.data = tail;
+        let mut bytes: [u8; N] = head.try_into().unwrap();
+        if self.big_endian {
+            bytes.reverse();
+        }
+        Ok(bytes)
+    }
+}
+
+impl ValueReader for BinaryReader<'_> {
+    fn read(&mut self, ty: ScalarType) -> Result<f64, LoadError> {
+        Ok(match ty {
+            ScalarType::I8 => i8::from_le_bytes(self.take()?) as f64,
+            ScalarType::U8 => u8::from_le_bytes(self.take()?) as f64,
+            ScalarType::I16 => i16::from_le_bytes(self.take()?) as f64,
+            ScalarType::U16 => u16::from_le_bytes(self.take()?) as f64,
+            ScalarType::I32 => i32::from_le_bytes(self.take()?) as f64,
+            ScalarType::U32 => u32::from_le_bytes(self.take()?) as f64,
+            ScalarType::F32 => f32::from_le_bytes(self.take()?) as f64,
+            ScalarType::F64 => f64::from_le_bytes(self.take()?),
+        })
+    }
+}
+
+#[derive(Default)]
+struct Body {
+    points: Vec<Vec3>,
+    normals: Vec<Vec3>,
+    uvs: Vec<Vec2>,
+    colors: Vec<Vec3>,
+    faces: Vec<[u32; 3]>,
+}
+
+fn split_header(data: &[u8]) -> Result<(&str, &[u8]), LoadError> {
+    if !data.starts_with(b"ply\n") && !data.starts_with(b"ply\r\n") {
+        return Err(LoadError::InvalidHeader("missing `ply` magic".into()));
+    }
+    // the header ends at the first line holding only `end_header`, not one mentioning it
+    let mut start = 0;
+    loop {
+        let end = data[start..]
+            .iter()
+            .position(|byte| *byte == b'\n')
+            .map(|pos| start + pos + 1)
+            .ok_or(LoadError::UnexpectedEof)?;
+        let line = &data[start..end - 1];
+        if line.strip_suffix(b"\r").unwrap_or(line) == b"end_header" {
+            let header =
+                core::str::from_utf8(&data[..start]).map_err(|_| LoadError::InvalidUtf8)?;
+            return Ok((header, &data[end..]));
+        }
+        start = end;
+    }
+}
+
+fn parse_header(header: &str) -> Result<(Encoding, Vec<Element>), LoadError> {
+    let mut encoding = None;
+    let mut elements: Vec<Element> = Vec::new();
+
+    for line in header.lines().skip(1) {
+        let invalid = || LoadError::InvalidHeader(line.to_string());
+        let mut tokens = line.split_ascii_whitespace();
+        match tokens.next() {
+            None | Some("comment") | Some("obj_info") => continue,
+            Some("format") => {
+                encoding = Some(match (tokens.next(), tokens.next()) {
+                    (Some("ascii"), Some("1.0")) => Encoding::Ascii,
+                    (Some("binary_little_endian"), Some("1.0")) => Encoding::BinaryLittleEndian,
+                    (Some("binary_big_endian"), Some("1.0")) => Encoding::BinaryBigEndian,
+                    _ => return Err(LoadError::UnsupportedFormat(line.to_string())),
+                });
+            }
+            Some("element") => {
+                let name = tokens.next().ok_or_else(invalid)?;
+                let count = tokens
+                    .next()
+                    .and_then(|count| count.parse::<usize>().ok())
+                    .ok_or_else(invalid)?;
+                elements.push(Element {
+                    name: name.into(),
+                    count,
+                    properties: Vec::new(),
+                });
+            }
+            Some("property") => {
+                let element = elements.last_mut().ok_or_else(invalid)?;
+                let kind = match tokens.next().ok_or_else(invalid)? {
+                    "list" => PropertyKind::List {
+                        count: ScalarType::parse(tokens.next().ok_or_else(invalid)?)?,
+                        item: ScalarType::parse(tokens.next().ok_or_else(invalid)?)?,
+                    },
+                    ty => PropertyKind::Scalar(ScalarType::parse(ty)?),
+                };
+                let name = tokens.next().ok_or_else(invalid)?;
+                element.properties.push(Property {
+                    name: name.into(),
+                    kind,
+                });
+            }
+            Some(_) => return Err(invalid()),
+        }
+    }
+
+    let encoding =
+        encoding.ok_or_else(|| LoadError::InvalidHeader("missing `format` line".into()))?;
+    Ok((encoding, elements))
+}
+
+// Longest list a property may hold, against corrupt counts.
+const MAX_LIST_LEN: usize = 1 << 16;
+
+// Face indices stored as floats or signed integers must still be whole and not negative.
+fn vertex_index(value: f64) -> Result<u32, LoadError> {
+    if libm::trunc(value) != value || !(0.0..=u32::MAX as f64).contains(&value) {
+        return Err(LoadError::InvalidNumber(value.to_string()));
+    }
+    Ok(value as u32)
+}
+
+fn list_len(value: f64) -> Result<usize, LoadError> {
+    if libm::trunc(value) != value || !(0.0..=MAX_LIST_LEN as f64).contains(&value) {
+        return Err(LoadError::InvalidNumber(value.to_string()));
+    }
+    Ok(value as usize)
+}
+
+fn read_body<R: ValueReader>(reader: &mut R, elements: &[Element]) -> Result<Body, LoadError> {
+    let mut body = Body::default();
+    let mut row = Vec::new();
+    let mut list = Vec::new();
+
+    for element in elements {
+        let position = [
+            element.find(&["x"]),
+            element.find(&["y"]),
+            element.find(&["z"]),
+        ];
+        let normal = [
+            element.find(&["nx"]),
+            element.find(&["ny"]),
+            element.find(&["nz"]),
+        ];
+        let uv = [
+            element.find(&["u", "s", "texture_u", "texture_s"]),
+            element.find(&["v", "t", "texture_v", "texture_t"]),
+        ];
+        let color = [
+            element.find(&["red", "r", "diffuse_red"]),
+            element.find(&["green", "g", "diffuse_green"]),
+            element.find(&["blue", "b", "diffuse_blue"]),
+        ];
+        let indices = element.find(&["vertex_indices", "vertex_index"]);
+        let is_vertex = element.name == "vertex";
+        let is_face = element.name == "face";
+
+        if is_vertex && position.iter().any(Option::is_none) {
+            return Err(LoadError::InvalidHeader("vertex element without x/y/z".into()));
+        }
+
+        for _ in 0..element.count {
+            row.clear();
+            for (idx, property) in element.properties.iter().enumerate() {
+                match property.kind {
+                    PropertyKind::Scalar(ty) => row.push(reader.read(ty)?),
+                    PropertyKind::List { count, item } => {
+                        let len = list_len(reader.read(count)?)?;
+                        let is_indices = Some(idx) == indices;
+                        if is_indices {
+                            list.clear();
+                        }
+                        for _ in 0..len {
+                            let value = reader.read(item)?;
+                            if is_indices {
+                                list.push(vertex_index(value)?);
+                            }
+                        }
+                        row.push(0.0);
+                    }
+                }
+            }
+
+            let load = |props: &[Option<usize>; 3]| {
+                props.map(|idx| idx.map(|idx| row[idx] as f32))
+            };
+
+            if is_vertex {
+                let [x, y, z] = load(&position);
+                body.points.push(Vec3::new(x.unwrap(), y.unwrap(), z.unwrap()));
+                if let [Some(x), Some(y), Some(z)] = load(&normal) {
+                    body.normals.push(Vec3::new(x, y, z).normalized());
+                }
+                if let [Some(u), Some(v)] = uv.map(|idx| idx.map(|idx| row[idx] as f32)) {
+                    body.uvs.push(Vec2::new(u, v));
+                }
+                if let [Some(r), Some(g), Some(b)] = load(&color) {
+                    let scales = color.map(|idx| match element.properties[idx.unwrap()].kind {
+                        PropertyKind::Scalar(ty) => ty.color_scale(),
+                        PropertyKind::List { .. } => 1.0,
+                    });
+                    body.colors
+                        .push(Vec3::new(r / scales[0], g / scales[1], b / scales[2]));
+                }
+            } else if is_face && indices.is_some() {
+                for i in 2..list.len() {
+                    body.faces.push([list[0], list[i - 1], list[i]]);
+                }
+            }
+        }
+    }
+
+    Ok(body)
+}
+
+pub fn load_ply(
+    geometry: &mut Geometry,
+    data: &[u8],
+    material: MaterialIdx,
+) -> Result<Mesh, LoadError> {
+    let (header, data) = split_header(data)?;
+    let (encoding, elements) = parse_header(header)?;
+
+    let body = match encoding {
+        Encoding::Ascii => {
+            let text = core::str::from_utf8(data).map_err(|_| LoadError::InvalidUtf8)?;
+            let mut reader = AsciiReader {
+                tokens: text.split_ascii_whitespace(),
+            };
+            read_body(&mut reader, &elements)?
+        }
+        Encoding::BinaryLittleEndian | Encoding::BinaryBigEndian => {
+            let mut reader = BinaryReader {
+                data,
+                big_endian: encoding == Encoding::BinaryBigEndian,
+            };
+            read_body(&mut reader, &elements)?
+        }
+    };
+
+    let vertex_count = body.points.len();
+    let mut mesh = build_mesh(geometry, body.points, &body.faces, material)?;
+    if body.normals.len() == vertex_count {
+        mesh.normals = Some(body.normals);
+    }
+    if body.uvs.len() == vertex_count {
+        mesh.uvs = Some(body.uvs);
+    }
+    if body.colors.len() == vertex_count {
+        mesh.colors = Some(body.colors);
+    }
+    Ok(mesh)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ASCII_QUAD: &str = "ply
+format ascii 1.0
+comment unit quad
+element vertex 4
+property float x
+property float y
+property float z
+property float nx
+property float ny
+property float nz
+property uchar red
+property uchar green
+property uchar blue
+element face 1
+property list uchar int vertex_indices
+end_header
+0 0 0 0 0 1 255 0 0
+1 0 0 0 0 1 0 255 0
+1 1 0 0 0 1 0 0 255
+0 1 0 0 0 1 255 255 255
+4 0 1 2 3
+";
+
+    #[test]
+    fn test_ply_ascii() {
+        let mut geometry = Geometry::default();
+        let mesh = load_ply(&mut geometry, ASCII_QUAD.as_bytes(), 0).unwrap();
+
+        assert_eq!(2, mesh.model.triangles.len());
+        assert_eq!(0..4, mesh.vertices);
+        assert_eq!(Vec3::new(0.0, 0.0, 1.0), mesh.model.triangles[0].normal);
+        assert_eq!(Vec3::new(0.0, 0.0, 1.0), mesh.normals.unwrap()[2]);
+        assert_eq!(Vec3::new(0.0, 1.0, 0.0), mesh.colors.unwrap()[1]);
+    }
+
+    #[test]
+    fn test_ply_binary_big_endian() {
+        let mut data = b"ply\nformat binary_big_endian 1.0\nelement vertex 3\n\
+            property float x\nproperty float y\nproperty float z\n\
+            element face 1\nproperty list uchar uint vertex_indices\nend_header\n"
+            .to_vec();
+        for point in [[0.0_f32, 0.0, 0.0], [1.0, 0.0, 0.0], [0.0, 1.0, 0.0]] {
+            point.iter().for_each(|val| data.extend(val.to_be_bytes()));
+        }
+        data.push(3);
+        [0_u32, 1, 2].iter().for_each(|val| data.extend(val.to_be_bytes()));
+
+        let mut geometry = Geometry::default();
+        let mesh = load_ply(&mut geometry, &data, 0).unwrap();
+
+        assert_eq!(1, mesh.model.triangles.len());
+        assert_eq!(Vec3::new(1.0, 0.0, 0.0), *geometry.vec_buf.load(1));
+        assert!(mesh.normals.is_none());
+    }
+
+    #[test]
+    fn test_ply_errors() {
+        let mut geometry = Geometry::default();
+        let truncated = ASCII_QUAD.replace("4 0 1 2 3\n", "4 0 1 2");
+        assert_eq!(
+            LoadError::UnexpectedEof,
+            load_ply(&mut geometry, truncated.as_bytes(), 0).unwrap_err()
+        );
+
+        let out_of_range = ASCII_QUAD.replace("4 0 1 2 3\n", "3 0 1 7\n");
+        assert_eq!(
+            LoadError::IndexOutOfRange { face: 0, index: 7 },
+            load_ply(&mut geometry, out_of_range.as_bytes(), 0).unwrap_err()
+        );
+        assert!(geometry.vec_buf.points.is_empty());
+
+        for (indices, token) in [("3 0 -1 2\n", "-1"), ("3 0 1.5 2\n", "1.5")] {
+            let invalid = ASCII_QUAD.replace("4 0 1 2 3\n", indices);
+            assert_eq!(
+                LoadError::InvalidNumber(token.into()),
+                load_ply(&mut geometry, invalid.as_bytes(), 0).unwrap_err()
+            );
+        }
+
+        let too_long = ASCII_QUAD.replace("4 0 1 2 3\n", "100000 0 1 2\n");
+        assert_eq!(
+            LoadError::InvalidNumber("100000".into()),
+            load_ply(&mut geometry, too_long.as_bytes(), 0).unwrap_err()
+        );
+    }
+
+    #[test]
+    fn test_ply_end_header_in_comment() {
+        let mut geometry = Geometry::default();
+        let commented = ASCII_QUAD.replace("comment unit quad", "comment ends at end_header");
+        let mesh = load_ply(&mut geometry, commented.as_bytes(), 0).unwrap();
+        assert_eq!(2, mesh.model.triangles.len());
+    }
+}
@@ -0,0 +1,183 @@
+use alloc::collections::BTreeMap;
+use alloc::vec::Vec;
+
+use crate::loader::{build_mesh, parse_f32, LoadError, Mesh};
+use crate::scene::Geometry;
+use crate::utils::MaterialIdx;
+use crate::vec3::Vec3;
+
+const BINARY_HEADER_LEN: usize = 80;
+const BINARY_FACET_LEN: usize = 50;
+
+// Merges bitwise-equal vertices, STL stores every facet with its own copy of the corners.
+#[derive(Default)]
+struct VertexDedup {
+    indexes: BTreeMap<[u32; 3], u32>,
+    points: Vec<Vec3>,
+}
+
+impl VertexDedup {
+    fn insert(&mut self, point: Vec3) -> u32 {
+        // `+ 0.0` turns -0.0 into 0.0 so both map to one vertex
+        let key = [point.x, point.y, point.z].map(|val| (val + 0.0).to_bits());
+        *self.indexes.entry(key).or_insert_with(|| {
+            self.points.push(point);
+            (self.points.len() - 1) as u32
+        })
+    }
+}
+
+fn is_binary(data: &[u8]) -> bool {
+    if data.len() >= BINARY_HEADER_LEN + 4 {
+        let count = u32::from_le_bytes(data[80..84].try_into().unwrap()) as usize;
+        // some exporters write binary files with a header starting with "solid"
+        if data.len() == BINARY_HEADER_LEN + 4 + count * BINARY_FACET_LEN {
+            return true;
+        }
+    }
+    !data.trim_ascii_start().starts_with(b"solid")
+}
+
+fn read_binary(data: &[u8]) -> Result<(Vec<Vec3>, Vec<[u32; 3]>), LoadError> {
+    if data.len() < BINARY_HEADER_LEN + 4 {
+        return Err(LoadError::UnexpectedEof);
+    }
+    let count = u32::from_le_bytes(data[80..84].try_into().unwrap()) as usize;
+    let facets = &data[BINARY_HEADER_LEN + 4..];
+    if facets.len() < count * BINARY_FACET_LEN {
+        return Err(LoadError::UnexpectedEof);
+    }
+
+    let read_vec = |bytes: &[u8]| {
+        let [x, y, z] = [0, 4, 8].map(|offset| {
+            f32::from_le_bytes(bytes[offset..offset + 4].try_into().unwrap())
+        });
+        Vec3::new(x, y, z)
+    };
+
+    let mut dedup = VertexDedup::default();
+    let faces = facets
+        .chunks_exact(BINARY_FACET_LEN)
+        .take(count)
+        .map(|facet| [12, 24, 36].map(|offset| dedup.insert(read_vec(&facet[offset..]))))
+        .collect();
+
+    Ok((dedup.points, faces))
+}
+
+fn read_ascii(data: &[u8]) -> Result<(Vec<Vec3>, Vec<[u32; 3]>), LoadError> {
+    let text = core::str::from_utf8(data).map_err(|_| LoadError::InvalidUtf8)?;
+    let mut tokens = text.split_ascii_whitespace();
+
+    let mut dedup = VertexDedup::default();
+    let mut faces = Vec::new();
+    let mut facet = Vec::new();
+
+    while let Some(token) = tokens.next() {
+        match token {
+            "vertex" => {
+                let mut next = || parse_f32(tokens.next().ok_or(LoadError::UnexpectedEof)?);
+                let point = Vec3::new(next()?, next()?, next()?);
+                facet.push(dedup.insert(point));
+            }
+            "endloop" => {
+                for i in 2..facet.len() {
+                    faces.push([facet[0], facet[i - 1], facet[i]]);
+                }
+                facet.clear();
+            }
+            "endsolid" => return Ok((dedup.points, faces)),
+            _ => {}
+        }
+    }
+
+    Err(LoadError::UnexpectedEof)
+}
+
+pub fn load_stl(
+    geometry: &mut Geometry,
+    data: &[u8],
+    material: MaterialIdx,
+) -> Result<Mesh, LoadError> {
+    let (points, faces) = if is_binary(data) {
+        read_binary(data)?
+    } else {
+        read_ascii(data)?
+    };
+    build_mesh(geometry, points, &faces, material)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ASCII_TETRAHEDRON: &str = "solid tetrahedron
+facet normal 0 0 -1
+ outer loop
+  vertex 0 0 0
+  vertex 0 1 0
+  vertex 1 0 0
+ endloop
+endfacet
+facet normal 0 -1 0
+ outer loop
+  vertex 0 0 0
+  vertex 1 0 0
+  vertex 0 0 1
+ endloop
+endfacet
+facet normal -1 0 0
+ outer loop
+  vertex 0 0 0
+  vertex 0 0 1
+  vertex 0 1 0
+ endloop
+endfacet
+facet normal 1 1 1
+ outer loop
+  vertex 1 0 0
+  vertex 0 1 0
+  vertex 0 0 1
+ endloop
+endfacet
+endsolid tetrahedron
+";
+
+    #[test]
+    fn test_stl_ascii_dedup() {
+        let mut geometry = Geometry::default();
+        let mesh = load_stl(&mut geometry, ASCII_TETRAHEDRON.as_bytes(), 0).unwrap();
+
+        assert_eq!(4, mesh.model.triangles.len());
+        assert_eq!(4, geometry.vec_buf.points.len());
+        assert_eq!(Vec3::new(0.0, 0.0, -1.0), mesh.model.triangles[0].normal);
+    }
+
+    #[test]
+    fn test_stl_binary() {
+        let mut data = b"solid but actually binary".to_vec();
+        data.resize(BINARY_HEADER_LEN, 0);
+        data.extend(2_u32.to_le_bytes());
+        let quad = [[0.0_f32, 0.0, 0.0], [1.0, 0.0, 0.0], [1.0, 1.0, 0.0], [0.0, 1.0, 0.0]];
+        for face in [[0, 1, 2], [0, 2, 3]] {
+            data.extend([0_u8; 12]);
+            face.iter()
+                .flat_map(|idx| quad[*idx])
+                .for_each(|val: f32| data.extend(val.to_le_bytes()));
+            data.extend([0_u8; 2]);
+        }
+
+        let mut geometry = Geometry::default();
+        let mesh = load_stl(&mut geometry, &data, 0).unwrap();
+
+        assert_eq!(2, mesh.model.triangles.len());
+        assert_eq!(4, geometry.vec_buf.points.len());
+
+        data[..5].copy_from_slice(b"quad ");
+        data.pop();
+        assert_eq!(
+            LoadError::UnexpectedEof,
+            load_stl(&mut geometry, &data, 0).unwrap_err()
+        );
+    }
+}
@@ -0,0 +1,174 @@
+use core::ops::Mul;
+
+use crate::vec3::Vec3;
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Mat4 {
+    pub rows: [[f32; 4]; 4],
+}
+
+impl Default for Mat4 {
+    fn default() -> Self {
+        Mat4::identity()
+    }
+}
+
+impl Mat4 {
+    pub fn new(rows: [[f32; 4]; 4]) -> Mat4 {
+        Mat4 { rows }
+    }
+
+    pub fn identity() -> Mat4 {
+        Mat4::new([
+            [1.0, 0.0, 0.0, 0.0],
+            [0.0, 1.0, 0.0, 0.0],
+            [0.0, 0.0, 1.0, 0.0],
+            [0.0, 0.0, 0.0, 1.0],
+        ])
+    }
+
+    pub fn translation(offset: Vec3) -> Mat4 {
+        Mat4::new([
+            [1.0, 0.0, 0.0, offset.x],
+            [0.0, 1.0, 0.0, offset.y],
+            [0.0, 0.0, 1.0, offset.z],
+            [0.0, 0.0, 0.0, 1.0],
+        ])
+    }
+
+    pub fn scaling(scale: Vec3) -> Mat4 {
+        Mat4::new([
+            [scale.x, 0.0, 0.0, 0.0],
+            [0.0, scale.y, 0.0, 0.0],
+            [0.0, 0.0, scale.z, 0.0],
+            [0.0, 0.0, 0.0, 1.0],
+        ])
+    }
+
+    // Rotation by `angle` radians around `axis`, counter-clockwise when looking against the axis.
+    pub fn rotation(axis: Vec3, angle: f32) -> Mat4 {
+        let Vec3 { x, y, z } = axis.normalized();
+        let (sin, cos) = (libm::sinf(angle), libm::cosf(angle));
+        let t = 1.0 - cos;
+        Mat4::new([
+            [t * x * x + cos, t * x * y - sin * z, t * x * z + sin * y, 0.0],
+            [t * x * y + sin * z, t * y * y + cos, t * y * z - sin * x, 0.0],
+            [t * x * z - sin * y, t * y * z + sin * x, t * z * z + cos, 0.0],
+            [0.0, 0.0, 0.0, 1.0],
+        ])
+    }
+
+    pub fn transpose(&self) -> Mat4 {
+        let mut rows = [[0.0; 4]; 4];
+        for (i, row) in rows.iter_mut().enumerate() {
+            for (j, val) in row.iter_mut().enumerate() {
+                *val = self.rows[j][i];
+            }
+        }
+        Mat4::new(rows)
+    }
+
+    // Gauss-Jordan elimination with partial pivoting, `None` for singular matrices.
+    pub fn inverse(&self) -> Option<Mat4> {
+        let mut lhs = self.rows;
+        let mut rhs = Mat4::identity().rows;
+
+        for col in 0..4 {
+            let pivot = (col..4)
+                .max_by(|a, b| libm::fabsf(lhs[*a][col]).total_cmp(&libm::fabsf(lhs[*b][col])))
+                .unwrap();
+            // singular up to rounding, relative to the size of the column
+            let magnitude = self.rows.iter().map(|row| libm::fabsf(row[col])).fold(0.0, f32::max);
+            let pivot_size = libm::fabsf(lhs[pivot][col]);
+            if pivot_size <= f32::EPSILON * magnitude || pivot_size.is_nan() {
+                return None;
+            }
+            lhs.swap(col, pivot);
+            rhs.swap(col, pivot);
+
+            let scale = lhs[col][col].recip();
+            for j in 0..4 {
+                lhs[col][j] *= scale;
+                rhs[col][j] *= scale;
+            }
+
+            for row in 0..4 {
+                if row == col {
+                    continue;
+                }
+                let factor = lhs[row][col];
+                for j in 0..4 {
+                    lhs[row][j] -= factor * lhs[col][j];
+                    rhs[row][j] -= factor * rhs[col][j];
+                }
+            }
+        }
+
+        Some(Mat4::new(rhs))
+    }
+
+    pub fn transform_point(&self, point: &Vec3) -> Vec3 {
+        let [x, y, z, w] = self.apply([point.x, point.y, point.z, 1.0]);
+        Vec3::new(x, y, z) * w.recip()
+    }
+
+    pub fn transform_vector(&self, vec: &Vec3) -> Vec3 {
+        let [x, y, z, _] = self.apply([vec.x, vec.y, vec.z, 0.0]);
+        Vec3::new(x, y, z)
+    }
+
+    fn apply(&self, vec: [f32; 4]) -> [f32; 4] {
+        self.rows
+            .map(|row| row.iter().zip(vec.iter()).map(|(a, b)| a * b).sum())
+    }
+}
+
+impl Mul<Mat4> for Mat4 {
+    type Output = Mat4;
+
+    fn mul(self, rhs: Mat4) -> Mat4 {
+        let mut rows = [[0.0; 4]; 4];
+        for (i, row) in rows.iter_mut().enumerate() {
+            for (j, val) in row.iter_mut().enumerate() {
+                *val = (0..4).map(|k| self.rows[i][k] * rhs.rows[k][j]).sum();
+            }
+        }
+        Mat4::new(rows)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vec3::assert_near;
+
+    #[test]
+    fn test_mat4_rotation() {
+        let rotation = Mat4::rotation(Vec3::new(0.0, 0.0, 1.0), core::f32::consts::FRAC_PI_2);
+        assert_near(
+            Vec3::new(0.0, 1.0, 0.0),
+            rotation.transform_vector(&Vec3::new(1.0, 0.0, 0.0)),
+        );
+    }
+
+    #[test]
+    fn test_mat4_inverse() {
+        let matrix = Mat4::translation(Vec3::new(1.0, -2.0, 3.0))
+            * Mat4::rotation(Vec3::new(1.0, 1.0, 0.0), 0.7)
+            * Mat4::scaling(Vec3::new(2.0, 0.5, 3.0));
+        let inverse = matrix.inverse().unwrap();
+        let point = Vec3::new(0.3, 4.0, -1.5);
+
+        assert_near(point, inverse.transform_point(&matrix.transform_point(&point)));
+        assert_near(point, matrix.transform_point(&inverse.transform_point(&point)));
+        assert!(Mat4::scaling(Vec3::new(1.0, 0.0, 1.0)).inverse().is_none());
+
+        // tiny scales are still invertible, flattened ones are not
+        let tiny = Mat4::scaling(Vec3::new(1e-8, 1e-8, 1e-8)) * matrix;
+        assert_near(point, tiny.inverse().unwrap().transform_point(&tiny.transform_point(&point)));
+        let flat =
+            Mat4::scaling(Vec3::new(1.0, 1.0, 1e-9)) * Mat4::rotation(Vec3::new(1.0, 0.0, 0.0), 0.7);
+        assert!(flat.inverse().is_none());
+    }
+}
@@ -1,11 +1,144 @@
-use crate::vec3::Vec3;
+use crate::bsdf::{fresnel_dielectric, Dielectric, Pbr, Subsurface};
+use crate::intersect::Hit;
+use crate::sampling::Rng;
+use crate::scene::Geometry;
+use crate::spectrum::{spectral, Dispersion, D_LINE};
+use crate::utils::{MaterialIdx, TextureBuf, TextureIdx};
+use crate::vec2::Vec2;
+use crate::vec3::{cross_product, dot_product, Vec3};
+
+// Deepest nesting of mixes and coats followed, against materials nesting in themselves.
+pub(crate) const MAX_NESTING: usize = 8;
+
+// Textures modulating material parameters, indexing `Geometry::texture_buf`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct MaterialTextures {
+    // multiplies `diffuse_color`, or the base color
+    pub diffuse: Option<TextureIdx>,
+    // multiplies the specular albedo, or the specular level, by the mean of the channels
+    pub specular: Option<TextureIdx>,
+    // red channel is the roughness, replacing the specular exponent with the matching
+    // Blinn-Phong one for `MaterialKind::Phong`
+    pub roughness: Option<TextureIdx>,
+    // red channel replaces the metalness
+    pub metallic: Option<TextureIdx>,
+    // multiplies the reflection albedo by the mean of the channels
+    pub reflection: Option<TextureIdx>,
+    // tangent space shading normals remapped to [0, 1], these should be stored linear
+    pub normal: Option<TextureIdx>,
+    // red channel is a height along the normal, in world units times `bump_scale`
+    pub bump: Option<TextureIdx>,
+    pub bump_scale: f32,
+}
+
+impl Default for MaterialTextures {
+    fn default() -> Self {
+        MaterialTextures {
+            diffuse: None,
+            specular: None,
+            roughness: None,
+            metallic: None,
+            reflection: None,
+            normal: None,
+            bump: None,
+            bump_scale: 1.0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum MaterialKind {
+    // ad hoc shading weighted by `albedo`: diffuse, specular highlight, mirror reflection and
+    // refraction
+    #[default]
+    Phong,
+    // physically based, only `textures` of the other fields apply
+    Pbr(Pbr),
+    // smooth dielectric, reflecting and refracting by the Fresnel equations
+    Dielectric(Dielectric),
+    // light scattering beneath the surface, only path traced, diffuse in `diffuse_color`
+    // otherwise
+    Subsurface(Subsurface),
+    // resolved into one of their parts when hit, where the textures of a coat apply to its
+    // reflection and the other fields are unused
+    Mix(Mix),
+    Layered(Layered),
+}
+
+// Blend of two materials of `Geometry::material_buf`, the second one weighted by `weight`
+// times the red channel of `mask` where given.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Mix {
+    pub materials: [MaterialIdx; 2],
+    pub weight: f32,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub mask: Option<TextureIdx>,
+}
+
+// Smooth or rough dielectric coat, such as clear-coat or varnish, absorbing `absorption` per
+// unit length through its `thickness`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Coat {
+    pub ior: f32,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub roughness: f32,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub thickness: f32,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub absorption: Vec3,
+}
+
+impl Coat {
+    pub fn new(ior: f32) -> Coat {
+        Coat {
+            ior,
+            roughness: 0.0,
+            thickness: 0.0,
+            absorption: Default::default(),
+        }
+    }
+
+    // Fraction of the light reaching the base at `cos` from the normal and coming back out
+    // the same way, refracted into the coat.
+    pub fn tint(&self, cos: f32) -> Vec3 {
+        let sin2 = (1.0 - cos * cos) / (self.ior * self.ior);
+        let cos_t = libm::sqrtf((1.0 - sin2).max(0.0)).max(1e-3);
+        let length = 2.0 * self.thickness / cos_t;
+        self.absorption.map(|coefficient| libm::expf(-coefficient * length))
+    }
+}
+
+// Material of `Geometry::material_buf` seen through a coat.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Layered {
+    pub base: MaterialIdx,
+    pub coat: Coat,
+}
 
 #[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Material {
     pub refract_index: f32,
     pub albedo: [f32; 4],
     pub diffuse_color: Vec3,
     pub spectacular_exp: f32,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub textures: MaterialTextures,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub kind: MaterialKind,
+    // radiance given off by the surface, the same from both sides
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub emission: Vec3,
+    // replaces `refract_index`, or the index of a dielectric, and splits light when rendering
+    // spectrally
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub dispersion: Option<Dispersion>,
 }
 
 impl Material {
@@ -20,7 +153,216 @@ impl Material {
             albedo,
             diffuse_color,
             spectacular_exp,
+            textures: Default::default(),
+            kind: Default::default(),
+            emission: Default::default(),
+            dispersion: None,
+        }
+    }
+
+    pub fn pbr(pbr: Pbr) -> Material {
+        Material {
+            kind: MaterialKind::Pbr(pbr),
+            ..Default::default()
+        }
+    }
+
+    pub fn dielectric(dielectric: Dielectric) -> Material {
+        Material {
+            kind: MaterialKind::Dielectric(dielectric),
+            ..Default::default()
+        }
+    }
+
+    pub fn subsurface(subsurface: Subsurface) -> Material {
+        Material {
+            diffuse_color: subsurface.color,
+            kind: MaterialKind::Subsurface(subsurface),
+            ..Default::default()
+        }
+    }
+
+    pub fn mix(mix: Mix) -> Material {
+        Material {
+            kind: MaterialKind::Mix(mix),
+            ..Default::default()
+        }
+    }
+
+    pub fn layered(layered: Layered) -> Material {
+        Material {
+            kind: MaterialKind::Layered(layered),
+            ..Default::default()
+        }
+    }
+
+    pub fn composite(&self) -> bool {
+        matches!(self.kind, MaterialKind::Mix(_) | MaterialKind::Layered(_))
+    }
+
+    // The plain material a ray along `dir` meets at `hit`, and the fraction of the light coming
+    // through the coats over it. Mixes pick one of their parts at random by weight, and coats
+    // reflect the ray by the Fresnel equations, as a white metal of their roughness, or let it
+    // through to their base.
+    pub fn resolve(
+        &self,
+        geometry: &Geometry,
+        hit: &Hit,
+        dir: &Vec3,
+        footprint: f32,
+        rng: &mut Rng,
+    ) -> (Material, Vec3) {
+        let mut material = *self;
+        let mut tint = Vec3::new(1.0, 1.0, 1.0);
+        for _ in 0..MAX_NESTING {
+            match material.kind {
+                MaterialKind::Mix(mix) => {
+                    let mask = mix
+                        .mask
+                        .and_then(|idx| geometry.texture_buf.get(idx))
+                        .map_or(1.0, |texture| texture.sample(hit, footprint).x);
+                    let second = rng.next_f32() < (mix.weight * mask).clamp(0.0, 1.0);
+                    material = *geometry.material_buf.load(mix.materials[second as usize]);
+                }
+                MaterialKind::Layered(layered) => {
+                    let cos = libm::fabsf(dot_product(dir, &hit.normal));
+                    if rng.next_f32() < fresnel_dielectric(cos, 1.0, layered.coat.ior) {
+                        return (
+                            Material {
+                                textures: material.textures,
+                                ..Material::pbr(Pbr::new(
+                                    Vec3::new(1.0, 1.0, 1.0),
+                                    1.0,
+                                    layered.coat.roughness,
+                                ))
+                            },
+                            tint,
+                        );
+                    }
+                    tint = tint * layered.coat.tint(cos);
+                    material = *geometry.material_buf.load(layered.base);
+                }
+                _ => return (material, tint),
+            }
+        }
+        (Material::default(), tint)
+    }
+
+    pub fn emissive(&self) -> bool {
+        self.emission.x.max(self.emission.y).max(self.emission.z) > 0.0
+    }
+
+    // The material for light of a single wavelength, colors being gray levels of their spectra,
+    // or for RGB rendering with `None`, where dispersive materials refract as at the d line.
+    pub fn at_wavelength(&self, wavelength: Option<f32>) -> Material {
+        let mut material = *self;
+        if let Some(dispersion) = self.dispersion {
+            let ior = dispersion.ior(wavelength.unwrap_or(D_LINE));
+            material.refract_index = ior;
+            if let MaterialKind::Dielectric(dielectric) = &mut material.kind {
+                dielectric.ior = ior;
+            }
+        }
+        if wavelength.is_none() {
+            return material;
+        }
+        material.diffuse_color = spectral(&material.diffuse_color, wavelength);
+        material.emission = spectral(&material.emission, wavelength);
+        match &mut material.kind {
+            MaterialKind::Phong | MaterialKind::Mix(_) | MaterialKind::Layered(_) => {}
+            MaterialKind::Pbr(pbr) => pbr.base_color = spectral(&pbr.base_color, wavelength),
+            MaterialKind::Dielectric(dielectric) => {
+                dielectric.absorption = spectral(&dielectric.absorption, wavelength)
+            }
+            MaterialKind::Subsurface(subsurface) => {
+                subsurface.color = spectral(&subsurface.color, wavelength);
+                subsurface.radius = spectral(&subsurface.radius, wavelength);
+            }
+        }
+        material
+    }
+
+    // The material as seen at `hit`, with every texture sampled in.
+    pub fn textured(&self, textures: &TextureBuf, hit: &Hit, footprint: f32) -> Material {
+        let sample = |slot: Option<TextureIdx>| Some(textures.get(slot?)?.sample(hit, footprint));
+        let mean = |color: Vec3| (color.x + color.y + color.z) / 3.0;
+        let mut material = *self;
+        if let MaterialKind::Pbr(pbr) = &mut material.kind {
+            if let Some(color) = sample(self.textures.diffuse) {
+                pbr.base_color = pbr.base_color * color;
+            }
+            if let Some(specular) = sample(self.textures.specular) {
+                pbr.specular *= mean(specular);
+            }
+            if let Some(roughness) = sample(self.textures.roughness) {
+                pbr.roughness = roughness.x;
+            }
+            if let Some(metallic) = sample(self.textures.metallic) {
+                pbr.metallic = metallic.x;
+            }
+            return material;
+        }
+
+        if let Some(color) = sample(self.textures.diffuse) {
+            material.diffuse_color = self.diffuse_color * color;
+            if let MaterialKind::Subsurface(subsurface) = &mut material.kind {
+                subsurface.color = subsurface.color * color;
+            }
+        }
+        if let Some(specular) = sample(self.textures.specular) {
+            material.albedo[1] *= mean(specular);
+        }
+        if let Some(reflection) = sample(self.textures.reflection) {
+            material.albedo[2] *= mean(reflection);
+        }
+        if let Some(roughness) = sample(self.textures.roughness) {
+            let alpha = (roughness.x * roughness.x).max(1e-2);
+            material.spectacular_exp = 2.0 / (alpha * alpha) - 2.0;
         }
+        material
+    }
+
+    // Normal to shade `hit` with, after the normal and bump maps.
+    pub fn shading_normal(&self, textures: &TextureBuf, hit: &Hit, footprint: f32) -> Vec3 {
+        let mut normal = hit.normal;
+        if let Some(texture) = self.textures.normal.and_then(|idx| textures.get(idx)) {
+            let tangent = (hit.tangent - normal * dot_product(&normal, &hit.tangent)).normalized();
+            let mut bitangent = cross_product(&normal, &tangent);
+            if dot_product(&bitangent, &hit.bitangent) < 0.0 {
+                bitangent = -bitangent;
+            }
+            let texel = texture.sample(hit, footprint) * 2.0 - Vec3::new(1.0, 1.0, 1.0);
+            normal = (tangent * texel.x + bitangent * texel.y + normal * texel.z).normalized();
+        }
+        if let Some(texture) = self.textures.bump.and_then(|idx| textures.get(idx)) {
+            // finite differences over about half a pixel, moving the point along with the uv
+            // for textures in world space (and object space, ignoring instance transforms)
+            let delta = (footprint * 0.5).max(1e-3);
+            let height = |du: f32, dv: f32| {
+                let shift = hit.tangent * du + hit.bitangent * dv;
+                let shifted = Hit {
+                    point: hit.point + shift,
+                    object_point: hit.object_point + shift,
+                    uv: hit.uv + Vec2::new(du, dv),
+                    ..*hit
+                };
+                texture.sample(&shifted, footprint).x * self.textures.bump_scale
+            };
+            let base = height(0.0, 0.0);
+            let slope_u = (height(delta, 0.0) - base) / delta;
+            let slope_v = (height(0.0, delta) - base) / delta;
+            let bumped = cross_product(
+                &(hit.tangent + normal * slope_u),
+                &(hit.bitangent + normal * slope_v),
+            )
+            .normalized();
+            normal = if dot_product(&bumped, &normal) < 0.0 {
+                -bumped
+            } else {
+                bumped
+            };
+        }
+        normal
     }
 }
 
@@ -31,6 +373,131 @@ impl Default for Material {
             albedo: [1.0, 0.0, 0.0, 0.0],
             diffuse_color: Default::default(),
             spectacular_exp: 0.0,
+            textures: Default::default(),
+            kind: Default::default(),
+            emission: Default::default(),
+            dispersion: None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::texture::{Pattern, ProceduralTexture, Texture, TextureSpace};
+
+    #[test]
+    fn test_bump_normal() {
+        let mut textures = TextureBuf::default();
+        let black = Vec3::new(0.0, 0.0, 0.0);
+        let white = Vec3::new(1.0, 1.0, 1.0);
+        let ramp = ProceduralTexture::new(Pattern::Gradient, TextureSpace::Uv, 1.0, [black, white]);
+        let mut material = Material::default();
+        material.textures.bump = Some(textures.push(Texture::Procedural(ramp)));
+        material.textures.bump_scale = 0.5;
+
+        let normal = Vec3::new(0.0, 0.0, 1.0);
+        let hit = Hit {
+            tangent: Vec3::new(1.0, 0.0, 0.0),
+            bitangent: Vec3::new(0.0, 1.0, 0.0),
+            ..Hit::new(Default::default(), normal, Vec2::new(0.5, 0.5), material)
+        };
+        // the height rises along u, so the normal leans back against it
+        let bumped = material.shading_normal(&textures, &hit, 0.0);
+        assert!((bumped - Vec3::new(-0.5, 0.0, 1.0).normalized()).norm() < 1e-3);
+        assert_eq!(normal, Material::default().shading_normal(&textures, &hit, 0.0));
+    }
+
+    #[test]
+    fn test_resolve_composites() {
+        let mut geometry = Geometry::default();
+        let red = Material::new(1.0, [1.0, 0.0, 0.0, 0.0], Vec3::new(1.0, 0.0, 0.0), 0.0);
+        let blue = Material::new(1.0, [1.0, 0.0, 0.0, 0.0], Vec3::new(0.0, 0.0, 1.0), 0.0);
+        let mix = Material::mix(Mix {
+            materials: [geometry.push_material(red), geometry.push_material(blue)],
+            weight: 0.25,
+            mask: None,
+        });
+        let coat = Coat {
+            thickness: 0.5,
+            absorption: Vec3::new(0.0, 0.2, 0.4),
+            ..Coat::new(1.5)
+        };
+        let paint = Material::layered(Layered {
+            base: geometry.push_material(mix),
+            coat,
+        });
+
+        // straight on, 4% is reflected off the coat and the rest goes through it twice
+        let hit = Hit::new(Default::default(), Vec3::new(0.0, 0.0, 1.0), Default::default(), paint);
+        let dir = Vec3::new(0.0, 0.0, -1.0);
+        let mut rng = Rng::new(9);
+        let count = 20000;
+        let (mut reflected, mut blues) = (0, 0);
+        for _ in 0..count {
+            let (material, tint) = paint.resolve(&geometry, &hit, &dir, 0.0, &mut rng);
+            match material.kind {
+                MaterialKind::Pbr(_) => reflected += 1,
+                _ => {
+                    assert!((tint - Vec3::new(1.0, libm::expf(-0.2), libm::expf(-0.4))).norm() < 1e-5);
+                    blues += (material.diffuse_color.z > 0.0) as usize;
+                }
+            }
+        }
+        assert!((reflected as f32 / count as f32 - 0.04).abs() < 0.01);
+        assert!((blues as f32 / (count - reflected) as f32 - 0.25).abs() < 0.02);
+    }
+
+    #[test]
+    fn test_resolve_self_nesting() {
+        // materials nesting in themselves are given up on after `MAX_NESTING` steps
+        let mut geometry = Geometry::default();
+        let idx = geometry.material_buf.materials.len() as MaterialIdx;
+        let looped = Material::mix(Mix {
+            materials: [idx, idx],
+            weight: 0.5,
+            mask: None,
+        });
+        geometry.push_material(looped);
+        let coated = Material::layered(Layered {
+            base: idx + 1,
+            coat: Coat::new(1.0),
+        });
+        geometry.push_material(coated);
+
+        let hit = Hit::new(Default::default(), Vec3::new(0.0, 0.0, 1.0), Default::default(), looped);
+        let dir = Vec3::new(0.0, 0.0, -1.0);
+        let mut rng = Rng::new(4);
+        for material in [looped, coated] {
+            let (resolved, tint) = material.resolve(&geometry, &hit, &dir, 0.0, &mut rng);
+            assert_eq!(MaterialKind::Phong, resolved.kind);
+            assert_eq!(Vec3::new(1.0, 1.0, 1.0), tint);
+        }
+    }
+
+    #[test]
+    fn test_coat_darkens_base() {
+        // the light reaching the base under a clear coat falls off towards grazing angles as
+        // the coat reflects more of it
+        let mut geometry = Geometry::default();
+        let white = Material::new(1.0, [1.0, 0.0, 0.0, 0.0], Vec3::new(1.0, 1.0, 1.0), 0.0);
+        let coated = Material::layered(Layered {
+            base: geometry.push_material(white),
+            coat: Coat::new(1.5),
+        });
+        let normal = Vec3::new(0.0, 0.0, 1.0);
+        let hit = Hit::new(Default::default(), normal, Default::default(), coated);
+        let mut rng = Rng::new(6);
+        let count = 20000;
+        for cos in [1.0, 0.5, 0.2] {
+            let dir = Vec3::new(libm::sqrtf(1.0 - cos * cos), 0.0, -cos);
+            let through: f32 = (0..count)
+                .map(|_| coated.resolve(&geometry, &hit, &dir, 0.0, &mut rng))
+                .filter(|(material, _)| material.kind == MaterialKind::Phong)
+                .map(|(material, tint)| (material.diffuse_color * tint).x)
+                .sum();
+            let expected = 1.0 - fresnel_dielectric(cos, 1.0, 1.5);
+            assert!((through / count as f32 - expected).abs() < 0.01, "{cos} {through}");
         }
     }
 }
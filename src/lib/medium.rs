@@ -0,0 +1,296 @@
+use alloc::vec::Vec;
+use core::f32::consts::PI;
+
+use crate::entity::bounding_box::BoundingBox;
+use crate::frame::Frame;
+use crate::ray::Ray;
+use crate::sampling::Rng;
+use crate::vec2::Vec2;
+use crate::vec3::{dot_product, Vec3};
+
+// Density samples on the corners of a regular grid of cells spanning `bounds`, x varying
+// fastest, interpolated trilinearly and zero outside.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(into = "GridData", try_from = "GridData"))]
+pub struct Grid {
+    bounds: BoundingBox,
+    resolution: [usize; 3],
+    values: Vec<f32>,
+    max_value: f32,
+}
+
+impl Grid {
+    // `None` unless there is one value, not negative, for each of the samples.
+    pub fn new(min: Vec3, max: Vec3, resolution: [usize; 3], values: Vec<f32>) -> Option<Grid> {
+        let count = resolution.iter().product::<usize>();
+        if count == 0
+            || values.len() != count
+            || values.iter().any(|value| *value < 0.0 || value.is_nan())
+        {
+            return None;
+        }
+        if !(min.x < max.x && min.y < max.y && min.z < max.z) {
+            return None;
+        }
+        Some(Grid {
+            bounds: BoundingBox::from_min_max(min, max),
+            resolution,
+            max_value: values.iter().copied().fold(0.0, f32::max),
+            values,
+        })
+    }
+
+    pub fn density(&self, point: &Vec3) -> f32 {
+        let (min, max) = (self.bounds.min(), self.bounds.max());
+        let relative = [
+            (point.x - min.x) / (max.x - min.x),
+            (point.y - min.y) / (max.y - min.y),
+            (point.z - min.z) / (max.z - min.z),
+        ];
+        if relative.iter().any(|t| !(0.0..=1.0).contains(t)) {
+            return 0.0;
+        }
+        let [nx, ny, _] = self.resolution;
+        let mut cell = [0; 3];
+        let mut weights = [0.0; 3];
+        for axis in 0..3 {
+            let last = self.resolution[axis] - 1;
+            let position = relative[axis] * last as f32;
+            cell[axis] = (position as usize).min(last.saturating_sub(1));
+            weights[axis] = (position - cell[axis] as f32).min(1.0);
+        }
+        let value = |x: usize, y: usize, z: usize| {
+            let x = (cell[0] + x).min(nx - 1);
+            let y = (cell[1] + y).min(ny - 1);
+            let z = (cell[2] + z).min(self.resolution[2] - 1);
+            self.values[x + nx * (y + ny * z)]
+        };
+        let lerp = |a: f32, b: f32, t: f32| a + (b - a) * t;
+        let [wx, wy, wz] = weights;
+        let layer = |z: usize| {
+            let near = lerp(value(0, 0, z), value(1, 0, z), wx);
+            let far = lerp(value(0, 1, z), value(1, 1, z), wx);
+            lerp(near, far, wy)
+        };
+        lerp(layer(0), layer(1), wz)
+    }
+}
+
+// What a grid is stored as, checked by `Grid::new` when loaded.
+#[cfg(feature = "serde")]
+#[derive(serde::Serialize, serde::Deserialize)]
+struct GridData {
+    min: Vec3,
+    max: Vec3,
+    resolution: [usize; 3],
+    values: Vec<f32>,
+}
+
+#[cfg(feature = "serde")]
+impl From<Grid> for GridData {
+    fn from(grid: Grid) -> Self {
+        GridData {
+            min: grid.bounds.min(),
+            max: grid.bounds.max(),
+            resolution: grid.resolution,
+            values: grid.values,
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl TryFrom<GridData> for Grid {
+    type Error = &'static str;
+
+    fn try_from(data: GridData) -> Result<Self, Self::Error> {
+        Grid::new(data.min, data.max, data.resolution, data.values)
+            .ok_or("density grid needs one value, not negative, per sample of a non empty box")
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(tag = "type", rename_all = "snake_case"))]
+pub enum Density {
+    #[default]
+    Homogeneous,
+    Grid(Grid),
+}
+
+// Participating medium such as fog or smoke. Light is taken out of a ray at `extinction`
+// times the density per unit length, and the `albedo` fraction of it is scattered by the
+// Henyey-Greenstein phase function, forwards for a positive `anisotropy`.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Medium {
+    pub extinction: f32,
+    pub albedo: Vec3,
+    pub anisotropy: f32,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub density: Density,
+}
+
+impl Medium {
+    pub fn new(extinction: f32, albedo: Vec3, anisotropy: f32) -> Medium {
+        Medium {
+            extinction,
+            albedo,
+            anisotropy,
+            density: Density::Homogeneous,
+        }
+    }
+
+    fn extinction_at(&self, point: &Vec3) -> f32 {
+        match &self.density {
+            Density::Homogeneous => self.extinction,
+            Density::Grid(grid) => self.extinction * grid.density(point),
+        }
+    }
+
+    // Part of the ray within `max_dist` that the medium can take light from.
+    fn span(&self, ray: &Ray, max_dist: f32) -> Option<(f32, f32)> {
+        let (near, far) = match &self.density {
+            Density::Homogeneous => (0.0, max_dist),
+            Density::Grid(grid) => {
+                let (near, far) = grid.bounds.ray_range(ray);
+                (near.max(0.0), far.min(max_dist))
+            }
+        };
+        (near < far).then_some((near, far))
+    }
+
+    fn majorant(&self) -> f32 {
+        match &self.density {
+            Density::Homogeneous => self.extinction,
+            Density::Grid(grid) => self.extinction * grid.max_value,
+        }
+    }
+
+    // Distance to where the ray is scattered before `max_dist`, by delta tracking (Woodcock).
+    pub fn sample_collision(&self, ray: &Ray, max_dist: f32, rng: &mut Rng) -> Option<f32> {
+        let majorant = self.majorant();
+        let (mut dist, far) = self.span(ray, max_dist)?;
+        if majorant <= 0.0 {
+            return None;
+        }
+        loop {
+            dist -= libm::logf(1.0 - rng.next_f32()) / majorant;
+            if dist >= far {
+                return None;
+            }
+            let point = ray.from + ray.dir * dist;
+            if rng.next_f32() * majorant < self.extinction_at(&point) {
+                return Some(dist);
+            }
+        }
+    }
+
+    // Fraction of the light getting through the first `dist` of the ray, estimated by ratio
+    // tracking where the density varies.
+    pub fn transmittance(&self, ray: &Ray, dist: f32, rng: &mut Rng) -> f32 {
+        let Some((mut along, far)) = self.span(ray, dist) else {
+            return 1.0;
+        };
+        let majorant = self.majorant();
+        match self.density {
+            _ if majorant <= 0.0 => 1.0,
+            Density::Homogeneous => libm::expf(-majorant * (far - along)),
+            Density::Grid(_) => {
+                let mut transmittance = 1.0;
+                loop {
+                    along -= libm::logf(1.0 - rng.next_f32()) / majorant;
+                    if along >= far || transmittance <= 0.0 {
+                        return transmittance;
+                    }
+                    let point = ray.from + ray.dir * along;
+                    transmittance *= 1.0 - self.extinction_at(&point) / majorant;
+                }
+            }
+        }
+    }
+
+    // Density of scattering from travelling along `dir` into `wi`, per solid angle.
+    pub fn phase(&self, dir: &Vec3, wi: &Vec3) -> f32 {
+        let g = self.anisotropy;
+        let denom = 1.0 + g * g - 2.0 * g * dot_product(dir, wi);
+        (1.0 - g * g) / (4.0 * PI * denom * libm::sqrtf(denom))
+    }
+
+    pub fn sample_phase(&self, dir: &Vec3, sample: Vec2) -> Vec3 {
+        sample_henyey_greenstein(self.anisotropy, dir, sample)
+    }
+}
+
+// Direction scattered from `dir` by the Henyey-Greenstein phase function of anisotropy `g`.
+pub fn sample_henyey_greenstein(g: f32, dir: &Vec3, sample: Vec2) -> Vec3 {
+    let cos = if libm::fabsf(g) < 1e-3 {
+        1.0 - 2.0 * sample.x
+    } else {
+        let ratio = (1.0 - g * g) / (1.0 - g + 2.0 * g * sample.x);
+        ((1.0 + g * g - ratio * ratio) / (2.0 * g)).clamp(-1.0, 1.0)
+    };
+    let sin = libm::sqrtf((1.0 - cos * cos).max(0.0));
+    let (sin_phi, cos_phi) = libm::sincosf(2.0 * PI * sample.y);
+    Frame::from_normal(dir).to_world(&Vec3::new(sin * cos_phi, sin * sin_phi, cos))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_delta_tracking() {
+        // a grid at a constant density behaves as the homogeneous medium
+        let values = alloc::vec![0.5; 8];
+        let grid = Grid::new(
+            Vec3::new(-1.0, -1.0, -1.0),
+            Vec3::new(1.0, 1.0, 1.0),
+            [2, 2, 2],
+            values,
+        )
+        .unwrap();
+        let homogeneous = Medium::new(1.0, Vec3::new(1.0, 1.0, 1.0), 0.0);
+        let smoke = Medium {
+            extinction: 2.0,
+            density: Density::Grid(grid),
+            ..homogeneous.clone()
+        };
+        let ray = Ray::new(Vec3::new(-1.0, 0.0, 0.0), Vec3::new(1.0, 0.0, 0.0));
+        let expected = libm::expf(-1.5);
+        assert!((homogeneous.transmittance(&ray, 1.5, &mut Rng::new(0)) - expected).abs() < 1e-6);
+
+        let mut rng = Rng::new(7);
+        let count = 20000;
+        let escaped = (0..count)
+            .filter(|_| smoke.sample_collision(&ray, 1.5, &mut rng).is_none())
+            .count();
+        assert!((escaped as f32 / count as f32 - expected).abs() < 0.02);
+        let mean = (0..count)
+            .map(|_| smoke.transmittance(&ray, 1.5, &mut rng))
+            .sum::<f32>();
+        assert!((mean / count as f32 - expected).abs() < 0.02);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_grid_serde() {
+        let grid = Grid::new(
+            Vec3::new(-1.0, -1.0, -1.0),
+            Vec3::new(1.0, 1.0, 1.0),
+            [2, 1, 1],
+            alloc::vec![0.0, 1.0],
+        )
+        .unwrap();
+        let json = serde_json::to_string(&grid).unwrap();
+        let restored: Grid = serde_json::from_str(&json).unwrap();
+        assert_eq!(1.0, restored.max_value);
+        assert_eq!(0.5, restored.density(&Vec3::new(0.0, 0.0, 0.0)));
+
+        // loaded through `Grid::new`, so short or negative values are refused
+        let short = json.replace("[0.0,1.0]", "[0.0]");
+        assert!(serde_json::from_str::<Grid>(&short).is_err());
+        let negative = json.replace("[0.0,1.0]", "[0.0,-1.0]");
+        assert!(serde_json::from_str::<Grid>(&negative).is_err());
+    }
+}
@@ -1,11 +1,34 @@
 #![no_std]
 extern crate alloc;
+#[cfg(feature = "std")]
+extern crate std;
 
+pub mod bsdf;
+pub mod builder;
+pub mod camera;
+#[cfg(feature = "serde")]
+pub mod description;
+pub mod emitter;
 pub mod entity;
+pub mod environment;
+pub mod frame;
+pub mod integrator;
+pub mod interior;
 pub mod intersect;
 pub mod light;
+pub mod loader;
+pub mod mat4;
 pub mod material;
+pub mod medium;
+pub mod noise;
 pub mod render;
+pub mod sampling;
+pub mod scene;
+pub mod solver;
+pub mod spectrum;
+pub mod texture;
+pub mod transform;
 pub mod utils;
+pub mod vec2;
 pub mod vec3;
 pub mod ray;
@@ -0,0 +1,182 @@
+// Lattice noise without permutation tables, lattice points are hashed instead.
+use crate::vec3::Vec3;
+
+fn hash(x: i32, y: i32, z: i32) -> u32 {
+    let mut h = (x as u32).wrapping_mul(0x8da6_b343)
+        ^ (y as u32).wrapping_mul(0xd816_3841)
+        ^ (z as u32).wrapping_mul(0xcb1a_b31f);
+    h ^= h >> 16;
+    h = h.wrapping_mul(0x7feb_352d);
+    h ^= h >> 15;
+    h = h.wrapping_mul(0x846c_a68b);
+    h ^ (h >> 16)
+}
+
+// Dot product with one of the 12 cube edge gradients (Perlin 2002).
+fn gradient(hash: u32, x: f32, y: f32, z: f32) -> f32 {
+    let h = hash & 15;
+    let u = if h < 8 { x } else { y };
+    let v = if h < 4 {
+        y
+    } else if h == 12 || h == 14 {
+        x
+    } else {
+        z
+    };
+    (if h & 1 == 0 { u } else { -u }) + (if h & 2 == 0 { v } else { -v })
+}
+
+fn lerp(t: f32, a: f32, b: f32) -> f32 {
+    a + t * (b - a)
+}
+
+fn fade(t: f32) -> f32 {
+    t * t * t * (t * (t * 6.0 - 15.0) + 10.0)
+}
+
+fn split(value: f32) -> (i32, f32) {
+    let floor = libm::floorf(value);
+    (floor as i32, value - floor)
+}
+
+// Improved Perlin noise, roughly in [-1, 1].
+pub fn perlin(point: &Vec3) -> f32 {
+    let ((xi, x), (yi, y), (zi, z)) = (split(point.x), split(point.y), split(point.z));
+    let (u, v, w) = (fade(x), fade(y), fade(z));
+    let corner = |dx: i32, dy: i32, dz: i32| {
+        let h = hash(xi + dx, yi + dy, zi + dz);
+        gradient(h, x - dx as f32, y - dy as f32, z - dz as f32)
+    };
+
+    lerp(
+        w,
+        lerp(
+            v,
+            lerp(u, corner(0, 0, 0), corner(1, 0, 0)),
+            lerp(u, corner(0, 1, 0), corner(1, 1, 0)),
+        ),
+        lerp(
+            v,
+            lerp(u, corner(0, 0, 1), corner(1, 0, 1)),
+            lerp(u, corner(0, 1, 1), corner(1, 1, 1)),
+        ),
+    )
+}
+
+// 3D simplex noise (Gustavson), roughly in [-1, 1].
+pub fn simplex(point: &Vec3) -> f32 {
+    const F3: f32 = 1.0 / 3.0;
+    const G3: f32 = 1.0 / 6.0;
+
+    let skew = (point.x + point.y + point.z) * F3;
+    let (i, j, k) = (
+        libm::floorf(point.x + skew) as i32,
+        libm::floorf(point.y + skew) as i32,
+        libm::floorf(point.z + skew) as i32,
+    );
+    let unskew = (i + j + k) as f32 * G3;
+    let origin = Vec3::new(
+        point.x - (i as f32 - unskew),
+        point.y - (j as f32 - unskew),
+        point.z - (k as f32 - unskew),
+    );
+
+    // the two middle corners of the simplex containing the point
+    let (first, second) = if origin.x >= origin.y {
+        if origin.y >= origin.z {
+            ((1, 0, 0), (1, 1, 0))
+        } else if origin.x >= origin.z {
+            ((1, 0, 0), (1, 0, 1))
+        } else {
+            ((0, 0, 1), (1, 0, 1))
+        }
+    } else if origin.y < origin.z {
+        ((0, 0, 1), (0, 1, 1))
+    } else if origin.x < origin.z {
+        ((0, 1, 0), (0, 1, 1))
+    } else {
+        ((0, 1, 0), (1, 1, 0))
+    };
+
+    [(0, 0, 0), first, second, (1, 1, 1)]
+        .iter()
+        .enumerate()
+        .map(|(idx, (di, dj, dk))| {
+            let offset = idx as f32 * G3;
+            let x = origin.x - *di as f32 + offset;
+            let y = origin.y - *dj as f32 + offset;
+            let z = origin.z - *dk as f32 + offset;
+            let t = 0.6 - x * x - y * y - z * z;
+            if t < 0.0 {
+                0.0
+            } else {
+                let t = t * t;
+                t * t * gradient(hash(i + di, j + dj, k + dk), x, y, z)
+            }
+        })
+        .sum::<f32>()
+        * 32.0
+}
+
+// Fractal Brownian motion over Perlin noise, normalized back to roughly [-1, 1].
+pub fn fbm(point: &Vec3, octaves: u32) -> f32 {
+    octaves_sum(point, octaves, perlin)
+}
+
+// Like `fbm`, but summing absolute values, in [0, 1].
+pub fn turbulence(point: &Vec3, octaves: u32) -> f32 {
+    octaves_sum(point, octaves, |point| libm::fabsf(perlin(point)))
+}
+
+fn octaves_sum(point: &Vec3, octaves: u32, noise: impl Fn(&Vec3) -> f32) -> f32 {
+    let (mut sum, mut norm) = (0.0, 0.0);
+    let (mut amplitude, mut frequency) = (1.0, 1.0);
+    for _ in 0..octaves.max(1) {
+        sum += amplitude * noise(&(*point * frequency));
+        norm += amplitude;
+        amplitude *= 0.5;
+        frequency *= 2.0;
+    }
+    sum / norm
+}
+
+// Distance to the nearest feature point of a jittered grid (Worley), usually below 1.
+pub fn voronoi(point: &Vec3) -> f32 {
+    let ((xi, x), (yi, y), (zi, z)) = (split(point.x), split(point.y), split(point.z));
+    let jitter = |h: u32, shift: u32| ((h >> shift) & 0x3ff) as f32 / 1024.0;
+
+    let mut nearest = f32::INFINITY;
+    for dz in -1..=1 {
+        for dy in -1..=1 {
+            for dx in -1..=1 {
+                let h = hash(xi + dx, yi + dy, zi + dz);
+                let feature = Vec3::new(
+                    dx as f32 + jitter(h, 0),
+                    dy as f32 + jitter(h, 10),
+                    dz as f32 + jitter(h, 20),
+                );
+                nearest = nearest.min((feature - Vec3::new(x, y, z)).norm());
+            }
+        }
+    }
+    nearest
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_noise_range() {
+        for idx in 0..1000 {
+            let t = idx as f32 * 0.137;
+            let point = Vec3::new(t, t * 0.7 - 3.0, 11.0 - t * 1.3);
+            assert!(libm::fabsf(perlin(&point)) <= 1.1);
+            assert!(libm::fabsf(simplex(&point)) <= 1.1);
+            assert!((0.0..=1.0).contains(&turbulence(&point, 4)));
+            assert!((0.0..1.8).contains(&voronoi(&point)));
+        }
+        // lattice points are zero crossings of gradient noise
+        assert_eq!(0.0, perlin(&Vec3::new(3.0, -2.0, 5.0)));
+    }
+}
@@ -1,63 +1,257 @@
-use alloc::vec::Vec;
-use crate::entity::scene::Scene;
-use crate::intersect::Intersect;
+use core::f32::consts::PI;
+
+use crate::bsdf::{fresnel_dielectric, reflect_local, schlick, Ggx, Pbr};
+use crate::camera::Camera;
+use crate::frame::Frame;
+use crate::integrator::Integrator;
+use crate::interior::{Boundary, Interior};
+use crate::intersect::{Hit, Intersect};
 use crate::light::Light;
-use crate::material::Material;
+use crate::material::{MaterialKind, MaterialTextures};
 use crate::ray::Ray;
-use crate::utils::{EPSILON, MaterialBuf, MaterialIdx, Vec3Idx, VecBuf};
-use crate::vec3::{cross_product, dot_product, Vec3};
+use crate::sampling::Rng;
+use crate::scene::Scene;
+use crate::spectrum::{sample_wavelength, spectral, to_rgb};
+use crate::utils::EPSILON;
+use crate::vec3::{dot_product, Vec3};
 
 #[derive(Debug, Clone)]
-pub struct RenderState {
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RenderSettings {
     pub width: usize,
     pub height: usize,
-    pub fov: f32,
-    pub origin: Vec3,
-    pub view_dir: Vec3,
     pub background_color: Vec3,
     pub recursion_limit: usize,
-    pub interest_point: Vec3,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub integrator: Integrator,
+    // traces a single wavelength per sample, for dispersion
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub spectral: bool,
+}
 
-    pub vec_buf: VecBuf,
-    pub material_buf: MaterialBuf,
+impl RenderSettings {
+    pub fn new(width: usize, height: usize) -> RenderSettings {
+        RenderSettings {
+            width,
+            height,
+            background_color: Default::default(),
+            recursion_limit: 5,
+            integrator: Default::default(),
+            spectral: false,
+        }
+    }
+}
 
-    pub scene: Scene,
-    pub lights: Vec<Light>
+#[derive(Debug, Clone, Copy)]
+pub struct Renderer<'a> {
+    pub scene: &'a Scene,
+    pub camera: &'a Camera,
+    pub settings: &'a RenderSettings,
 }
 
-impl RenderState {
-    fn cast_ray(&self, cast_depth: usize, ray: Ray) -> Vec3 {
-        if cast_depth >= self.recursion_limit {
-            return self.background_color;
+impl<'a> Renderer<'a> {
+    pub fn new(scene: &'a Scene, camera: &'a Camera, settings: &'a RenderSettings) -> Renderer<'a> {
+        Renderer {
+            scene,
+            camera,
+            settings,
+        }
+    }
+
+    // `offsets` are the rays of the neighbouring pixels, followed through the same bounces.
+    fn cast_ray(
+        &self,
+        cast_depth: usize,
+        ray: Ray,
+        offsets: &[Ray; 2],
+        interior: &Interior,
+        rng: &mut Rng,
+        wavelength: Option<f32>,
+    ) -> Vec3 {
+        if cast_depth >= self.settings.recursion_limit {
+            return spectral(&self.settings.background_color, wavelength);
         }
 
-        if let Some((hit, normal, material)) = self.scene.ray_intersect(self, ray) {
+        let geometry = self.scene.geometry();
+        if let Some(intersection) = self.scene.root().ray_intersect(geometry, ray) {
+            let hit = intersection.point;
+            // whatever comes back from the hit is absorbed by the medium on the way
+            let absorbed = interior.transmittance((hit - ray.from).norm());
+            let material = &intersection.material;
+            let footprint =
+                if material.textures == MaterialTextures::default() && !material.composite() {
+                    0.0
+                } else {
+                    self.footprint(&intersection, offsets)
+                };
+            let (material, tint) =
+                material.resolve(geometry, &intersection, &ray.dir, footprint, rng);
+            let absorbed = absorbed * spectral(&tint, wavelength);
+            let normal = material.shading_normal(&geometry.texture_buf, &intersection, footprint);
+            let material = material
+                .textured(&geometry.texture_buf, &intersection, footprint)
+                .at_wavelength(wavelength);
+            // offset rays leave from the tangent plane of the hit, curvature is ignored
+            let leave = |bend: &dyn Fn(&Vec3) -> Vec3| {
+                offsets.map(|offset| {
+                    let from = tangent_plane_point(&offset, &intersection).unwrap_or(hit);
+                    Ray::new(from, bend(&offset.dir).normalized())
+                })
+            };
+
+            if let MaterialKind::Pbr(pbr) = material.kind {
+                let frame = Frame::from_normal(&facing(&normal, &ray.dir));
+                let wo = frame.to_local(&-ray.dir);
+                // one glossy ray around the mirror direction, through a sampled microfacet
+                let ggx = Ggx::new(pbr.roughness);
+                let facet = ggx.sample_visible(&wo, rng);
+                let wi = reflect_local(&wo, &facet);
+                let mirror = if wi.z > 0.0 {
+                    schlick(pbr.f0(), dot_product(&wo, &facet)) * (ggx.g(&wo, &wi) / ggx.g1(&wo))
+                } else {
+                    Default::default()
+                };
+                let reflect_color = if mirror.x.max(mirror.y).max(mirror.z) < EPSILON {
+                    Default::default()
+                } else {
+                    let facet = frame.to_world(&facet);
+                    let bend = |dir: &Vec3| reflect(dir, &facet);
+                    let reflect_ray = Ray::new(hit, frame.to_world(&wi).normalized());
+                    self.cast_ray(
+                        cast_depth + 1,
+                        reflect_ray,
+                        &leave(&bend),
+                        interior,
+                        rng,
+                        wavelength,
+                    )
+                };
+                // direct light from the point lights only, as for the Phong shading below
+                let color = material.emission
+                    + self.direct_light(&pbr, &frame, &wo, &hit, |light| self.visible(&hit, light))
+                    + reflect_color * mirror;
+                return color * absorbed;
+            }
+
+            if let MaterialKind::Dielectric(dielectric) = material.kind {
+                let entering = dot_product(&ray.dir, &intersection.normal) < 0.0;
+                let (eta_i, eta_t, refracted) = match interior.cross(&dielectric, entering) {
+                    Boundary::Hidden(inside) => {
+                        let straight = |dir: &Vec3| *dir;
+                        let ray = Ray::new(hit, ray.dir);
+                        return self.cast_ray(
+                            cast_depth + 1,
+                            ray,
+                            &leave(&straight),
+                            &inside,
+                            rng,
+                            wavelength,
+                        ) * absorbed;
+                    }
+                    Boundary::Visible {
+                        eta_i,
+                        eta_t,
+                        refracted,
+                    } => (eta_i, eta_t, refracted),
+                };
+                let frame = Frame::from_normal(&facing(&normal, &ray.dir));
+                let wo = frame.to_local(&-ray.dir);
+                let facet = frame.to_world(&dielectric.facet(&wo, rng));
+                let reflectance = fresnel_dielectric(dot_product(&-ray.dir, &facet), eta_i, eta_t);
+                // scattered rays ending up on the wrong side of the surface are lost
+                let weight = |dir: &Vec3, side: f32| {
+                    let wi = frame.to_local(dir);
+                    if wi.z * side > 0.0 {
+                        dielectric.facet_weight(&wo, &wi)
+                    } else {
+                        0.0
+                    }
+                };
+
+                let reflect_dir = reflect(&ray.dir, &facet).normalized();
+                let reflect_weight = reflectance * weight(&reflect_dir, 1.0);
+                let reflect_color = if reflect_weight > 0.0 {
+                    let bend = |dir: &Vec3| reflect(dir, &facet);
+                    let reflect_ray = Ray::new(hit, reflect_dir);
+                    self.cast_ray(
+                        cast_depth + 1,
+                        reflect_ray,
+                        &leave(&bend),
+                        interior,
+                        rng,
+                        wavelength,
+                    )
+                } else {
+                    Default::default()
+                };
+                let refract_dir = refract_full(&ray.dir, &facet, eta_t, eta_i);
+                let refract_weight =
+                    refract_dir.map_or(0.0, |dir| (1.0 - reflectance) * weight(&dir, -1.0));
+                let refract_color = match refract_dir {
+                    Some(dir) if refract_weight > 0.0 => {
+                        let bend =
+                            |dir: &Vec3| refract_full(dir, &facet, eta_t, eta_i).unwrap_or(*dir);
+                        let refract_ray = Ray::new(hit, dir.normalized());
+                        self.cast_ray(
+                            cast_depth + 1,
+                            refract_ray,
+                            &leave(&bend),
+                            &refracted,
+                            rng,
+                            wavelength,
+                        )
+                    }
+                    _ => Default::default(),
+                };
+                let color = material.emission
+                    + reflect_color * reflect_weight
+                    + refract_color * refract_weight;
+                return color * absorbed;
+            }
+
+            // back faces of open meshes are shaded as seen, refraction keeps the winding order
+            // to tell leaving from entering
+            let facing_normal = facing(&normal, &ray.dir);
             let reflect_color = if libm::fabsf(material.albedo[2]) < EPSILON {
                 Default::default()
             } else {
-                let reflect_dir = reflect(&ray.dir, &normal).normalized();
-                self.cast_ray(cast_depth + 1, Ray::new(hit, reflect_dir))
+                let bend = |dir: &Vec3| reflect(dir, &facing_normal);
+                let reflect_dir = bend(&ray.dir).normalized();
+                self.cast_ray(
+                    cast_depth + 1,
+                    Ray::new(hit, reflect_dir),
+                    &leave(&bend),
+                    interior,
+                    rng,
+                    wavelength,
+                )
             };
 
             let refract_color = if libm::fabsf(material.albedo[3]) < EPSILON {
                 Default::default()
             } else {
-                let refract_dir = refract(&ray.dir, &normal, material.refract_index).normalized();
-                self.cast_ray(cast_depth + 1, Ray::new(hit, refract_dir))
+                // total internal reflection turns the refracted ray back inside
+                let bend = |dir: &Vec3| {
+                    refract(dir, &normal, material.refract_index)
+                        .unwrap_or_else(|| reflect(dir, &normal))
+                };
+                let refract_dir = bend(&ray.dir).normalized();
+                self.cast_ray(
+                    cast_depth + 1,
+                    Ray::new(hit, refract_dir),
+                    &leave(&bend),
+                    interior,
+                    rng,
+                    wavelength,
+                )
             };
 
             let (diffuse_light_intensity, specular_light_intensity) = self
-                .lights
+                .scene
+                .lights()
                 .iter()
-                .filter(|light| {
-                    let light_dir = (light.position - hit).normalized();
-                    if let Some((another_hit, _, _)) = self.scene.ray_intersect(self, Ray::new(hit, light_dir)) {
-                        hit.dist_observer(&another_hit) > hit.dist_observer(&light.position)
-                    } else {
-                        true
-                    }
-                })
-                .map(|light| light.get_light_scales(&hit, &ray.dir, &normal, &material))
+                .filter(|light| self.lit_by(&hit, light))
+                .map(|light| light.get_light_scales(&hit, &ray.dir, &facing_normal, &material))
                 .fold((0.0, 0.0), |acc, val| (acc.0 + val.0, acc.1 + val.1));
 
             let albedo_it = material.albedo.iter();
@@ -68,61 +262,159 @@ impl RenderState {
                 refract_color,
             ];
 
-            return scales.iter().zip(albedo_it).map(|(a, b)| *a * *b).sum();
+            // emissive surfaces glow here but only the path tracer lights the scene with them
+            let color =
+                material.emission + scales.iter().zip(albedo_it).map(|(a, b)| *a * *b).sum();
+            return color * absorbed;
         }
-        self.background_color
+        // the environment is only seen here, the path tracer also lights the scene with it
+        self.background(&ray.dir, wavelength) * interior.transmittance(f32::INFINITY)
     }
 
+    // Light arriving along `dir` from outside the scene.
+    pub(crate) fn background(&self, dir: &Vec3, wavelength: Option<f32>) -> Vec3 {
+        let color = match self.scene.environment() {
+            Some(environment) => {
+                environment.radiance(&self.scene.geometry().texture_buf, &dir.normalized())
+            }
+            None => self.settings.background_color,
+        };
+        spectral(&color, wavelength)
+    }
 
-    pub fn render_scene_pixel(
-        &self,
-        pixel_id: usize,
-    ) -> Vec3 {
-        let width_f = self.width as f32;
-        let height_f = self.height as f32;
-
-        let i = pixel_id / self.width;
-        let j = pixel_id % self.width;
-
-        let i_f = i as f32;
-        let j_f = j as f32;
-
-        let y_dir = Vec3::new(0.0, 1.0, 0.0);
+    fn visible(&self, point: &Vec3, light: &Light) -> Vec3 {
+        if self.lit_by(point, light) {
+            Vec3::new(1.0, 1.0, 1.0)
+        } else {
+            Vec3::default()
+        }
+    }
 
-        let i_vec = y_dir;
-        let j_vec = cross_product(&self.view_dir, &y_dir).normalized();
-        let width_real_f = self.view_dir.norm() * libm::sinf(self.fov / 2.0) * 2.0;
-        let height_real_f = width_real_f * height_f / width_f;
+    pub(crate) fn lit_by(&self, point: &Vec3, light: &Light) -> bool {
+        let light_dir = (light.position - *point).normalized();
+        let shadow_ray = Ray::new(*point, light_dir);
+        if let Some(another_hit) = self
+            .scene
+            .root()
+            .ray_intersect(self.scene.geometry(), shadow_ray)
+        {
+            point.dist_observer(&another_hit.point) > point.dist_observer(&light.position)
+        } else {
+            true
+        }
+    }
 
-        let j_step = j_vec * (width_real_f / width_f);
-        let i_step = i_vec * (height_real_f / height_f);
+    // Light reaching the viewer from the point lights off a physically based surface. Point
+    // lights keep the convention of `Light::get_light_scales`: no falloff, and a white
+    // Lambertian surface facing one reflects its intensity. `reach` is the fraction of a
+    // light's intensity getting to the point.
+    pub(crate) fn direct_light(
+        &self,
+        pbr: &Pbr,
+        frame: &Frame,
+        wo: &Vec3,
+        point: &Vec3,
+        mut reach: impl FnMut(&Light) -> Vec3,
+    ) -> Vec3 {
+        self.scene
+            .lights()
+            .iter()
+            .map(|light| {
+                let wi = frame.to_local(&(light.position - *point).normalized());
+                pbr.eval(wo, &wi) * (PI * light.intensity) * reach(light)
+            })
+            .sum()
+    }
 
-        let dir = (self.view_dir
-            + i_step * -2.0 * ((i_f - height_f / 2.0) as f32)
-            + j_step * 2.0 * ((j_f - width_f / 2.0) as f32))
-            .normalized();
+    // Size of the pixel at `hit` in uv units, from where the offset rays hit the scene. Zero
+    // when neither lands near the tangent plane of the hit, as at silhouettes.
+    pub(crate) fn footprint(&self, hit: &Hit, offsets: &[Ray; 2]) -> f32 {
+        offsets
+            .iter()
+            .filter_map(|offset| {
+                let expected = tangent_plane_point(offset, hit)?;
+                let offset_hit = self
+                    .scene
+                    .root()
+                    .ray_intersect(self.scene.geometry(), *offset)?;
+                let spread = (expected - hit.point).norm();
+                if (offset_hit.point - expected).norm() > 0.5 * spread + EPSILON {
+                    return None;
+                }
+                // uv wraps around on closed surfaces
+                let delta = offset_hit.uv - hit.uv;
+                let wrapped = |value: f32| libm::fabsf(value - libm::roundf(value));
+                Some(wrapped(delta.x).max(wrapped(delta.y)))
+            })
+            .fold(0.0, f32::max)
+    }
 
-        self.cast_ray(0, Ray::new(self.origin, dir))
+    pub fn render_scene_pixel(&self, pixel_id: usize) -> Vec3 {
+        let (width, height) = (self.settings.width, self.settings.height);
+        let offsets = self.camera.primary_ray_offsets(width, height, pixel_id);
+        let mut rng = Rng::new(pixel_id as u64);
+        let (samples_per_pixel, path) = match self.settings.integrator {
+            Integrator::Whitted { samples_per_pixel } => (samples_per_pixel, false),
+            Integrator::Path { samples_per_pixel } => (samples_per_pixel, true),
+        };
+        let samples = samples_per_pixel.max(1);
+        let sum: Vec3 = (0..samples)
+            .map(|sample| {
+                // a single Whitted sample keeps going through the pixel corner, as it always did
+                let ray = if path || samples > 1 {
+                    let jitter = rng.next_vec2();
+                    self.camera.sample_ray(width, height, pixel_id, jitter)
+                } else {
+                    self.camera.primary_ray(width, height, pixel_id)
+                };
+                // wavelengths are stratified over the samples of a pixel to tame the color noise
+                let wavelength = self
+                    .settings
+                    .spectral
+                    .then(|| sample_wavelength((sample as f32 + rng.next_f32()) / samples as f32));
+                let color = if path {
+                    self.trace_path(ray, &offsets, &mut rng, wavelength)
+                } else {
+                    self.cast_ray(0, ray, &offsets, &Interior::default(), &mut rng, wavelength)
+                };
+                // the channels are all equal when tracing a single wavelength
+                match wavelength {
+                    Some(wavelength) => to_rgb(color.x, wavelength),
+                    None => color,
+                }
+            })
+            .sum();
+        sum * (1.0 / samples as f32)
     }
+}
 
-    pub fn push_vec(&mut self, vec: Vec3) -> Vec3Idx {
-        self.vec_buf.push(vec)
+// The normal flipped towards where `dir` comes from.
+pub(crate) fn facing(normal: &Vec3, dir: &Vec3) -> Vec3 {
+    if dot_product(normal, dir) > 0.0 {
+        -*normal
+    } else {
+        *normal
     }
+}
 
-    pub fn push_material(&mut self, material: Material) -> MaterialIdx {
-        self.material_buf.push(material)
+fn tangent_plane_point(ray: &Ray, hit: &Hit) -> Option<Vec3> {
+    let cos = dot_product(&ray.dir, &hit.normal);
+    if libm::fabsf(cos) < EPSILON {
+        return None;
     }
+    Some(ray.from + ray.dir * (dot_product(&(hit.point - ray.from), &hit.normal) / cos))
 }
 
 pub fn reflect(dir: &Vec3, normal: &Vec3) -> Vec3 {
     *dir - *normal * 2.0 * dot_product(dir, normal)
 }
 
-fn refract(dir: &Vec3, normal: &Vec3, eta_t: f32) -> Vec3 {
+// Refracted direction, or `None` on total internal reflection.
+pub(crate) fn refract(dir: &Vec3, normal: &Vec3, eta_t: f32) -> Option<Vec3> {
     refract_full(dir, normal, eta_t, 1.0)
 }
 
-fn refract_full(dir: &Vec3, normal: &Vec3, eta_t: f32, eta_i: f32) -> Vec3 {
+pub(crate) fn refract_full(dir: &Vec3, normal: &Vec3, eta_t: f32, eta_i: f32) -> Option<Vec3> {
     let cos_i = -(1.0_f32.min((-1.0_f32).max(dot_product(dir, normal))));
 
     if cos_i < 0.0 {
@@ -133,8 +425,154 @@ fn refract_full(dir: &Vec3, normal: &Vec3, eta_t: f32, eta_i: f32) -> Vec3 {
     let k = 1.0 - eta * eta * (1.0 - cos_i * cos_i);
 
     if k < 0.0 {
-        Vec3::new(1.0, 0.0, 0.0)
+        None
     } else {
-        *dir * eta + *normal * (eta * cos_i - libm::sqrtf(k))
+        Some(*dir * eta + *normal * (eta * cos_i - libm::sqrtf(k)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::builder::SceneBuilder;
+    #[cfg(feature = "serde")]
+    use crate::environment::Projection;
+    use crate::light::Light;
+    use crate::loader::Shading;
+    use crate::material::Material;
+    use crate::texture::{Filter, ImageTexture, Texture, WrapMode};
+    #[cfg(feature = "serde")]
+    use alloc::vec;
+
+    #[test]
+    fn test_back_faces_shaded_as_seen() {
+        // a triangle wound either way reflects the light next to the camera the same
+        let render = |points: [[f32; 3]; 3]| {
+            let mut builder = SceneBuilder::new();
+            let gray = Vec3::new(0.5, 0.5, 0.5);
+            let matte = builder
+                .add_material(Material::new(1.0, [1.0, 0.0, 0.0, 0.0], gray, 10.0))
+                .unwrap();
+            builder.add_light(Light::new(Vec3::new(0.0, 1.0, 0.0), 1.0));
+            let points = points.map(|[x, y, z]| Vec3::new(x, y, z)).to_vec();
+            builder
+                .add_mesh(points, &[[0, 1, 2]], matte, Shading::Flat)
+                .unwrap();
+            let scene = builder.build();
+            let camera = Camera::new(Default::default(), Vec3::new(0.0, 0.0, -1.0), 1.0).unwrap();
+            let settings = RenderSettings::new(3, 3);
+            Renderer::new(&scene, &camera, &settings).render_scene_pixel(4)
+        };
+        let front = render([[-20.0, -20.0, -4.0], [20.0, -20.0, -4.0], [0.0, 20.0, -4.0]]);
+        let back = render([[-20.0, -20.0, -4.0], [0.0, 20.0, -4.0], [20.0, -20.0, -4.0]]);
+        assert!(front.x > 0.1, "{front:?} {back:?}");
+        assert!((front - back).norm() < 1e-5);
+    }
+
+    #[test]
+    fn test_texture_footprint() {
+        // a checker of single texels on a distant wall, the pixel centered on a texel
+        let size = 256;
+        let texels = (0..size * size)
+            .map(|idx| {
+                let value = ((idx / size + idx % size) % 2) as f32;
+                Vec3::new(value, value, value)
+            })
+            .collect();
+        let mut builder = SceneBuilder::new();
+        let image = ImageTexture::new(
+            size as u32,
+            size as u32,
+            texels,
+            WrapMode::Repeat,
+            Filter::default(),
+        )
+        .unwrap();
+        let checker = builder.add_texture(Texture::Image(image));
+        let wall = builder.add_material(Material::default()).unwrap();
+        let corner = -10.0 - 10.0 / size as f32;
+        builder
+            .add_quad(
+                Vec3::new(corner, corner, -4.0),
+                Vec3::new(20.0, 0.0, 0.0),
+                Vec3::new(0.0, 20.0, 0.0),
+                wall,
+            )
+            .unwrap();
+        let scene = builder.build();
+        let camera = Camera::new(Default::default(), Vec3::new(0.0, 0.0, -1.0), 1.0).unwrap();
+        let settings = RenderSettings::new(8, 8);
+        let renderer = Renderer::new(&scene, &camera, &settings);
+
+        let pixel = 8 * 4 + 4;
+        let ray = camera.primary_ray(8, 8, pixel);
+        let hit = scene.root().ray_intersect(scene.geometry(), ray).unwrap();
+        let footprint = renderer.footprint(&hit, &camera.primary_ray_offsets(8, 8, pixel));
+        // the next pixels are 2 sin(fov / 2) apart at this distance
+        let expected = 2.0 * libm::sinf(0.5) / 20.0;
+        assert!((footprint - expected).abs() < 0.05 * expected);
+
+        // the texels average out by default, rather than alias to one of them
+        let texture = scene.geometry().texture_buf.get(checker.idx()).unwrap();
+        let gray = Vec3::new(0.5, 0.5, 0.5);
+        assert!((texture.sample(&hit, footprint) - gray).norm() < 0.05);
+        assert!((texture.sample(&hit, 0.0) - gray).norm() > 0.4);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_scene_serde_roundtrip() {
+        let mut builder = SceneBuilder::new();
+        let red = builder
+            .add_material(Material::new(
+                1.0,
+                [0.9, 0.1, 0.0, 0.0],
+                Vec3::new(0.3, 0.1, 0.1),
+                10.0,
+            ))
+            .unwrap();
+        builder.add_light(Light::new(Vec3::new(-5.0, 5.0, 5.0), 1.5));
+        builder
+            .add_mesh(
+                vec![
+                    Vec3::new(-1.0, -1.0, -4.0),
+                    Vec3::new(1.0, -1.0, -4.0),
+                    Vec3::new(0.0, 1.0, -4.0),
+                ],
+                &[[0, 1, 2]],
+                red,
+                Shading::Flat,
+            )
+            .unwrap();
+        builder
+            .add_sphere(Vec3::new(2.0, 0.0, -6.0), 1.0, red)
+            .unwrap();
+        let texels = vec![Vec3::new(0.2, 0.3, 0.9), Vec3::new(0.9, 0.8, 0.2)];
+        let sky = ImageTexture::new(2, 1, texels, WrapMode::Repeat, Filter::Nearest).unwrap();
+        let sky = builder.add_texture(Texture::Image(sky));
+        builder
+            .set_environment(Projection::LatLong(sky), 0.5, 2.0)
+            .unwrap();
+        let scene = builder.build();
+
+        let json = serde_json::to_string(&scene).unwrap();
+        let restored: Scene = serde_json::from_str(&json).unwrap();
+        assert_eq!(json, serde_json::to_string(&restored).unwrap());
+        // only the settings of the environment are stored, its tables are rebuilt
+        assert!(!json.contains("cdf"));
+        let dir = Vec3::new(0.6, 0.0, 0.8);
+        let pdf = |scene: &Scene| scene.environment().unwrap().pdf(&dir);
+        assert_eq!(pdf(&scene), pdf(&restored));
+
+        let camera = Camera::new(Default::default(), Vec3::new(0.0, 0.0, -1.0), 1.0).unwrap();
+        let settings = RenderSettings::new(8, 6);
+        let renderer = Renderer::new(&scene, &camera, &settings);
+        let restored_renderer = Renderer::new(&restored, &camera, &settings);
+        for pixel in 0..settings.width * settings.height {
+            assert_eq!(
+                renderer.render_scene_pixel(pixel),
+                restored_renderer.render_scene_pixel(pixel)
+            );
+        }
     }
 }
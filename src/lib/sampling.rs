@@ -0,0 +1,66 @@
+use core::f32::consts::PI;
+
+use crate::vec2::Vec2;
+use crate::vec3::Vec3;
+
+// PCG32 (O'Neill 2014), small and good enough for Monte Carlo sampling.
+#[derive(Debug, Clone)]
+pub struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    pub fn new(seed: u64) -> Rng {
+        let mut rng = Rng { state: 0 };
+        rng.next_u32();
+        rng.state = rng.state.wrapping_add(seed);
+        rng.next_u32();
+        rng
+    }
+
+    pub fn next_u32(&mut self) -> u32 {
+        let old = self.state;
+        self.state = old
+            .wrapping_mul(6364136223846793005)
+            .wrapping_add(1442695040888963407);
+        let xorshifted = (((old >> 18) ^ old) >> 27) as u32;
+        xorshifted.rotate_right((old >> 59) as u32)
+    }
+
+    // Uniform in [0, 1).
+    pub fn next_f32(&mut self) -> f32 {
+        (self.next_u32() >> 8) as f32 / (1u32 << 24) as f32
+    }
+
+    pub fn next_vec2(&mut self) -> Vec2 {
+        Vec2::new(self.next_f32(), self.next_f32())
+    }
+}
+
+// Direction around the local z axis with density cos(theta) / pi.
+pub fn cosine_hemisphere(sample: Vec2) -> Vec3 {
+    let radius = libm::sqrtf(sample.x);
+    let (sin, cos) = libm::sincosf(2.0 * PI * sample.y);
+    Vec3::new(
+        radius * cos,
+        radius * sin,
+        libm::sqrtf((1.0 - sample.x).max(0.0)),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rng_uniform() {
+        let mut rng = Rng::new(7);
+        let count = 10000;
+        let mean = (0..count).map(|_| rng.next_f32()).sum::<f32>() / count as f32;
+        assert!((mean - 0.5).abs() < 0.01);
+        assert_ne!(Rng::new(1).next_u32(), Rng::new(2).next_u32());
+
+        let dir = cosine_hemisphere(rng.next_vec2());
+        assert!((dir.norm() - 1.0).abs() < 1e-5 && dir.z >= 0.0);
+    }
+}
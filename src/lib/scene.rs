@@ -0,0 +1,156 @@
+use alloc::vec;
+use alloc::vec::Vec;
+
+use crate::emitter::Emitters;
+use crate::entity::group::Group;
+use crate::environment::Environment;
+#[cfg(feature = "serde")]
+use crate::environment::EnvironmentData;
+use crate::light::Light;
+use crate::material::Material;
+use crate::medium::Medium;
+use crate::texture::Texture;
+use crate::utils::{
+    MaterialBuf, MaterialIdx, MediumBuf, MediumIdx, NormalBuf, NormalIdx, TextureBuf, TextureIdx,
+    UvBuf, UvIdx, Vec3Idx, VecBuf,
+};
+use crate::vec2::Vec2;
+use crate::vec3::Vec3;
+
+#[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Geometry {
+    pub vec_buf: VecBuf,
+    pub material_buf: MaterialBuf,
+    pub uv_buf: UvBuf,
+    // vertex normals for smooth shading
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub normal_buf: NormalBuf,
+    pub texture_buf: TextureBuf,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub medium_buf: MediumBuf,
+}
+
+impl Geometry {
+    pub fn push_vec(&mut self, vec: Vec3) -> Vec3Idx {
+        self.vec_buf.push(vec)
+    }
+
+    pub fn push_material(&mut self, material: Material) -> MaterialIdx {
+        self.material_buf.push(material)
+    }
+
+    pub fn push_uv(&mut self, uv: Vec2) -> UvIdx {
+        self.uv_buf.push(uv)
+    }
+
+    pub fn push_normal(&mut self, normal: Vec3) -> NormalIdx {
+        self.normal_buf.push(normal)
+    }
+
+    pub fn push_texture(&mut self, texture: Texture) -> TextureIdx {
+        self.texture_buf.push(texture)
+    }
+
+    pub fn push_medium(&mut self, medium: Medium) -> MediumIdx {
+        self.medium_buf.push(medium)
+    }
+}
+
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(from = "SceneData"))]
+pub struct Scene {
+    geometry: Geometry,
+    root: Group,
+    lights: Vec<Light>,
+    // gathered from `root` by `Scene::new`
+    #[cfg_attr(feature = "serde", serde(skip_serializing))]
+    emitters: Emitters,
+    // fills the space outside of every dielectric
+    atmosphere: Option<MediumIdx>,
+    // replaces the background color, lighting the scene in the path tracer
+    environment: Option<Environment>,
+}
+
+// What a scene is loaded from, with the emitters and the environment tables rebuilt.
+#[cfg(feature = "serde")]
+#[derive(serde::Deserialize)]
+struct SceneData {
+    geometry: Geometry,
+    root: Group,
+    lights: Vec<Light>,
+    #[serde(default)]
+    atmosphere: Option<MediumIdx>,
+    #[serde(default)]
+    environment: Option<EnvironmentData>,
+}
+
+#[cfg(feature = "serde")]
+impl From<SceneData> for Scene {
+    fn from(data: SceneData) -> Self {
+        let textures = &data.geometry.texture_buf;
+        let environment = data
+            .environment
+            .map(|environment| environment.build(textures));
+        Scene::new(data.geometry, data.root, data.lights)
+            .with_surroundings(data.atmosphere, environment)
+    }
+}
+
+impl Scene {
+    pub fn new(geometry: Geometry, root: Group, lights: Vec<Light>) -> Scene {
+        let emitters = Emitters::gather(&geometry, &root);
+        Scene {
+            geometry,
+            root,
+            lights,
+            emitters,
+            atmosphere: None,
+            environment: None,
+        }
+    }
+
+    // Set by the builder, which checks that they refer into `geometry`.
+    pub(crate) fn with_surroundings(
+        self,
+        atmosphere: Option<MediumIdx>,
+        environment: Option<Environment>,
+    ) -> Scene {
+        Scene {
+            atmosphere,
+            environment,
+            ..self
+        }
+    }
+
+    pub fn geometry(&self) -> &Geometry {
+        &self.geometry
+    }
+
+    pub fn root(&self) -> &Group {
+        &self.root
+    }
+
+    pub fn lights(&self) -> &[Light] {
+        &self.lights
+    }
+
+    pub fn emitters(&self) -> &Emitters {
+        &self.emitters
+    }
+
+    pub fn atmosphere(&self) -> Option<MediumIdx> {
+        self.atmosphere
+    }
+
+    pub fn environment(&self) -> Option<&Environment> {
+        self.environment.as_ref()
+    }
+}
+
+impl Default for Scene {
+    fn default() -> Self {
+        Scene::new(Default::default(), Group::new(vec![]), vec![])
+    }
+}
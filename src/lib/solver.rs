@@ -0,0 +1,213 @@
+// Closed-form polynomial root finding after J. Schwarze, "Cubic and Quartic Roots" (Graphics Gems).
+// Coefficients are given from the constant term up, roots come back unsorted.
+
+const ZERO_EPSILON: f64 = 1e-9;
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Roots {
+    values: [f64; 4],
+    len: usize,
+}
+
+impl Roots {
+    fn push(&mut self, value: f64) {
+        self.values[self.len] = value;
+        self.len += 1;
+    }
+
+    pub fn as_slice(&self) -> &[f64] {
+        &self.values[..self.len]
+    }
+}
+
+fn is_zero(value: f64) -> bool {
+    value > -ZERO_EPSILON && value < ZERO_EPSILON
+}
+
+// c[0] + c[1] x + c[2] x^2 = 0
+pub fn solve_quadratic(c: [f64; 3]) -> Roots {
+    let mut roots = Roots::default();
+    if is_zero(c[2]) {
+        if !is_zero(c[1]) {
+            roots.push(-c[0] / c[1]);
+        }
+        return roots;
+    }
+
+    let p = c[1] / (2.0 * c[2]);
+    let q = c[0] / c[2];
+    let discriminant = p * p - q;
+
+    if is_zero(discriminant) {
+        roots.push(-p);
+    } else if discriminant > 0.0 {
+        let sqrt_d = libm::sqrt(discriminant);
+        roots.push(sqrt_d - p);
+        roots.push(-sqrt_d - p);
+    }
+    roots
+}
+
+// c[0] + c[1] x + c[2] x^2 + c[3] x^3 = 0
+pub fn solve_cubic(c: [f64; 4]) -> Roots {
+    if is_zero(c[3]) {
+        return solve_quadratic([c[0], c[1], c[2]]);
+    }
+
+    let a = c[2] / c[3];
+    let b = c[1] / c[3];
+    let c = c[0] / c[3];
+
+    // substitute x = y - a/3 to eliminate the quadric term: y^3 + 3py + 2q = 0
+    let sq_a = a * a;
+    let p = (-sq_a / 3.0 + b) / 3.0;
+    let q = (2.0 / 27.0 * a * sq_a - a * b / 3.0 + c) / 2.0;
+
+    let cb_p = p * p * p;
+    let discriminant = q * q + cb_p;
+
+    let mut roots = Roots::default();
+    if is_zero(discriminant) {
+        if is_zero(q) {
+            roots.push(0.0);
+        } else {
+            let u = libm::cbrt(-q);
+            roots.push(2.0 * u);
+            roots.push(-u);
+        }
+    } else if discriminant < 0.0 {
+        let phi = libm::acos(-q / libm::sqrt(-cb_p)) / 3.0;
+        let t = 2.0 * libm::sqrt(-p);
+        let third = core::f64::consts::PI / 3.0;
+        roots.push(t * libm::cos(phi));
+        roots.push(-t * libm::cos(phi + third));
+        roots.push(-t * libm::cos(phi - third));
+    } else {
+        let sqrt_d = libm::sqrt(discriminant);
+        roots.push(libm::cbrt(sqrt_d - q) - libm::cbrt(sqrt_d + q));
+    }
+
+    let sub = a / 3.0;
+    for idx in 0..roots.len {
+        roots.values[idx] -= sub;
+    }
+    roots
+}
+
+// c[0] + c[1] x + c[2] x^2 + c[3] x^3 + c[4] x^4 = 0
+pub fn solve_quartic(coeffs: [f64; 5]) -> Roots {
+    if is_zero(coeffs[4]) {
+        return solve_cubic([coeffs[0], coeffs[1], coeffs[2], coeffs[3]]);
+    }
+
+    let a = coeffs[3] / coeffs[4];
+    let b = coeffs[2] / coeffs[4];
+    let c = coeffs[1] / coeffs[4];
+    let d = coeffs[0] / coeffs[4];
+
+    // substitute x = y - a/4 to eliminate the cubic term: y^4 + py^2 + qy + r = 0
+    let sq_a = a * a;
+    let p = -3.0 / 8.0 * sq_a + b;
+    let q = sq_a * a / 8.0 - a * b / 2.0 + c;
+    let r = -3.0 / 256.0 * sq_a * sq_a + sq_a * b / 16.0 - a * c / 4.0 + d;
+
+    let mut roots = Roots::default();
+    if is_zero(r) {
+        // no absolute term: y(y^3 + py + q) = 0
+        solve_cubic([q, p, 0.0, 1.0])
+            .as_slice()
+            .iter()
+            .for_each(|root| roots.push(*root));
+        roots.push(0.0);
+    } else {
+        // take one root of the resolvent cubic and split into two quadrics
+        let z = solve_cubic([r * p / 2.0 - q * q / 8.0, -r, -p / 2.0, 1.0]).values[0];
+
+        let u = z * z - r;
+        let v = 2.0 * z - p;
+        let u = if is_zero(u) {
+            0.0
+        } else if u > 0.0 {
+            libm::sqrt(u)
+        } else {
+            return roots;
+        };
+        let v = if is_zero(v) {
+            0.0
+        } else if v > 0.0 {
+            libm::sqrt(v)
+        } else {
+            return roots;
+        };
+
+        let v = if q < 0.0 { -v } else { v };
+        for root in solve_quadratic([z - u, v, 1.0])
+            .as_slice()
+            .iter()
+            .chain(solve_quadratic([z + u, -v, 1.0]).as_slice())
+        {
+            roots.push(*root);
+        }
+    }
+
+    let sub = a / 4.0;
+    for idx in 0..roots.len {
+        roots.values[idx] = polish(&coeffs, roots.values[idx] - sub);
+    }
+    roots
+}
+
+// A couple of Newton steps on the original polynomial to recover precision lost above.
+fn polish(coeffs: &[f64; 5], mut x: f64) -> f64 {
+    for _ in 0..2 {
+        let (value, derivative) = coeffs
+            .iter()
+            .rev()
+            .fold((0.0, 0.0), |(value, derivative), coeff| {
+                (value * x + coeff, derivative * x + value)
+            });
+        if is_zero(derivative) {
+            break;
+        }
+        x -= value / derivative;
+    }
+    x
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sorted(roots: Roots) -> [f64; 4] {
+        let mut values = [f64::NAN; 4];
+        values[..roots.len].copy_from_slice(roots.as_slice());
+        values[..roots.len].sort_by(f64::total_cmp);
+        values
+    }
+
+    fn assert_roots(expected: &[f64], roots: Roots) {
+        assert_eq!(expected.len(), roots.len, "{roots:?}");
+        for (expected, actual) in expected.iter().zip(sorted(roots)) {
+            assert!((expected - actual).abs() < 1e-6, "{expected} != {actual}");
+        }
+    }
+
+    #[test]
+    fn test_solve_quadratic_and_cubic() {
+        assert_roots(&[-3.0, 2.0], solve_quadratic([-6.0, 1.0, 1.0]));
+        assert_roots(&[], solve_quadratic([1.0, 0.0, 1.0]));
+        // (x - 1)(x - 2)(x + 3)
+        assert_roots(&[-3.0, 1.0, 2.0], solve_cubic([6.0, -7.0, 0.0, 1.0]));
+    }
+
+    #[test]
+    fn test_solve_quartic() {
+        // (x - 1)(x + 2)(x - 3)(x + 0.5)
+        assert_roots(
+            &[-2.0, -0.5, 1.0, 3.0],
+            solve_quartic([3.0, 3.5, -6.0, -1.5, 1.0]),
+        );
+        // (x^2 + 1)(x - 2)(x - 4)
+        assert_roots(&[2.0, 4.0], solve_quartic([8.0, -6.0, 9.0, -6.0, 1.0]));
+    }
+}
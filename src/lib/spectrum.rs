@@ -0,0 +1,148 @@
+use crate::vec3::Vec3;
+
+// Visible range that wavelengths are sampled from, in nanometers.
+pub const MIN_WAVELENGTH: f32 = 380.0;
+pub const MAX_WAVELENGTH: f32 = 780.0;
+// Fraunhofer d line, where catalogs give the index of refraction of glasses.
+pub const D_LINE: f32 = 587.6;
+
+// Linear sRGB from CIE XYZ, for the D65 white point.
+const XYZ_TO_RGB: [[f32; 3]; 3] = [
+    [3.2406, -1.5372, -0.4986],
+    [-0.9689, 1.8758, 0.0415],
+    [0.0557, -0.2040, 1.0570],
+];
+// Integrals of the sRGB matching functions over the sampled range, so that a flat spectrum
+// comes out white.
+const RGB_INTEGRALS: [f32; 3] = [128.3627, 101.5486, 97.0496];
+
+// Index of refraction varying with the wavelength, which splits white light.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(
+    feature = "serde",
+    serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)
+)]
+pub enum Dispersion {
+    // a + b / λ², with λ in micrometers
+    Cauchy { a: f32, b: f32 },
+    // n² = 1 + Σ b λ² / (λ² - c), with λ in micrometers, as in glass catalogs
+    Sellmeier { b: [f32; 3], c: [f32; 3] },
+}
+
+impl Dispersion {
+    pub fn ior(&self, wavelength: f32) -> f32 {
+        let micrometers = wavelength * 1e-3;
+        let squared = micrometers * micrometers;
+        match self {
+            Dispersion::Cauchy { a, b } => a + b / squared,
+            Dispersion::Sellmeier { b, c } => {
+                let sum: f32 = (0..3).map(|i| b[i] * squared / (squared - c[i])).sum();
+                libm::sqrtf(1.0 + sum)
+            }
+        }
+    }
+}
+
+// Uniform over the visible range.
+pub fn sample_wavelength(sample: f32) -> f32 {
+    MIN_WAVELENGTH + sample * (MAX_WAVELENGTH - MIN_WAVELENGTH)
+}
+
+fn smoothstep(from: f32, to: f32, x: f32) -> f32 {
+    let t = ((x - from) / (to - from)).clamp(0.0, 1.0);
+    t * t * (3.0 - 2.0 * t)
+}
+
+// `color` as seen at `wavelength` in spectral mode, a gray level of its spectrum.
+pub fn spectral(color: &Vec3, wavelength: Option<f32>) -> Vec3 {
+    match wavelength {
+        Some(wavelength) => {
+            let value = upsample(color, wavelength);
+            Vec3::new(value, value, value)
+        }
+        None => *color,
+    }
+}
+
+// Value at `wavelength` of a smooth spectrum for an RGB color, blending a blue, a green and
+// a red band that add up to one. White stays flat and reflectances stay within [0, 1], and
+// the bands are placed so that colors come back within a few percent.
+pub fn upsample(color: &Vec3, wavelength: f32) -> f32 {
+    let blue = 1.0 - smoothstep(485.0, 505.0, wavelength);
+    let red = smoothstep(575.0, 600.0, wavelength);
+    color.x * red + color.y * (1.0 - blue - red) + color.z * blue
+}
+
+// Piecewise Gaussian fit of the CIE 1931 matching functions (Wyman, Sloan and Shirley 2013).
+fn xyz_matching(wavelength: f32) -> [f32; 3] {
+    let lobe = |mean: f32, below: f32, above: f32| {
+        let spread = if wavelength < mean { below } else { above };
+        let t = (wavelength - mean) / spread;
+        libm::expf(-0.5 * t * t)
+    };
+    [
+        1.056 * lobe(599.8, 37.9, 31.0) + 0.362 * lobe(442.0, 16.0, 26.7)
+            - 0.065 * lobe(501.1, 20.4, 26.2),
+        0.821 * lobe(568.8, 46.9, 40.5) + 0.286 * lobe(530.9, 16.3, 31.1),
+        1.217 * lobe(437.0, 11.8, 36.0) + 0.681 * lobe(459.0, 26.0, 13.8),
+    ]
+}
+
+// Linear sRGB estimate of a spectrum from its `value` at one `sample_wavelength`.
+pub fn to_rgb(value: f32, wavelength: f32) -> Vec3 {
+    let xyz = xyz_matching(wavelength);
+    let rgb = XYZ_TO_RGB.map(|row| row[0] * xyz[0] + row[1] * xyz[1] + row[2] * xyz[2]);
+    let scale = value * (MAX_WAVELENGTH - MIN_WAVELENGTH);
+    Vec3::new(
+        rgb[0] / RGB_INTEGRALS[0],
+        rgb[1] / RGB_INTEGRALS[1],
+        rgb[2] / RGB_INTEGRALS[2],
+    ) * scale
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_spectral_round_trip() {
+        let count = 4000;
+        let average = |color: Vec3| {
+            let sum: Vec3 = (0..count)
+                .map(|i| {
+                    let wavelength = sample_wavelength((i as f32 + 0.5) / count as f32);
+                    to_rgb(upsample(&color, wavelength), wavelength)
+                })
+                .sum();
+            sum * (1.0 / count as f32)
+        };
+        let close = |a: Vec3, b: Vec3, tolerance: f32| {
+            let diff = (a - b).map(libm::fabsf);
+            diff.x.max(diff.y).max(diff.z) < tolerance
+        };
+        assert!(close(
+            average(Vec3::new(1.0, 1.0, 1.0)),
+            Vec3::new(1.0, 1.0, 1.0),
+            1e-2
+        ));
+        let orange = Vec3::new(0.8, 0.5, 0.2);
+        assert!(close(average(orange), orange, 5e-2));
+    }
+
+    #[test]
+    fn test_dispersion() {
+        // N-BK7 from its Sellmeier coefficients, and a Cauchy fit of it
+        let bk7 = Dispersion::Sellmeier {
+            b: [1.039_612, 0.231_792_3, 1.010_469_5],
+            c: [0.006_000_7, 0.020_017_9, 103.560_65],
+        };
+        assert!((bk7.ior(D_LINE) - 1.5168).abs() < 1e-3);
+        assert!(bk7.ior(450.0) > bk7.ior(650.0));
+        let cauchy = Dispersion::Cauchy {
+            a: 1.5046,
+            b: 0.00420,
+        };
+        assert!((cauchy.ior(D_LINE) - 1.5168).abs() < 1e-3);
+    }
+}
@@ -0,0 +1,455 @@
+use alloc::vec::Vec;
+
+use crate::intersect::Hit;
+use crate::noise;
+use crate::vec2::Vec2;
+use crate::vec3::Vec3;
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum WrapMode {
+    #[default]
+    Repeat,
+    Mirror,
+    Clamp,
+}
+
+impl WrapMode {
+    fn apply(&self, idx: i64, len: u32) -> usize {
+        let len = len as i64;
+        let wrapped = match self {
+            WrapMode::Repeat => idx.rem_euclid(len),
+            WrapMode::Mirror => {
+                let idx = idx.rem_euclid(2 * len);
+                if idx < len {
+                    idx
+                } else {
+                    2 * len - 1 - idx
+                }
+            }
+            WrapMode::Clamp => idx.clamp(0, len - 1),
+        };
+        wrapped as usize
+    }
+}
+
+// Encoding of stored color values, textures always keep them linear.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum ColorSpace {
+    #[default]
+    Srgb,
+    Linear,
+}
+
+impl ColorSpace {
+    pub fn to_linear(&self, value: f32) -> f32 {
+        match self {
+            ColorSpace::Srgb if value <= 0.04045 => value / 12.92,
+            ColorSpace::Srgb => libm::powf((value + 0.055) / 1.055, 2.4),
+            ColorSpace::Linear => value,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum Filter {
+    Nearest,
+    // the full resolution level only, which aliases where the texture is minified
+    Bilinear,
+    // bilinear in the two mip levels around the requested level of detail, blended
+    #[default]
+    Trilinear,
+}
+
+#[derive(Debug, Clone)]
+struct MipLevel {
+    width: u32,
+    height: u32,
+    texels: Vec<Vec3>,
+}
+
+impl MipLevel {
+    // Box filtered half size level, odd edges fold their last texel in twice.
+    fn downsampled(&self) -> MipLevel {
+        let (width, height) = ((self.width / 2).max(1), (self.height / 2).max(1));
+        let texel = |x: u32, y: u32| {
+            self.texels[(y.min(self.height - 1) * self.width + x.min(self.width - 1)) as usize]
+        };
+        let texels = (0..height)
+            .flat_map(|y| (0..width).map(move |x| (x, y)))
+            .map(|(x, y)| {
+                let (x, y) = (x * 2, y * 2);
+                (texel(x, y) + texel(x + 1, y) + texel(x, y + 1) + texel(x + 1, y + 1)) * 0.25
+            })
+            .collect();
+        MipLevel {
+            width,
+            height,
+            texels,
+        }
+    }
+
+    fn texel(&self, x: i64, y: i64, wrap: WrapMode) -> Vec3 {
+        let x = wrap.apply(x, self.width);
+        let y = wrap.apply(y, self.height);
+        self.texels[y * self.width as usize + x]
+    }
+
+    fn bilinear(&self, uv: Vec2, wrap: WrapMode) -> Vec3 {
+        let x = uv.x * self.width as f32 - 0.5;
+        let y = (1.0 - uv.y) * self.height as f32 - 0.5;
+        let (x0, y0) = (libm::floorf(x), libm::floorf(y));
+        let (tx, ty) = (x - x0, y - y0);
+        let (x0, y0) = (x0 as i64, y0 as i64);
+
+        let top = self.texel(x0, y0, wrap) * (1.0 - tx) + self.texel(x0 + 1, y0, wrap) * tx;
+        let bottom =
+            self.texel(x0, y0 + 1, wrap) * (1.0 - tx) + self.texel(x0 + 1, y0 + 1, wrap) * tx;
+        top * (1.0 - ty) + bottom * ty
+    }
+}
+
+// RGB texture with a full mip chain. Texel rows go from the top of the image down, while
+// uv (0, 0) is its bottom left corner.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(into = "ImageData", try_from = "ImageData"))]
+pub struct ImageTexture {
+    levels: Vec<MipLevel>,
+    pub wrap: WrapMode,
+    pub filter: Filter,
+}
+
+impl ImageTexture {
+    // `None` unless the size is not zero and there is a texel for each pixel.
+    pub fn new(
+        width: u32,
+        height: u32,
+        texels: Vec<Vec3>,
+        wrap: WrapMode,
+        filter: Filter,
+    ) -> Option<ImageTexture> {
+        let count = (width as usize).checked_mul(height as usize);
+        if count == Some(0) || count != Some(texels.len()) {
+            return None;
+        }
+
+        let mut levels = Vec::from([MipLevel {
+            width,
+            height,
+            texels,
+        }]);
+        while let Some(last) = levels
+            .last()
+            .filter(|level| level.width > 1 || level.height > 1)
+        {
+            levels.push(last.downsampled());
+        }
+        Some(ImageTexture {
+            levels,
+            wrap,
+            filter,
+        })
+    }
+
+    pub fn width(&self) -> u32 {
+        self.levels[0].width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.levels[0].height
+    }
+
+    pub fn mip_levels(&self) -> usize {
+        self.levels.len()
+    }
+
+    // Level of detail for a footprint measured in uv units.
+    pub fn lod(&self, footprint: f32) -> f32 {
+        libm::log2f(footprint * self.width().max(self.height()) as f32)
+    }
+
+    // `lod` is the base two logarithm of the texel footprint, only `Trilinear` uses it.
+    pub fn sample(&self, uv: Vec2, lod: f32) -> Vec3 {
+        match self.filter {
+            Filter::Nearest => {
+                let level = &self.levels[0];
+                let x = libm::floorf(uv.x * level.width as f32) as i64;
+                let y = libm::floorf((1.0 - uv.y) * level.height as f32) as i64;
+                level.texel(x, y, self.wrap)
+            }
+            Filter::Bilinear => self.levels[0].bilinear(uv, self.wrap),
+            Filter::Trilinear => {
+                let lod = lod.clamp(0.0, (self.levels.len() - 1) as f32);
+                let fine = libm::floorf(lod) as usize;
+                let coarse = (fine + 1).min(self.levels.len() - 1);
+                let blend = lod - fine as f32;
+                self.levels[fine].bilinear(uv, self.wrap) * (1.0 - blend)
+                    + self.levels[coarse].bilinear(uv, self.wrap) * blend
+            }
+        }
+    }
+}
+
+// What an image texture is stored as, its mip chain rebuilt by `ImageTexture::new` when loaded.
+#[cfg(feature = "serde")]
+#[derive(serde::Serialize, serde::Deserialize)]
+struct ImageData {
+    width: u32,
+    height: u32,
+    texels: Vec<Vec3>,
+    wrap: WrapMode,
+    filter: Filter,
+}
+
+#[cfg(feature = "serde")]
+impl From<ImageTexture> for ImageData {
+    fn from(image: ImageTexture) -> Self {
+        let MipLevel {
+            width,
+            height,
+            texels,
+        } = image.levels.into_iter().next().unwrap();
+        ImageData {
+            width,
+            height,
+            texels,
+            wrap: image.wrap,
+            filter: image.filter,
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl TryFrom<ImageData> for ImageTexture {
+    type Error = &'static str;
+
+    fn try_from(data: ImageData) -> Result<Self, Self::Error> {
+        ImageTexture::new(data.width, data.height, data.texels, data.wrap, data.filter)
+            .ok_or("image texture needs one texel per pixel and a size other than zero")
+    }
+}
+
+// Where a procedural pattern is evaluated: the uv plane, or the hit point itself in world or
+// object (before any instance transform) space.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum TextureSpace {
+    #[default]
+    Uv,
+    World,
+    Object,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(
+    feature = "serde",
+    serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)
+)]
+pub enum Pattern {
+    // unit cells, in 2D in uv space
+    Checker,
+    // ramp along x (u) from 0 to 1
+    Gradient,
+    Perlin,
+    Simplex,
+    Fbm { octaves: u32 },
+    Turbulence { octaves: u32 },
+    // sine stripes along x, bent by turbulence
+    Marble { octaves: u32, distortion: f32 },
+    // unit spaced rings around the z axis, bent by noise
+    Wood { distortion: f32 },
+    // distance to the nearest cell point
+    Voronoi,
+}
+
+impl Pattern {
+    // Pattern value in [0, 1].
+    pub fn value(&self, point: &Vec3) -> f32 {
+        let value = match *self {
+            Pattern::Checker => {
+                let cell = libm::floorf(point.x) + libm::floorf(point.y) + libm::floorf(point.z);
+                if libm::fmodf(cell, 2.0) == 0.0 {
+                    0.0
+                } else {
+                    1.0
+                }
+            }
+            Pattern::Gradient => point.x,
+            Pattern::Perlin => 0.5 + 0.5 * noise::perlin(point),
+            Pattern::Simplex => 0.5 + 0.5 * noise::simplex(point),
+            Pattern::Fbm { octaves } => 0.5 + 0.5 * noise::fbm(point, octaves),
+            Pattern::Turbulence { octaves } => noise::turbulence(point, octaves),
+            Pattern::Marble {
+                octaves,
+                distortion,
+            } => {
+                let phase = point.x + distortion * noise::turbulence(point, octaves);
+                0.5 + 0.5 * libm::sinf(core::f32::consts::PI * phase)
+            }
+            Pattern::Wood { distortion } => {
+                let ring = libm::sqrtf(point.x * point.x + point.y * point.y)
+                    + distortion * noise::perlin(point);
+                ring - libm::floorf(ring)
+            }
+            Pattern::Voronoi => noise::voronoi(point),
+        };
+        value.clamp(0.0, 1.0)
+    }
+}
+
+// Pattern blending between two colors, evaluated at `scale` times the point in `space`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ProceduralTexture {
+    pub pattern: Pattern,
+    pub space: TextureSpace,
+    pub scale: f32,
+    pub colors: [Vec3; 2],
+}
+
+impl ProceduralTexture {
+    pub fn new(
+        pattern: Pattern,
+        space: TextureSpace,
+        scale: f32,
+        colors: [Vec3; 2],
+    ) -> ProceduralTexture {
+        ProceduralTexture {
+            pattern,
+            space,
+            scale,
+            colors,
+        }
+    }
+
+    pub fn sample(&self, hit: &Hit) -> Vec3 {
+        let point = match self.space {
+            TextureSpace::Uv => Vec3::new(hit.uv.x, hit.uv.y, 0.0),
+            TextureSpace::World => hit.point,
+            TextureSpace::Object => hit.object_point,
+        };
+        let t = self.pattern.value(&(point * self.scale));
+        self.colors[0] * (1.0 - t) + self.colors[1] * t
+    }
+}
+
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Texture {
+    Image(ImageTexture),
+    Procedural(ProceduralTexture),
+}
+
+impl Texture {
+    // `footprint` is the size of the pixel seen at `hit` in uv units, zero if it is unknown.
+    pub fn sample(&self, hit: &Hit, footprint: f32) -> Vec3 {
+        match self {
+            Texture::Image(image) => image.sample(hit.uv, image.lod(footprint)),
+            Texture::Procedural(procedural) => procedural.sample(hit),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec;
+
+    #[test]
+    fn test_texture_sampling() {
+        let black = Vec3::new(0.0, 0.0, 0.0);
+        let white = Vec3::new(1.0, 1.0, 1.0);
+        let texels = vec![white, black, black, white];
+        let texture =
+            ImageTexture::new(2, 2, texels.clone(), WrapMode::Repeat, Filter::Nearest).unwrap();
+
+        assert_eq!(2, texture.mip_levels());
+        assert_eq!(white, texture.sample(Vec2::new(0.25, 0.75), 0.0));
+        assert_eq!(black, texture.sample(Vec2::new(0.75, 0.75), 0.0));
+        assert_eq!(black, texture.sample(Vec2::new(-0.25, 0.75), 0.0));
+
+        let texture = ImageTexture::new(2, 2, texels, WrapMode::Clamp, Filter::Trilinear).unwrap();
+        // halfway between texel centers, and the averaged top level
+        let gray = Vec3::new(0.5, 0.5, 0.5);
+        assert!((texture.sample(Vec2::new(0.5, 0.75), 0.0) - gray).norm() < 1e-6);
+        assert!((texture.sample(Vec2::new(0.1, 0.9), 1.0) - gray).norm() < 1e-6);
+        assert_eq!(white, texture.sample(Vec2::new(-3.0, 5.0), 0.0));
+        assert_eq!(1.0, texture.lod(1.0));
+
+        assert!((ColorSpace::Srgb.to_linear(0.5) - 0.214).abs() < 1e-3);
+        assert_eq!(1.0, ColorSpace::Srgb.to_linear(1.0));
+
+        let image = |width, height, texels: &[Vec3]| {
+            ImageTexture::new(
+                width,
+                height,
+                texels.to_vec(),
+                WrapMode::Repeat,
+                Filter::Nearest,
+            )
+        };
+        assert!(image(0, 0, &[]).is_none());
+        assert!(image(2, 1, &[white]).is_none());
+        assert!(image(u32::MAX, u32::MAX, &[white]).is_none());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_image_texture_serde() {
+        let texels = vec![Vec3::new(1.0, 0.0, 0.0); 8];
+        let texture = ImageTexture::new(4, 2, texels, WrapMode::Mirror, Filter::Trilinear).unwrap();
+        let json = serde_json::to_string(&texture).unwrap();
+        let restored: ImageTexture = serde_json::from_str(&json).unwrap();
+        assert_eq!(3, restored.mip_levels());
+        assert_eq!(WrapMode::Mirror, restored.wrap);
+
+        // loaded through `ImageTexture::new`, so an empty one is refused
+        let empty = r#"{"width":0,"height":0,"texels":[],"wrap":"repeat","filter":"nearest"}"#;
+        assert!(serde_json::from_str::<ImageTexture>(empty).is_err());
+    }
+
+    #[test]
+    fn test_procedural_patterns() {
+        let black = Vec3::new(0.0, 0.0, 0.0);
+        let white = Vec3::new(1.0, 1.0, 1.0);
+        let checker =
+            ProceduralTexture::new(Pattern::Checker, TextureSpace::World, 2.0, [black, white]);
+        let hit = |point| {
+            Hit::new(
+                point,
+                Vec3::new(0.0, 1.0, 0.0),
+                Vec2::new(0.0, 0.0),
+                Default::default(),
+            )
+        };
+        assert_eq!(black, checker.sample(&hit(Vec3::new(0.2, 0.2, 0.2))));
+        assert_eq!(white, checker.sample(&hit(Vec3::new(0.7, 0.2, 0.2))));
+        assert_eq!(white, checker.sample(&hit(Vec3::new(-0.2, 0.2, 0.2))));
+
+        let patterns = [
+            Pattern::Gradient,
+            Pattern::Simplex,
+            Pattern::Fbm { octaves: 5 },
+            Pattern::Marble {
+                octaves: 4,
+                distortion: 5.0,
+            },
+            Pattern::Wood { distortion: 0.3 },
+            Pattern::Voronoi,
+        ];
+        for pattern in patterns {
+            for idx in 0..100 {
+                let t = idx as f32 * 0.31;
+                assert!((0.0..=1.0).contains(&pattern.value(&Vec3::new(t, -t, 0.5 * t))));
+            }
+        }
+    }
+}
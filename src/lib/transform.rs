@@ -0,0 +1,73 @@
+use crate::mat4::Mat4;
+use crate::vec3::Vec3;
+
+#[derive(Debug, Copy, Clone, PartialEq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Transform {
+    pub matrix: Mat4,
+    pub inverse: Mat4,
+}
+
+impl Transform {
+    pub fn from_matrix(matrix: Mat4) -> Option<Transform> {
+        Some(Transform {
+            matrix,
+            inverse: matrix.inverse()?,
+        })
+    }
+
+    pub fn identity() -> Transform {
+        Default::default()
+    }
+
+    pub fn translation(offset: Vec3) -> Transform {
+        Transform {
+            matrix: Mat4::translation(offset),
+            inverse: Mat4::translation(-offset),
+        }
+    }
+
+    // `None` unless every axis is scaled by a finite factor other than zero.
+    pub fn scaling(scale: Vec3) -> Option<Transform> {
+        [scale.x, scale.y, scale.z].iter().all(|factor| factor.is_normal()).then(|| Transform {
+            matrix: Mat4::scaling(scale),
+            inverse: Mat4::scaling(Vec3::new(scale.x.recip(), scale.y.recip(), scale.z.recip())),
+        })
+    }
+
+    pub fn rotation(axis: Vec3, angle: f32) -> Transform {
+        let matrix = Mat4::rotation(axis, angle);
+        Transform {
+            matrix,
+            inverse: matrix.transpose(),
+        }
+    }
+
+    // Applies `self` first and `next` after it.
+    pub fn then(&self, next: &Transform) -> Transform {
+        Transform {
+            matrix: next.matrix * self.matrix,
+            inverse: self.inverse * next.inverse,
+        }
+    }
+
+    pub fn inverted(&self) -> Transform {
+        Transform {
+            matrix: self.inverse,
+            inverse: self.matrix,
+        }
+    }
+
+    pub fn point(&self, point: &Vec3) -> Vec3 {
+        self.matrix.transform_point(point)
+    }
+
+    pub fn vector(&self, vec: &Vec3) -> Vec3 {
+        self.matrix.transform_vector(vec)
+    }
+
+    // Normals go through the inverse transpose to stay perpendicular under non-uniform scaling.
+    pub fn normal(&self, normal: &Vec3) -> Vec3 {
+        self.inverse.transpose().transform_vector(normal).normalized()
+    }
+}
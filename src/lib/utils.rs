@@ -1,6 +1,9 @@
 use alloc::vec::Vec;
 use core::cmp::Ordering;
 use crate::material::Material;
+use crate::medium::Medium;
+use crate::texture::Texture;
+use crate::vec2::Vec2;
 use crate::vec3::Vec3;
 
 pub(crate) static EPSILON: f32 = 1e-3;
@@ -20,7 +23,7 @@ impl Eq for OrderedFloat32 {}
 
 impl PartialOrd for OrderedFloat32 {
     fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
-        self.val.partial_cmp(&other.val)
+        Some(self.cmp(other))
     }
 }
 
@@ -32,8 +35,13 @@ impl Ord for OrderedFloat32 {
 
 pub type Vec3Idx = u32;
 pub type MaterialIdx = u32;
+pub type UvIdx = u32;
+pub type NormalIdx = u32;
+pub type TextureIdx = u32;
+pub type MediumIdx = u32;
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct VecBuf {
     pub points: Vec<Vec3>,
 }
@@ -49,7 +57,8 @@ impl VecBuf {
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct MaterialBuf {
     pub materials: Vec<Material>,
 }
@@ -64,3 +73,71 @@ impl MaterialBuf {
         &self.materials[idx as usize]
     }
 }
+
+#[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct UvBuf {
+    pub uvs: Vec<Vec2>,
+}
+
+impl UvBuf {
+    pub fn push(&mut self, uv: Vec2) -> UvIdx {
+        self.uvs.push(uv);
+        (self.uvs.len() - 1) as UvIdx
+    }
+
+    pub fn load(&self, idx: UvIdx) -> &Vec2 {
+        &self.uvs[idx as usize]
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct NormalBuf {
+    pub normals: Vec<Vec3>,
+}
+
+impl NormalBuf {
+    pub fn push(&mut self, normal: Vec3) -> NormalIdx {
+        self.normals.push(normal);
+        (self.normals.len() - 1) as NormalIdx
+    }
+
+    pub fn load(&self, idx: NormalIdx) -> &Vec3 {
+        &self.normals[idx as usize]
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TextureBuf {
+    pub textures: Vec<Texture>,
+}
+
+impl TextureBuf {
+    pub fn push(&mut self, texture: Texture) -> TextureIdx {
+        self.textures.push(texture);
+        (self.textures.len() - 1) as TextureIdx
+    }
+
+    pub fn get(&self, idx: TextureIdx) -> Option<&Texture> {
+        self.textures.get(idx as usize)
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MediumBuf {
+    pub media: Vec<Medium>,
+}
+
+impl MediumBuf {
+    pub fn push(&mut self, medium: Medium) -> MediumIdx {
+        self.media.push(medium);
+        (self.media.len() - 1) as MediumIdx
+    }
+
+    pub fn get(&self, idx: MediumIdx) -> Option<&Medium> {
+        self.media.get(idx as usize)
+    }
+}
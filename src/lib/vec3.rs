@@ -3,6 +3,7 @@ use core::iter::Sum;
 use core::ops::{Add, Mul, Neg, Sub};
 
 #[derive(Debug, Copy, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Vec3 {
     pub x: f32,
     pub y: f32,
@@ -28,6 +29,10 @@ impl Vec3 {
         self.mul(self.norm().recip())
     }
 
+    pub fn map(&self, f: impl Fn(f32) -> f32) -> Vec3 {
+        Vec3::new(f(self.x), f(self.y), f(self.z))
+    }
+
     pub fn dist_observer(&self, rhs: &Vec3) -> f32 {
         let diff = *self - *rhs;
         dot_product(&diff, &diff)
@@ -82,6 +87,19 @@ impl Mul<f32> for Vec3 {
     }
 }
 
+// Component-wise, as used for colors.
+impl Mul<Vec3> for Vec3 {
+    type Output = Vec3;
+
+    fn mul(self, rhs: Vec3) -> Vec3 {
+        Vec3 {
+            x: self.x * rhs.x,
+            y: self.y * rhs.y,
+            z: self.z * rhs.z,
+        }
+    }
+}
+
 impl Sum<Vec3> for Vec3 {
     fn sum<I>(iter: I) -> Self
     where
@@ -103,6 +121,24 @@ pub fn cross_product(a: &Vec3, b: &Vec3) -> Vec3 {
     )
 }
 
+// Two unit vectors that form a right-handed basis with `normal` (Duff et al. 2017).
+pub fn orthonormal_basis(normal: &Vec3) -> (Vec3, Vec3) {
+    let sign = 1.0_f32.copysign(normal.z);
+    let a = -1.0 / (sign + normal.z);
+    let b = normal.x * normal.y * a;
+    (
+        Vec3::new(1.0 + sign * normal.x * normal.x * a, sign * b, -sign * normal.x),
+        Vec3::new(b, sign + normal.y * normal.y * a, -normal.y),
+    )
+}
+
+// Fails unless the vectors agree up to rounding, for tests.
+#[cfg(test)]
+#[track_caller]
+pub(crate) fn assert_near(expected: Vec3, actual: Vec3) {
+    assert!((expected - actual).norm() < 1e-5, "{expected:?} != {actual:?}");
+}
+
 #[cfg(test)]
 mod tests {
 